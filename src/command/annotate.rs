@@ -0,0 +1,60 @@
+//! Handles processing of user annotate commands
+
+use std::sync::Arc;
+use state;
+use prompt::{PromptType as PT, Prompt as P};
+use state::AnnotationTarget;
+use java_model::Annotation;
+use super::AnnotateObject;
+
+/// Called when the user issues an annotate command. Prompts for the object to annotate and the
+/// annotation, e.g. `SuppressWarnings("unchecked")`.
+pub fn annotate(state: Arc<state::State>, obj: AnnotateObject) {
+    let state_clone = state.clone();
+    let curr_sel = match *state.project.curr_sel.lock().unwrap() {
+        Some(ref sel) => sel.decl().map(|d| d.to_owned()),
+        None => None,
+    };
+    let mut prompts = vec![PT::Decl(P::new_exact("Class Name", false, curr_sel))];
+    match obj {
+        AnnotateObject::Class => (),
+        AnnotateObject::Member => prompts.push(PT::String(P::new("Member Name"))),
+        AnnotateObject::Param => {
+            prompts.push(PT::String(P::new("Method Name")));
+            prompts.push(PT::String(P::new("Param Name")));
+        }
+    }
+    prompts.push(PT::Annotation(P::new("Annotation")));
+
+    state::State::prompt(
+        state.clone(),
+        prompts,
+        Box::new(move |data| {
+            let annotation = match Annotation::parse(&data[data.len() - 1].val) {
+                Some(a) => a,
+                None => {
+                    println!("Bad annotation: {}", data[data.len() - 1].val);
+                    return;
+                }
+            };
+            let target = match obj {
+                AnnotateObject::Class => AnnotationTarget::Decl(data[0].val.clone()),
+                AnnotateObject::Member => {
+                    AnnotationTarget::Member(data[0].val.clone(), data[1].val.clone())
+                }
+                AnnotateObject::Param => AnnotationTarget::Param(
+                    data[0].val.clone(),
+                    data[1].val.clone(),
+                    data[2].val.clone(),
+                ),
+            };
+            if !state_clone.project.edit_annotations(
+                &target,
+                |a| a.push(annotation),
+            )
+            {
+                println!("Failed to find {:?}", target);
+            }
+        }),
+    );
+}
@@ -0,0 +1,193 @@
+//! The command line, for running named commands with arguments - e.g.
+//! `create-class com.foo Bar`. Arguments fill in the command's prompts in order, and the user is
+//! prompted for any which are missing.
+
+use std::sync::Arc;
+use state;
+use prompt::{PromptType as PT, Prompt as P, Completion, MAX_COMPLETIONS, to_completions};
+use search::SearchBuffer;
+use template::PlaceholderKind;
+use super::*;
+
+/// Split a command line into words. Words are separated by whitespace, unless the whitespace is
+/// in double quotes.
+pub fn split_args(line: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut curr = String::new();
+    let mut in_word = false;
+    let mut in_quotes = false;
+    for c in line.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                in_word = true;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if in_word {
+                    args.push(curr.clone());
+                    curr.clear();
+                    in_word = false;
+                }
+            }
+            c => {
+                curr.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        args.push(curr);
+    }
+    args
+}
+
+/// Get a prompt type for completing the given argument of a command. This mirrors the prompts
+/// opened by each command. Returns None if the command doesn't take that many arguments.
+fn arg_prompt(state: &state::State, cmd: &Command, ix: usize) -> Option<PT> {
+    let p = P::new("");
+    let arg_types: Vec<fn(P) -> PT> = match *cmd {
+        Command::Create(CreateCommand(CreateObject::Class)) => vec![PT::Package, PT::String],
+        Command::Create(CreateCommand(CreateObject::Package)) => vec![PT::Package],
+        Command::Create(CreateCommand(CreateObject::Field)) => {
+            vec![PT::Decl, PT::Decl, PT::String]
+        }
+        Command::Select(SelectCommand(SelectObject::Package)) => vec![PT::Package],
+        Command::Select(SelectCommand(SelectObject::Class)) => vec![PT::Decl],
+        Command::Select(SelectCommand(SelectObject::Symbol)) => vec![PT::Symbol],
+        Command::Select(SelectCommand(SelectObject::Theme)) => vec![PT::Theme],
+        Command::Refactor(RefactorCommand(RefactorKind::ChangeSignature)) => {
+            vec![PT::Decl, PT::String, PT::Decl, PT::String]
+        }
+        Command::Refactor(_) => vec![PT::Decl, PT::String],
+        Command::Doc(DocCommand(DocObject::Class)) => vec![PT::Decl, PT::MultiLine],
+        Command::Doc(DocCommand(DocObject::Package)) => vec![PT::Package, PT::MultiLine],
+        Command::Doc(DocCommand(DocObject::Member)) => {
+            vec![PT::Decl, PT::String, PT::MultiLine]
+        }
+        Command::Annotate(AnnotateCommand(AnnotateObject::Class)) => {
+            vec![PT::Decl, PT::Annotation]
+        }
+        Command::Annotate(AnnotateCommand(AnnotateObject::Member)) => {
+            vec![PT::Decl, PT::String, PT::Annotation]
+        }
+        Command::Annotate(AnnotateCommand(AnnotateObject::Param)) => {
+            vec![PT::Decl, PT::String, PT::String, PT::Annotation]
+        }
+        Command::Template(ref name) => {
+            match state.templates.iter().find(|t| &t.name == name) {
+                Some(t) => {
+                    t.placeholders
+                        .iter()
+                        .map(|p| match p.kind {
+                            PlaceholderKind::String => PT::String as fn(P) -> PT,
+                            PlaceholderKind::Package => PT::Package,
+                            PlaceholderKind::Decl => PT::Decl,
+                        })
+                        .collect()
+                }
+                None => Vec::new(),
+            }
+        }
+        Command::Macro(MacroCommand(MacroAction::Record)) => vec![PT::String],
+        Command::Macro(MacroCommand(MacroAction::Play)) => vec![PT::Macro],
+        Command::Source(_) => vec![PT::String],
+        Command::Pane(_) | Command::Navigate | Command::CommandLine | Command::Repeat => {
+            Vec::new()
+        }
+    };
+    arg_types.get(ix).map(|f| f(p))
+}
+
+/// Complete a command line. The last word is completed as a command name if it's the first word,
+/// otherwise as an argument of the command. Returns the whole completed command line.
+pub fn complete_command_line(state: Arc<state::State>, input: &str) -> Vec<Completion> {
+    let args = split_args(input);
+    let new_word = input.len() == 0 || input.ends_with(char::is_whitespace);
+    // The index of the word being completed, and the input up to that word
+    let (ix, prefix) = if new_word {
+        (args.len(), input)
+    } else {
+        let start = input.rfind(char::is_whitespace).map(|ix| ix + 1).unwrap_or(0);
+        (args.len() - 1, &input[..start])
+    };
+    let partial = if new_word { "" } else { args[ix].as_ref() };
+
+    let completions: Vec<Completion> = if ix == 0 {
+        let mut names = SearchBuffer::new();
+        names.add_strings(COMMAND_NAMES);
+        let template_names: Vec<String> = state
+            .templates
+            .iter()
+            .map(|t| format!("template:{}", t.name))
+            .collect();
+        names.add_strings_owned(&template_names[..]);
+        to_completions(names.find_ranked(partial, MAX_COMPLETIONS))
+    } else {
+        let cmd = match Command::from_name(&args[0]) {
+            Some(c) => c,
+            None => return Vec::new(),
+        };
+        match arg_prompt(&state, &cmd, ix - 1) {
+            Some(p) => p.complete(state.clone(), partial),
+            None => return Vec::new(),
+        }
+    };
+
+    completions
+        .into_iter()
+        .map(|c| {
+            let (val, offset) = if c.val.contains(char::is_whitespace) {
+                (format!("{}\"{}\"", prefix, c.val), prefix.len() + 1)
+            } else {
+                (prefix.to_owned() + &c.val, prefix.len())
+            };
+            Completion {
+                val: val,
+                positions: c.positions.into_iter().map(|p| p + offset).collect(),
+                detail: c.detail,
+            }
+        })
+        .collect()
+}
+
+/// Called when the user opens the command line. Prompts for a command line, then queues the
+/// named command with its arguments.
+pub fn command_line(state: Arc<state::State>) {
+    let state_clone = state.clone();
+    state::State::prompt(
+        state.clone(),
+        vec![PT::CommandLine(P::new(":"))],
+        Box::new(move |data| {
+            let mut args = split_args(&data[0].val);
+            if args.len() == 0 {
+                return;
+            }
+            let name = args.remove(0);
+            match Command::from_name(&name) {
+                Some(cmd) => {
+                    *state_clone.pending_args.lock().unwrap() = Some(args);
+                    state_clone.command_buffer.lock().unwrap().push_cmd(cmd);
+                }
+                None => println!("Unknown command: {}", name),
+            }
+        }),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split() {
+        assert_eq!(
+            split_args("  rename com.foo.Bar  Baz "),
+            vec!["rename", "com.foo.Bar", "Baz"]
+        );
+        assert_eq!(
+            split_args("change-signature a.B foo int \"String x, int y\""),
+            vec!["change-signature", "a.B", "foo", "int", "String x, int y"]
+        );
+        assert_eq!(split_args("doc-class a.B \"\""), vec!["doc-class", "a.B", ""]);
+    }
+}
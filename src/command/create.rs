@@ -25,7 +25,6 @@ pub fn create_class(state: Arc<state::State>) {
                 &data[0].val,
                 Declaration::Class(class),
             );
-            state_clone.project.regen_decl_completion_list();
         }),
     );
 }
@@ -51,8 +50,8 @@ pub fn create_field(state: Arc<state::State>) {
     let state_clone = state.clone();
     // If curr sel is some and is a package, then fill that in
     let curr_sel = match *state.project.curr_sel.lock().unwrap() {
-        Some(Selection::Decl(ref val)) => Some(val.clone()),
-        _ => None,
+        Some(ref sel) => sel.decl().map(|d| d.to_owned()),
+        None => None,
     };
     state::State::prompt(
         state.clone(),
@@ -68,6 +67,8 @@ pub fn create_field(state: Arc<state::State>) {
                     modifiers: Vec::new(),
                     field_type: data[1].val.clone(),
                     name: data[2].val.clone(),
+                    doc: None,
+                    annotations: Vec::new(),
                 },
             ).is_err() {
                 println!("Decl not found: {}", data[0].val);
@@ -0,0 +1,93 @@
+//! Handles processing of user doc commands
+
+use std::sync::Arc;
+use state;
+use prompt::{PromptType as PT, Prompt as P};
+use state::{Selection, DocTarget};
+use java_model::DocComment;
+use super::DocObject;
+
+/// Prompt for the doc text of the given target, pre-filled with its current doc. An empty doc
+/// removes the doc comment.
+fn prompt_doc_text(state: Arc<state::State>, target: DocTarget) {
+    let mut curr_doc = None;
+    if !state.project.edit_doc(&target, |d| curr_doc = d.clone()) {
+        println!("Failed to find {:?}", target);
+        return;
+    }
+    let state_clone = state.clone();
+    state::State::prompt(
+        state.clone(),
+        vec![
+            PT::MultiLine(P::new_exact(
+                "Doc",
+                true,
+                Some(curr_doc.map(|d| d.to_text()).unwrap_or_default()),
+            )),
+        ],
+        Box::new(move |data| {
+            let doc = if data[0].val.trim().len() == 0 {
+                None
+            } else {
+                Some(DocComment::parse(&data[0].val))
+            };
+            if !state_clone.project.edit_doc(&target, |d| *d = doc) {
+                println!("Failed to find {:?}", target);
+            }
+        }),
+    );
+}
+
+/// Called when the user issues a doc command. Prompts for the object to document, then prompts
+/// for the doc text.
+pub fn edit_doc(state: Arc<state::State>, obj: DocObject) {
+    let curr_sel = state.project.curr_sel.lock().unwrap().clone();
+    let state_clone = state.clone();
+    match obj {
+        DocObject::Package => {
+            let curr_sel = match curr_sel {
+                Some(Selection::Package(p)) => Some(p),
+                _ => None,
+            };
+            state::State::prompt(
+                state.clone(),
+                vec![PT::Package(P::new_exact("Package Name", false, curr_sel))],
+                Box::new(move |data| {
+                    prompt_doc_text(state_clone.clone(), DocTarget::Package(data[0].val.clone()))
+                }),
+            );
+        }
+        DocObject::Class => {
+            let curr_sel = match curr_sel {
+                Some(ref sel) => sel.decl().map(|d| d.to_owned()),
+                None => None,
+            };
+            state::State::prompt(
+                state.clone(),
+                vec![PT::Decl(P::new_exact("Class Name", false, curr_sel))],
+                Box::new(move |data| {
+                    prompt_doc_text(state_clone.clone(), DocTarget::Decl(data[0].val.clone()))
+                }),
+            );
+        }
+        DocObject::Member => {
+            let curr_sel = match curr_sel {
+                Some(ref sel) => sel.decl().map(|d| d.to_owned()),
+                None => None,
+            };
+            state::State::prompt(
+                state.clone(),
+                vec![
+                    PT::Decl(P::new_exact("Class Name", false, curr_sel)),
+                    PT::String(P::new("Member Name")),
+                ],
+                Box::new(move |data| {
+                    prompt_doc_text(
+                        state_clone.clone(),
+                        DocTarget::Member(data[0].val.clone(), data[1].val.clone()),
+                    )
+                }),
+            );
+        }
+    }
+}
@@ -1,52 +1,24 @@
 //! An implementation of a trie for holding the current input.
 
 use smallvec::SmallVec;
-use winit::VirtualKeyCode as VKC;
 use super::{InputChunk, Command};
+use input::parse_key_seq;
 use std::fmt;
 use std::error::Error;
 
-/// Convert a char to keycode if possible.
-fn char_to_keycode(c: char) -> Option<VKC> {
-    match c {
-        'a' | 'A' => Some(VKC::A),
-        'b' | 'B' => Some(VKC::B),
-        'c' | 'C' => Some(VKC::C),
-        'd' | 'D' => Some(VKC::D),
-        'e' | 'E' => Some(VKC::E),
-        'f' | 'F' => Some(VKC::F),
-        'g' | 'G' => Some(VKC::G),
-        'h' | 'H' => Some(VKC::H),
-        'i' | 'I' => Some(VKC::I),
-        'j' | 'J' => Some(VKC::J),
-        'k' | 'K' => Some(VKC::K),
-        'l' | 'L' => Some(VKC::L),
-        'm' | 'M' => Some(VKC::M),
-        'n' | 'N' => Some(VKC::N),
-        'o' | 'O' => Some(VKC::O),
-        'p' | 'P' => Some(VKC::P),
-        'q' | 'Q' => Some(VKC::Q),
-        'r' | 'R' => Some(VKC::R),
-        's' | 'S' => Some(VKC::S),
-        't' | 'T' => Some(VKC::T),
-        'u' | 'U' => Some(VKC::U),
-        'v' | 'V' => Some(VKC::V),
-        'w' | 'W' => Some(VKC::W),
-        'x' | 'X' => Some(VKC::X),
-        'y' | 'Y' => Some(VKC::Y),
-        'z' | 'Z' => Some(VKC::Z),
-        _ => None,
-    }
-}
-
 pub type NodeRef = usize;
 
+/// The display name of a prefix of commands from more than one group
+const MIXED_PREFIX_NAME: &'static str = "+prefix";
+
 #[derive(Clone, Debug)]
 struct TrieNode {
     /// The input for this node.
     input: InputChunk,
 
-    /// A short display name for this node - useful for GUI.
+    /// A short display name for this node - useful for GUI. For leaves, this is the name of the
+    /// command. For other nodes, this is the group of the commands under it, e.g. 'create', or
+    /// MIXED_PREFIX_NAME if they're from different groups.
     display_name: SmallVec<[char; 16]>,
 
     /// This node's children
@@ -107,99 +79,151 @@ impl InputTrie {
         self.trie_nodes[r].command.as_ref()
     }
 
-    /// Convenience method to map a string to a command. Will panic if string contains a
-    /// non-alphabetic char.
-    pub fn add_cmd_str(&mut self, cmd_str: &str, cmd: Command) -> Result<(), AddCommandError> {
-        // Loop over all the chars and convert into input chunks
-        let input_chunks: Vec<InputChunk> = cmd_str
-            .chars()
-            .map(|c| {
-                InputChunk(
-                    char_to_keycode(c).unwrap(),
-                    if c.is_uppercase() { 0b1000 } else { 0b0000 },
-                )
-            })
-            .collect();
+    /// Panics if ref invalid
+    pub fn get_display_name(&self, r: NodeRef) -> String {
+        self.trie_nodes[r].display_name.iter().collect()
+    }
 
-        if input_chunks.len() == 0 {
-            return Err(AddCommandError::CommandEmpty);
+    /// Get the children of the given node, or the root nodes if None.
+    pub fn get_children(&self, r: Option<NodeRef>) -> &[NodeRef] {
+        match r {
+            Some(r) => &self.trie_nodes[r].children[..],
+            None => &self.roots[..],
         }
+    }
 
-        // Special case for root nodes
-        let mut curr_node = None;
-        for c in &self.roots {
-            let n = &mut self.trie_nodes[*c];
-            if n.input == input_chunks[0] {
-                curr_node = Some(*c);
-                break;
-            }
+    /// Panics if ref invalid
+    pub fn get_input(&self, r: NodeRef) -> InputChunk {
+        self.trie_nodes[r].input
+    }
+
+    /// Convenience method to map a string to a command. See `input::parse_key_seq` for the
+    /// notation.
+    pub fn add_cmd_str(&mut self, cmd_str: &str, cmd: Command) -> Result<(), AddCommandError> {
+        match parse_key_seq(cmd_str) {
+            Ok(input_chunks) => self.add_cmd(&input_chunks[..], cmd),
+            Err(e) => Err(AddCommandError::BadKey(e)),
         }
-        if curr_node.is_none() {
-            self.trie_nodes.push(TrieNode {
-                input: input_chunks[0],
-                display_name: SmallVec::new(),
-                children: SmallVec::new(),
-                command: None,
-            });
-            self.roots.push(self.trie_nodes.len() - 1);
-            curr_node = Some(self.trie_nodes.len() - 1);
+    }
+
+    /// Map a sequence of input to a command. If the sequence is already mapped, the command is
+    /// replaced.
+    pub fn add_cmd(&mut self, input_chunks: &[InputChunk], cmd: Command) -> Result<(), AddCommandError> {
+        if input_chunks.len() == 0 {
+            return Err(AddCommandError::CommandEmpty);
         }
 
         // Trace down the trie until we get to a leaf, or until we need to create a new node.
-        let mut consumed = 1;
-        let mut curr_node = curr_node.unwrap();
-        'outer: for i in &input_chunks[1..] {
-            for c in &self.trie_nodes[curr_node].children {
-                if self.trie_nodes[*c].input == *i {
-                    curr_node = *c;
+        let mut curr_node: Option<NodeRef> = None;
+        let mut consumed = 0;
+        let mut prefixes: SmallVec<[NodeRef; 8]> = SmallVec::new();
+        for i in input_chunks {
+            match self.advance_node_ref(curr_node, *i) {
+                Some(n) => {
+                    curr_node = Some(n);
                     consumed += 1;
-                    continue 'outer;
+                    if consumed < input_chunks.len() {
+                        // A leaf in the middle of our sequence means it'd never be reached
+                        if self.get_node(n).command.is_some() {
+                            return Err(AddCommandError::CommandAlreadyPrefixed);
+                        }
+                        prefixes.push(n);
+                    }
                 }
+                None => break,
             }
-            // If we're here, there are no valid children - if this is a leaf, throw an error,
-            // otherwise break & we can add child nodes
-            if self.get_node(curr_node).command.is_some() {
-                return Err(AddCommandError::CommandAlreadyPrefixed);
+        }
+        if consumed == input_chunks.len() && self.get_node(curr_node.unwrap()).children.len() > 0 {
+            return Err(AddCommandError::CommandIsPrefix);
+        }
+
+        // A prefix shared with another group can't be named after either
+        for n in prefixes {
+            let node = self.get_node_mut(n);
+            if !node.display_name.iter().cloned().eq(cmd.group().chars()) {
+                node.display_name = MIXED_PREFIX_NAME.chars().collect();
             }
-            break;
         }
 
-        // Loop until 1 before the end, which will be a leaf
-        for i in &input_chunks[consumed..input_chunks.len() - 1] {
+        // The whole sequence already exists - replace the command, as it's a leaf
+        if consumed == input_chunks.len() {
+            let node = self.get_node_mut(curr_node.unwrap());
+            node.display_name = cmd.name().chars().collect();
+            node.command = Some(cmd);
+            return Ok(());
+        }
+
+        // Add the rest of the nodes, with the last being a leaf
+        for (ii, i) in input_chunks[consumed..].iter().enumerate() {
+            let is_leaf = consumed + ii == input_chunks.len() - 1;
+            let display_name = if is_leaf { cmd.name() } else { cmd.group().to_owned() };
             self.trie_nodes.push(TrieNode {
                 input: *i,
-                display_name: SmallVec::new(),
+                display_name: display_name.chars().collect(),
                 children: SmallVec::new(),
-                command: None,
+                command: if is_leaf { Some(cmd.clone()) } else { None },
             });
             let last_ix = self.trie_nodes.len() - 1;
-            self.trie_nodes[curr_node].children.push(last_ix);
-            curr_node = self.trie_nodes.len() - 1;
+            match curr_node {
+                Some(n) => self.trie_nodes[n].children.push(last_ix),
+                None => self.roots.push(last_ix),
+            }
+            curr_node = Some(last_ix);
         }
 
-        // At the end now, add the leaf
-        self.trie_nodes.push(TrieNode {
-            input: input_chunks[input_chunks.len() - 1],
-            display_name: SmallVec::new(),
-            children: SmallVec::new(),
-            command: Some(cmd),
-        });
-        let last_ix = self.trie_nodes.len() - 1;
-        self.trie_nodes[curr_node].children.push(last_ix);
-
         return Ok(());
     }
+
+    /// Remove the command mapped to the given input sequence, or all the commands starting with
+    /// it if it's a prefix. Returns false if nothing was mapped.
+    pub fn remove_cmd(&mut self, input_chunks: &[InputChunk]) -> bool {
+        // Trace the path down to the node to remove
+        let mut path: Vec<NodeRef> = Vec::new();
+        for i in input_chunks {
+            match self.advance_node_ref(path.last().cloned(), *i) {
+                Some(n) => path.push(n),
+                None => return false,
+            }
+        }
+        // Detach nodes from their parents, moving up whilst the parent is left with nothing
+        while let Some(n) = path.pop() {
+            match path.last() {
+                Some(&p) => self.trie_nodes[p].children.retain(|c| *c != n),
+                None => self.roots.retain(|c| *c != n),
+            }
+            match path.last() {
+                Some(&p) if self.trie_nodes[p].children.len() == 0 &&
+                    self.trie_nodes[p].command.is_none() => (),
+                _ => break,
+            }
+        }
+        return true;
+    }
 }
 
 #[derive(Debug)]
 pub enum AddCommandError {
+    /// A prefix of this command is already mapped to a command
     CommandAlreadyPrefixed,
+    /// This command is a prefix of other mapped commands
+    CommandIsPrefix,
     CommandEmpty,
+    /// The command string couldn't be parsed
+    BadKey(String),
 }
 
 impl fmt::Display for AddCommandError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{:?}", self)
+        match *self {
+            AddCommandError::CommandAlreadyPrefixed => {
+                write!(f, "A prefix of this key sequence is already bound")
+            }
+            AddCommandError::CommandIsPrefix => {
+                write!(f, "This key sequence is a prefix of other bindings")
+            }
+            AddCommandError::CommandEmpty => write!(f, "Empty key sequence"),
+            AddCommandError::BadKey(ref e) => write!(f, "{}", e),
+        }
     }
 }
 
@@ -212,7 +236,9 @@ impl Error for AddCommandError {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use input::CTRL;
     use command::{Command, CreateCommand, CreateObject};
+    use winit::VirtualKeyCode as VKC;
 
     #[test]
     pub fn test_str_cmd_insertion() {
@@ -257,4 +283,73 @@ mod tests {
             None
         );
     }
+
+    #[test]
+    pub fn test_prefix_conflicts() {
+        let mut input_trie = InputTrie::new();
+        input_trie
+            .add_cmd_str("cc", Command::Create(CreateCommand(CreateObject::Class)))
+            .unwrap();
+        match input_trie.add_cmd_str("c", Command::Create(CreateCommand(CreateObject::Field))) {
+            Err(AddCommandError::CommandIsPrefix) => (),
+            r => panic!("Expected CommandIsPrefix, got {:?}", r),
+        }
+        match input_trie.add_cmd_str("ccc", Command::Create(CreateCommand(CreateObject::Field))) {
+            Err(AddCommandError::CommandAlreadyPrefixed) => (),
+            r => panic!("Expected CommandAlreadyPrefixed, got {:?}", r),
+        }
+        // Rebinding replaces the command
+        input_trie
+            .add_cmd_str("cc", Command::Create(CreateCommand(CreateObject::Field)))
+            .unwrap();
+        assert_eq!(input_trie.trie_nodes.len(), 2);
+        match input_trie.get_cmd(1) {
+            Some(&Command::Create(CreateCommand(CreateObject::Field))) => (),
+            c => panic!("Expected create field, got {:?}", c),
+        }
+        // Chords
+        input_trie
+            .add_cmd_str("C-x C-f", Command::Create(CreateCommand(CreateObject::Class)))
+            .unwrap();
+        assert_eq!(
+            input_trie.advance_node_ref(None, InputChunk(VKC::X, CTRL)),
+            Some(2)
+        );
+    }
+
+    #[test]
+    pub fn test_remove_cmd() {
+        let mut input_trie = InputTrie::new();
+        input_trie
+            .add_cmd_str("abc", Command::Create(CreateCommand(CreateObject::Class)))
+            .unwrap();
+        input_trie
+            .add_cmd_str("cp", Command::Create(CreateCommand(CreateObject::Package)))
+            .unwrap();
+        assert!(input_trie.remove_cmd(&[InputChunk(VKC::A, 0), InputChunk(VKC::B, 0)]));
+        assert_eq!(input_trie.advance_node_ref(None, InputChunk(VKC::A, 0)), None);
+        assert!(!input_trie.remove_cmd(&[InputChunk(VKC::A, 0)]));
+        // Now 'a' is free
+        input_trie
+            .add_cmd_str("a", Command::Create(CreateCommand(CreateObject::Class)))
+            .unwrap();
+    }
+
+    #[test]
+    pub fn test_display_names() {
+        let mut input_trie = InputTrie::new();
+        input_trie
+            .add_cmd_str("cc", Command::Create(CreateCommand(CreateObject::Class)))
+            .unwrap();
+        assert_eq!(input_trie.get_display_name(0), "create");
+        assert_eq!(input_trie.get_display_name(1), "create-class");
+        assert_eq!(input_trie.get_children(Some(0)), &[1]);
+        assert_eq!(input_trie.get_children(None), &[0]);
+        input_trie
+            .add_cmd_str("cp", Command::Create(CreateCommand(CreateObject::Package)))
+            .unwrap();
+        assert_eq!(input_trie.get_display_name(0), "create");
+        input_trie.add_cmd_str("cn", Command::Navigate).unwrap();
+        assert_eq!(input_trie.get_display_name(0), MIXED_PREFIX_NAME);
+    }
 }
@@ -0,0 +1,104 @@
+//! Loading of user key bindings from the `keymap` config file.
+//!
+//! Each line binds a key sequence to a named command, or unbinds a key sequence (including any
+//! bindings it's a prefix of). Bindings are applied on top of the default bindings. See
+//! `input::parse_key_seq` for the key notation.
+//!
+//! ```text
+//! # Use C-x C-f to select a class
+//! C-x C-f select-class
+//! # Free up 'c' so it can be bound on its own
+//! unbind c
+//! c create-class
+//! F5 template:singleton
+//! ```
+
+use input::parse_key_seq;
+use super::Command;
+use super::input_trie::InputTrie;
+
+/// The name of the keymap file in the config directory
+pub const KEYMAP_FILE: &'static str = "keymap";
+
+/// An error in the keymap file, with the line number (starting at 1) it occurred on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeymapErr {
+    pub line: usize,
+    pub msg: String,
+}
+
+/// Apply the bindings in the given keymap source to a trie. Lines with errors are skipped, and an
+/// error is returned for each.
+pub fn apply_keymap(input_trie: &mut InputTrie, src: &str) -> Vec<KeymapErr> {
+    let mut errors = Vec::new();
+    for (ii, line) in src.lines().enumerate() {
+        let line = line.trim();
+        if line.len() == 0 || line.starts_with('#') {
+            continue;
+        }
+        let err = |msg: String| {
+            KeymapErr {
+                line: ii + 1,
+                msg: msg,
+            }
+        };
+        if line.starts_with("unbind ") {
+            match parse_key_seq(&line["unbind ".len()..]) {
+                Ok(keys) => {
+                    if !input_trie.remove_cmd(&keys[..]) {
+                        errors.push(err("Nothing bound to this key sequence".to_owned()));
+                    }
+                }
+                Err(e) => errors.push(err(e)),
+            }
+            continue;
+        }
+        let (keys, name) = match line.rfind(char::is_whitespace) {
+            Some(ix) => (&line[..ix], line[ix..].trim()),
+            None => {
+                errors.push(err("Expected a key sequence and a command".to_owned()));
+                continue;
+            }
+        };
+        let cmd = match Command::from_name(name) {
+            Some(c) => c,
+            None => {
+                errors.push(err(format!("Unknown command '{}'", name)));
+                continue;
+            }
+        };
+        if let Err(e) = input_trie.add_cmd_str(keys, cmd) {
+            errors.push(err(format!("{}", e)));
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use input::InputChunk;
+    use winit::VirtualKeyCode as VKC;
+
+    #[test]
+    fn apply() {
+        let mut input_trie = InputTrie::new();
+        input_trie
+            .add_cmd_str("cc", Command::from_name("create-class").unwrap())
+            .unwrap();
+        let errors = apply_keymap(
+            &mut input_trie,
+            "# Comment\n\nC-x C-f select-class\nc create-field\nbad-line\nq foo\nunbind c\n\
+             c create-field",
+        );
+        assert_eq!(
+            errors.iter().map(|e| e.line).collect::<Vec<_>>(),
+            vec![4, 5, 6]
+        );
+        let n = input_trie.advance_node_ref(None, InputChunk(VKC::C, 0)).unwrap();
+        match input_trie.get_cmd(n) {
+            Some(&Command::Create(_)) => (),
+            c => panic!("Expected create field, got {:?}", c),
+        }
+    }
+}
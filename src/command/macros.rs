@@ -0,0 +1,64 @@
+//! Handles processing of user macro commands
+
+use std::sync::Arc;
+use state;
+use prompt::{PromptType as PT, Prompt as P};
+use macros::{self, MacroRecorder};
+
+/// Called when the user issues a record macro command. If a macro is being recorded, this stops
+/// the recording and saves the macro. Otherwise, prompts for the name of the macro to record.
+pub fn record_macro(state: Arc<state::State>) {
+    let recorder = state.macro_recorder.lock().unwrap().take();
+    if let Some(recorder) = recorder {
+        let m = recorder.finish();
+        println!("Recorded macro {} ({} events)", m.name, m.events.len());
+        let mut all_macros = state.macros.lock().unwrap();
+        all_macros.retain(|other| other.name != m.name);
+        all_macros.push(m);
+        if let Err(e) = macros::save_macros(&all_macros[..]) {
+            println!("Failed to save macros: {}", e);
+        }
+        return;
+    }
+    let state_clone = state.clone();
+    state::State::prompt(
+        state.clone(),
+        vec![PT::String(P::new("Macro Name"))],
+        Box::new(move |data| {
+            let name = data[0].val.trim();
+            if name.len() == 0 {
+                println!("Macro name can't be empty");
+                return;
+            }
+            println!("Recording macro {}", name);
+            *state_clone.macro_recorder.lock().unwrap() = Some(MacroRecorder::new(name));
+        }),
+    );
+}
+
+/// Called when the user issues a play macro command. Prompts for the name of the macro, then
+/// queues its events to be played back.
+pub fn play_macro(state: Arc<state::State>) {
+    let state_clone = state.clone();
+    state::State::prompt(
+        state.clone(),
+        vec![PT::Macro(P::new("Macro Name"))],
+        Box::new(move |data| {
+            let events = match state_clone.macros.lock().unwrap().iter().find(
+                |m| m.name == data[0].val,
+            ) {
+                Some(m) => m.events.clone(),
+                None => {
+                    println!("No macro named {}", data[0].val);
+                    return;
+                }
+            };
+            // Play these events before any which are already queued, since this may be called
+            // from another macro
+            let mut playback = state_clone.macro_playback.lock().unwrap();
+            for e in events.into_iter().rev() {
+                playback.push_front(e);
+            }
+        }),
+    );
+}
@@ -9,12 +9,36 @@
 
 mod create;
 mod select;
+mod refactor;
+mod template;
+mod doc;
+mod annotate;
+mod command_line;
+mod macros;
+mod navigate;
+mod pane;
+mod source;
 
 pub use self::create::*;
 pub use self::select::*;
+pub use self::refactor::*;
+pub use self::template::*;
+pub use self::doc::*;
+pub use self::annotate::*;
+pub use self::command_line::*;
+pub use self::macros::*;
+pub use self::navigate::*;
+pub use self::pane::*;
+pub use self::source::*;
 
 mod input_trie;
+mod keymap;
 use input::*;
+use config;
+use std::cmp;
+use std::fs;
+use std::mem;
+use std::time::{Duration, Instant, SystemTime};
 
 /// The noun for the create command - what object are we creating?
 #[derive(Clone, Debug)]
@@ -29,6 +53,61 @@ pub enum CreateObject {
 pub enum SelectObject {
     Class,
     Package,
+    /// Any package, decl or member
+    Symbol,
+    /// One of the built-in themes
+    Theme,
+}
+
+/// The noun for the doc command - what object are we documenting?
+#[derive(Clone, Debug)]
+pub enum DocObject {
+    Class,
+    Package,
+    Member,
+}
+
+/// The noun for the annotate command - what object are we annotating?
+#[derive(Clone, Debug)]
+pub enum AnnotateObject {
+    Class,
+    Member,
+    Param,
+}
+
+/// What to do with a keyboard macro
+#[derive(Clone, Debug)]
+pub enum MacroAction {
+    /// Start recording a macro, or stop if one is being recorded
+    Record,
+    Play,
+}
+
+/// What to do with the panes of the layout
+#[derive(Clone, Debug)]
+pub enum PaneAction {
+    FocusNext,
+    FocusPrev,
+    /// Grow the focused pane within its split
+    Grow,
+    Shrink,
+}
+
+/// Whether to import or export java source
+#[derive(Clone, Debug)]
+pub enum SourceAction {
+    Import,
+    Export,
+}
+
+/// The kind of refactoring to perform
+#[derive(Clone, Debug)]
+pub enum RefactorKind {
+    PullUp,
+    PushDown,
+    EncapsulateField,
+    ChangeSignature,
+    Rename,
 }
 
 #[derive(Clone, Debug)]
@@ -37,13 +116,185 @@ pub struct CreateCommand(pub CreateObject);
 #[derive(Clone, Debug)]
 pub struct SelectCommand(pub SelectObject);
 
+#[derive(Clone, Debug)]
+pub struct RefactorCommand(pub RefactorKind);
+
+#[derive(Clone, Debug)]
+pub struct DocCommand(pub DocObject);
+
+#[derive(Clone, Debug)]
+pub struct AnnotateCommand(pub AnnotateObject);
+
+#[derive(Clone, Debug)]
+pub struct MacroCommand(pub MacroAction);
+
+#[derive(Clone, Debug)]
+pub struct PaneCommand(pub PaneAction);
+
+#[derive(Clone, Debug)]
+pub struct SourceCommand(pub SourceAction);
+
 /// A command
 #[derive(Clone, Debug)]
 pub enum Command {
     Create(CreateCommand),
     Select(SelectCommand),
+    Refactor(RefactorCommand),
+    Doc(DocCommand),
+    Annotate(AnnotateCommand),
+    Macro(MacroCommand),
+    Pane(PaneCommand),
+    Source(SourceCommand),
+    /// Enter navigation mode, to move the selection through the package tree
+    Navigate,
+    /// Expand the user defined template with the given name
+    Template(String),
+    /// Open the command line, to run a named command
+    CommandLine,
+    /// Run the last complete command again, with the same prompt answers
+    Repeat,
 }
 
+/// The names of all the commands, as accepted by `Command::from_name`. Templates aren't
+/// included.
+pub const COMMAND_NAMES: &'static [&'static str] = &[
+    "create-class",
+    "create-package",
+    "create-field",
+    "select-package",
+    "select-class",
+    "select-symbol",
+    "select-theme",
+    "pull-up",
+    "push-down",
+    "encapsulate-field",
+    "change-signature",
+    "rename",
+    "doc-class",
+    "doc-package",
+    "doc-member",
+    "annotate-class",
+    "annotate-member",
+    "annotate-param",
+    "record-macro",
+    "play-macro",
+    "focus-next-pane",
+    "focus-prev-pane",
+    "grow-pane",
+    "shrink-pane",
+    "import-source",
+    "export-source",
+    "navigate",
+    "repeat",
+];
+
+impl Command {
+    /// Get a command from its name, as used in the keymap file - e.g. `create-class`. Templates
+    /// are named `template:<name>`.
+    pub fn from_name(name: &str) -> Option<Command> {
+        if name.starts_with("template:") {
+            return Some(Command::Template(name["template:".len()..].to_owned()));
+        }
+        let cmd = match name {
+            "create-class" => Command::Create(CreateCommand(CreateObject::Class)),
+            "create-package" => Command::Create(CreateCommand(CreateObject::Package)),
+            "create-field" => Command::Create(CreateCommand(CreateObject::Field)),
+            "select-package" => Command::Select(SelectCommand(SelectObject::Package)),
+            "select-class" => Command::Select(SelectCommand(SelectObject::Class)),
+            "select-symbol" => Command::Select(SelectCommand(SelectObject::Symbol)),
+            "select-theme" => Command::Select(SelectCommand(SelectObject::Theme)),
+            "pull-up" => Command::Refactor(RefactorCommand(RefactorKind::PullUp)),
+            "push-down" => Command::Refactor(RefactorCommand(RefactorKind::PushDown)),
+            "encapsulate-field" => {
+                Command::Refactor(RefactorCommand(RefactorKind::EncapsulateField))
+            }
+            "change-signature" => Command::Refactor(RefactorCommand(RefactorKind::ChangeSignature)),
+            "rename" => Command::Refactor(RefactorCommand(RefactorKind::Rename)),
+            "doc-class" => Command::Doc(DocCommand(DocObject::Class)),
+            "doc-package" => Command::Doc(DocCommand(DocObject::Package)),
+            "doc-member" => Command::Doc(DocCommand(DocObject::Member)),
+            "annotate-class" => Command::Annotate(AnnotateCommand(AnnotateObject::Class)),
+            "annotate-member" => Command::Annotate(AnnotateCommand(AnnotateObject::Member)),
+            "annotate-param" => Command::Annotate(AnnotateCommand(AnnotateObject::Param)),
+            "record-macro" => Command::Macro(MacroCommand(MacroAction::Record)),
+            "play-macro" => Command::Macro(MacroCommand(MacroAction::Play)),
+            "focus-next-pane" => Command::Pane(PaneCommand(PaneAction::FocusNext)),
+            "focus-prev-pane" => Command::Pane(PaneCommand(PaneAction::FocusPrev)),
+            "grow-pane" => Command::Pane(PaneCommand(PaneAction::Grow)),
+            "shrink-pane" => Command::Pane(PaneCommand(PaneAction::Shrink)),
+            "import-source" => Command::Source(SourceCommand(SourceAction::Import)),
+            "export-source" => Command::Source(SourceCommand(SourceAction::Export)),
+            "navigate" => Command::Navigate,
+            "repeat" => Command::Repeat,
+            _ => return None,
+        };
+        Some(cmd)
+    }
+
+    /// Get the name of this command, as accepted by `from_name`.
+    pub fn name(&self) -> String {
+        let name = match *self {
+            Command::Create(CreateCommand(CreateObject::Class)) => "create-class",
+            Command::Create(CreateCommand(CreateObject::Package)) => "create-package",
+            Command::Create(CreateCommand(CreateObject::Field)) => "create-field",
+            Command::Select(SelectCommand(SelectObject::Package)) => "select-package",
+            Command::Select(SelectCommand(SelectObject::Class)) => "select-class",
+            Command::Select(SelectCommand(SelectObject::Symbol)) => "select-symbol",
+            Command::Select(SelectCommand(SelectObject::Theme)) => "select-theme",
+            Command::Refactor(RefactorCommand(RefactorKind::PullUp)) => "pull-up",
+            Command::Refactor(RefactorCommand(RefactorKind::PushDown)) => "push-down",
+            Command::Refactor(RefactorCommand(RefactorKind::EncapsulateField)) => {
+                "encapsulate-field"
+            }
+            Command::Refactor(RefactorCommand(RefactorKind::ChangeSignature)) => "change-signature",
+            Command::Refactor(RefactorCommand(RefactorKind::Rename)) => "rename",
+            Command::Doc(DocCommand(DocObject::Class)) => "doc-class",
+            Command::Doc(DocCommand(DocObject::Package)) => "doc-package",
+            Command::Doc(DocCommand(DocObject::Member)) => "doc-member",
+            Command::Annotate(AnnotateCommand(AnnotateObject::Class)) => "annotate-class",
+            Command::Annotate(AnnotateCommand(AnnotateObject::Member)) => "annotate-member",
+            Command::Annotate(AnnotateCommand(AnnotateObject::Param)) => "annotate-param",
+            Command::Macro(MacroCommand(MacroAction::Record)) => "record-macro",
+            Command::Macro(MacroCommand(MacroAction::Play)) => "play-macro",
+            Command::Pane(PaneCommand(PaneAction::FocusNext)) => "focus-next-pane",
+            Command::Pane(PaneCommand(PaneAction::FocusPrev)) => "focus-prev-pane",
+            Command::Pane(PaneCommand(PaneAction::Grow)) => "grow-pane",
+            Command::Pane(PaneCommand(PaneAction::Shrink)) => "shrink-pane",
+            Command::Source(SourceCommand(SourceAction::Import)) => "import-source",
+            Command::Source(SourceCommand(SourceAction::Export)) => "export-source",
+            Command::Navigate => "navigate",
+            Command::Template(ref name) => return format!("template:{}", name),
+            Command::CommandLine => "command-line",
+            Command::Repeat => "repeat",
+        };
+        name.to_owned()
+    }
+
+    /// Get the name of the group of commands this belongs to, i.e. the verb.
+    pub fn group(&self) -> &'static str {
+        match *self {
+            Command::Create(_) => "create",
+            Command::Select(_) => "select",
+            Command::Refactor(_) => "refactor",
+            Command::Doc(_) => "doc",
+            Command::Annotate(_) => "annotate",
+            Command::Macro(_) => "macro",
+            Command::Pane(_) => "pane",
+            Command::Source(_) => "source",
+            Command::Navigate => "navigate",
+            Command::Template(_) => "template",
+            Command::CommandLine => "command-line",
+            Command::Repeat => "repeat",
+        }
+    }
+}
+
+/// How long to wait after a key in the middle of a command before showing what can follow.
+const CONTINUATION_DELAY_MS: u64 = 400;
+
+/// The largest count which can prefix a command
+const MAX_COUNT: usize = 999;
+
 /// Holds 2 butffers storing the user's currently entered command string, and also all the executed
 /// commands. When a command string is recognised, its characters are removed from the input buffer
 /// and the specific command is added to the command buffer.
@@ -58,6 +309,17 @@ pub struct CommandBuffer {
     input_trie: input_trie::InputTrie,
     /// The reference to the current node ref in the input tree (according to the input_buf)
     node_ref: Option<input_trie::NodeRef>,
+
+    /// Mappings added with add_mapping, which need to be re-added when the keymap is reloaded
+    extra_mappings: Vec<(String, Command)>,
+    /// The modified time of the keymap file when it was last loaded
+    keymap_mtime: Option<SystemTime>,
+    /// When we last checked whether the keymap file had changed
+    last_keymap_check: Instant,
+    /// When the last key was added to the input buffer
+    last_key_time: Instant,
+    /// The number of times to run the next command, if a count has been entered
+    count: Option<usize>,
 }
 
 impl CommandBuffer {
@@ -78,6 +340,72 @@ impl CommandBuffer {
         input_trie
             .add_cmd_str("sc", Command::Select(SelectCommand(SelectObject::Class)))
             .unwrap();
+        input_trie
+            .add_cmd_str("ss", Command::Select(SelectCommand(SelectObject::Symbol)))
+            .unwrap();
+        input_trie
+            .add_cmd_str("st", Command::Select(SelectCommand(SelectObject::Theme)))
+            .unwrap();
+        input_trie
+            .add_cmd_str("ru", Command::Refactor(RefactorCommand(RefactorKind::PullUp)))
+            .unwrap();
+        input_trie
+            .add_cmd_str("rd", Command::Refactor(RefactorCommand(RefactorKind::PushDown)))
+            .unwrap();
+        input_trie
+            .add_cmd_str(
+                "re",
+                Command::Refactor(RefactorCommand(RefactorKind::EncapsulateField)),
+            )
+            .unwrap();
+        input_trie
+            .add_cmd_str(
+                "rs",
+                Command::Refactor(RefactorCommand(RefactorKind::ChangeSignature)),
+            )
+            .unwrap();
+        input_trie
+            .add_cmd_str("rn", Command::Refactor(RefactorCommand(RefactorKind::Rename)))
+            .unwrap();
+        input_trie
+            .add_cmd_str("dc", Command::Doc(DocCommand(DocObject::Class)))
+            .unwrap();
+        input_trie
+            .add_cmd_str("dp", Command::Doc(DocCommand(DocObject::Package)))
+            .unwrap();
+        input_trie
+            .add_cmd_str("dm", Command::Doc(DocCommand(DocObject::Member)))
+            .unwrap();
+        input_trie
+            .add_cmd_str("ac", Command::Annotate(AnnotateCommand(AnnotateObject::Class)))
+            .unwrap();
+        input_trie
+            .add_cmd_str("am", Command::Annotate(AnnotateCommand(AnnotateObject::Member)))
+            .unwrap();
+        input_trie
+            .add_cmd_str("ap", Command::Annotate(AnnotateCommand(AnnotateObject::Param)))
+            .unwrap();
+        input_trie
+            .add_cmd_str("mr", Command::Macro(MacroCommand(MacroAction::Record)))
+            .unwrap();
+        input_trie
+            .add_cmd_str("mp", Command::Macro(MacroCommand(MacroAction::Play)))
+            .unwrap();
+        input_trie
+            .add_cmd_str("C-w w", Command::Pane(PaneCommand(PaneAction::FocusNext)))
+            .unwrap();
+        input_trie
+            .add_cmd_str("C-w W", Command::Pane(PaneCommand(PaneAction::FocusPrev)))
+            .unwrap();
+        input_trie
+            .add_cmd_str("C-w >", Command::Pane(PaneCommand(PaneAction::Grow)))
+            .unwrap();
+        input_trie
+            .add_cmd_str("C-w <", Command::Pane(PaneCommand(PaneAction::Shrink)))
+            .unwrap();
+        input_trie.add_cmd_str("n", Command::Navigate).unwrap();
+        input_trie.add_cmd_str(":", Command::CommandLine).unwrap();
+        input_trie.add_cmd_str(".", Command::Repeat).unwrap();
         return input_trie;
     }
 
@@ -87,44 +415,169 @@ impl CommandBuffer {
             cmd_buf: Vec::with_capacity(4),
             input_trie: CommandBuffer::init_mappings(),
             node_ref: None,
+            extra_mappings: Vec::new(),
+            keymap_mtime: None,
+            last_keymap_check: Instant::now(),
+            last_key_time: Instant::now(),
+            count: None,
+        }
+    }
+
+    /// Map a string to a command, in addition to the default mappings.
+    pub fn add_mapping(
+        &mut self,
+        cmd_str: &str,
+        cmd: Command,
+    ) -> Result<(), input_trie::AddCommandError> {
+        self.input_trie.add_cmd_str(cmd_str, cmd.clone())?;
+        self.extra_mappings.push((cmd_str.to_owned(), cmd));
+        Ok(())
+    }
+
+    /// Rebuild the mappings from the defaults, the mappings added with add_mapping, and the
+    /// keymap file. Errors in the keymap file are printed.
+    pub fn reload_keymap(&mut self) {
+        let mut input_trie = CommandBuffer::init_mappings();
+        for &(ref cmd_str, ref cmd) in &self.extra_mappings {
+            input_trie.add_cmd_str(cmd_str, cmd.clone()).unwrap();
+        }
+        self.keymap_mtime = fs::metadata(config::config_path(keymap::KEYMAP_FILE))
+            .and_then(|m| m.modified())
+            .ok();
+        if let Some(src) = config::read_config_file(keymap::KEYMAP_FILE) {
+            for e in keymap::apply_keymap(&mut input_trie, &src) {
+                println!("Error in keymap file, line {}: {}", e.line, e.msg);
+            }
+        }
+        self.input_trie = input_trie;
+        self.reset_input();
+    }
+
+    /// Reload the keymap if the keymap file has changed. This only checks the file at most once
+    /// a second, so can be called every frame.
+    pub fn poll_keymap(&mut self) {
+        if self.last_keymap_check.elapsed() < Duration::from_secs(1) {
+            return;
+        }
+        self.last_keymap_check = Instant::now();
+        let mtime = fs::metadata(config::config_path(keymap::KEYMAP_FILE))
+            .and_then(|m| m.modified())
+            .ok();
+        if mtime != self.keymap_mtime {
+            self.reload_keymap();
         }
     }
 
     pub fn is_empty(&self) -> bool {
-        self.input_buf.is_empty()
+        self.input_buf.is_empty() && self.count.is_none()
     }
 
     pub fn get_input_as_str(&self) -> String {
-        let mut input_str = String::from("");
+        let mut input_str = match self.count {
+            Some(c) => c.to_string(),
+            None => String::from(""),
+        };
+        // Chords & special keys are separated by spaces, e.g. `3cf` or `C-x c`
+        let mut prev_long = false;
         for i in &self.input_buf {
-            input_str += i.to_str();
+            let s = i.to_str();
+            let long = s.chars().count() > 1;
+            if (long || prev_long) && input_str.len() > 0 {
+                input_str += " ";
+            }
+            input_str += &s;
+            prev_long = long;
         }
         return input_str;
     }
 
-    /// Clear the current input buffer and reset the node_ref to None
+    /// Clear the current input buffer and count, and reset the node_ref to None. Commands which
+    /// have already been queued are kept.
     pub fn reset_input(&mut self) {
         self.node_ref = None;
         self.input_buf.clear();
+        self.count = None;
+    }
+
+    /// Reset the input, and drop any queued commands as well.
+    pub fn abort(&mut self) {
+        self.reset_input();
+        self.cmd_buf.clear();
     }
 
     /// Add a character to the command buffer. This should be called when user input is detected.
+    ///
+    /// Digits entered before a command are a count - the command is queued that many times. A
+    /// digit which starts a key sequence in the mappings is used for that instead.
     pub fn add_key(&mut self, input: InputChunk) {
+        self.last_key_time = Instant::now();
+        if self.node_ref.is_none() && self.input_trie.advance_node_ref(None, input).is_none() {
+            match (input.to_digit(), self.count) {
+                (Some(d), Some(c)) => {
+                    self.count = Some(cmp::min(c * 10 + d, MAX_COUNT));
+                    return;
+                }
+                (Some(d), None) if d > 0 => {
+                    self.count = Some(d);
+                    return;
+                }
+                _ => (),
+            }
+        }
         self.input_buf.push(input);
         self.node_ref = self.input_trie.advance_node_ref(self.node_ref, input);
         if self.node_ref.is_none() {
             self.input_buf.clear();
+            self.count = None;
             return;
         }
         let cmd = self.input_trie.get_cmd(self.node_ref.unwrap());
         if cmd.is_some() {
             self.node_ref = None;
             self.input_buf.clear();
-            self.cmd_buf.push(cmd.unwrap().clone());
+            for _ in 0..self.count.take().unwrap_or(1) {
+                self.cmd_buf.push(cmd.unwrap().clone());
+            }
             println!("Registered command: {:?}", cmd.unwrap());
         }
     }
 
+    /// Get the possible continuations of the current input, as pairs of keys & display names.
+    /// This is empty unless some input has been entered, and the user has paused for
+    /// CONTINUATION_DELAY_MS.
+    pub fn get_continuations(&self) -> Vec<(String, String)> {
+        let delay = Duration::from_millis(CONTINUATION_DELAY_MS);
+        if self.node_ref.is_none() || self.last_key_time.elapsed() < delay {
+            return Vec::new();
+        }
+        self.input_trie
+            .get_children(self.node_ref)
+            .iter()
+            .map(|c| {
+                (
+                    self.input_trie.get_input(*c).to_str(),
+                    self.input_trie.get_display_name(*c),
+                )
+            })
+            .collect()
+    }
+
+    /// Queue a command to be executed, as if its key sequence had been entered.
+    pub fn push_cmd(&mut self, cmd: Command) {
+        self.cmd_buf.push(cmd);
+    }
+
+    /// Remove all the queued commands, in the order they'd run.
+    pub fn take_cmds(&mut self) -> Vec<Command> {
+        mem::replace(&mut self.cmd_buf, Vec::new())
+    }
+
+    /// Queue commands to run before any which are already queued.
+    pub fn push_cmds_front(&mut self, cmds: Vec<Command>) {
+        let rest = mem::replace(&mut self.cmd_buf, cmds);
+        self.cmd_buf.extend(rest);
+    }
+
     /// Get a command if queued
     pub fn poll_cmd(&mut self) -> Option<Command> {
         if self.cmd_buf.len() == 0 {
@@ -134,3 +587,68 @@ impl CommandBuffer {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn add_str(command_buffer: &mut CommandBuffer, s: &str) {
+        for i in parse_key_seq(s).unwrap() {
+            command_buffer.add_key(i);
+        }
+    }
+
+    #[test]
+    fn test_count() {
+        let mut command_buffer = CommandBuffer::new();
+        add_str(&mut command_buffer, "3c");
+        assert_eq!(command_buffer.get_input_as_str(), "3c");
+        add_str(&mut command_buffer, "f");
+        for _ in 0..3 {
+            match command_buffer.poll_cmd() {
+                Some(Command::Create(CreateCommand(CreateObject::Field))) => (),
+                c => panic!("Expected create-field, got {:?}", c),
+            }
+        }
+        assert!(command_buffer.poll_cmd().is_none());
+
+        // Counts are capped, and reset on a bad key
+        add_str(&mut command_buffer, "123456x.");
+        assert!(command_buffer.poll_cmd().is_some());
+        assert!(command_buffer.poll_cmd().is_none());
+        add_str(&mut command_buffer, "99999.");
+        let mut n = 0;
+        while command_buffer.poll_cmd().is_some() {
+            n += 1;
+        }
+        assert_eq!(n, MAX_COUNT);
+
+        // Resetting the input keeps queued commands, but aborting drops them
+        add_str(&mut command_buffer, "2cf3c");
+        command_buffer.reset_input();
+        assert_eq!(command_buffer.get_input_as_str(), "");
+        assert!(command_buffer.poll_cmd().is_some());
+        add_str(&mut command_buffer, "3c");
+        command_buffer.abort();
+        assert_eq!(command_buffer.get_input_as_str(), "");
+        assert!(command_buffer.poll_cmd().is_none());
+    }
+
+    #[test]
+    fn test_digit_mapping() {
+        let mut command_buffer = CommandBuffer::new();
+        command_buffer.add_mapping("1", Command::Navigate).unwrap();
+        add_str(&mut command_buffer, "1");
+        match command_buffer.poll_cmd() {
+            Some(Command::Navigate) => (),
+            c => panic!("Expected navigate, got {:?}", c),
+        }
+        assert!(command_buffer.poll_cmd().is_none());
+
+        // Digits which aren't mapped are still a count
+        add_str(&mut command_buffer, "21");
+        assert!(command_buffer.poll_cmd().is_some());
+        assert!(command_buffer.poll_cmd().is_some());
+        assert!(command_buffer.poll_cmd().is_none());
+    }
+}
@@ -0,0 +1,48 @@
+//! Handles navigation of the package tree with the keyboard
+
+use std::sync::Arc;
+use state::{self, TreeMotion};
+use input::InputChunk;
+use winit::VirtualKeyCode as VKC;
+use std::collections::HashSet;
+
+/// Called when the user issues a navigate command. Enters navigation mode, where keys move the
+/// selection through the package tree until ESC is pressed.
+pub fn navigate(state: Arc<state::State>) {
+    *state.nav_mode.lock().unwrap() = true;
+    let collapsed = state.project.collapsed.lock().unwrap().clone();
+    let curr_sel = state.project.curr_sel.lock().unwrap().clone();
+    match curr_sel {
+        Some(sel) => state.project.reveal(&sel),
+        // Select the first item
+        None => state.project.navigate(TreeMotion::Next),
+    }
+    save_collapsed(&state, collapsed);
+}
+
+/// Save the collapsed packages if they've changed from `before`
+fn save_collapsed(state: &state::State, before: HashSet<String>) {
+    if *state.project.collapsed.lock().unwrap() == before {
+        return;
+    }
+    if let Err(e) = state.project.save_collapsed() {
+        println!("Failed to save collapsed packages: {}", e);
+    }
+}
+
+/// Handle a key in navigation mode while the package tree has focus. Returns false if the key
+/// isn't a navigation key, in which case it should be sent to the command buffer as usual.
+pub fn navigate_key(state: Arc<state::State>, i: InputChunk) -> bool {
+    let motion = match (i.0, i.1) {
+        (VKC::J, 0) | (VKC::Down, 0) => TreeMotion::Next,
+        (VKC::K, 0) | (VKC::Up, 0) => TreeMotion::Prev,
+        (VKC::H, 0) | (VKC::Left, 0) => TreeMotion::Parent,
+        (VKC::L, 0) | (VKC::Right, 0) => TreeMotion::FirstChild,
+        (VKC::Space, 0) | (VKC::Tab, 0) | (VKC::O, 0) => TreeMotion::Toggle,
+        _ => return false,
+    };
+    let collapsed = state.project.collapsed.lock().unwrap().clone();
+    state.project.navigate(motion);
+    save_collapsed(&state, collapsed);
+    true
+}
@@ -0,0 +1,24 @@
+//! Handles processing of user pane commands, which move the focus between the panes of the
+//! layout & resize them
+
+use std::sync::Arc;
+use state;
+use layout;
+use super::PaneAction;
+
+/// How much of its split a pane grows or shrinks by at a time
+const RESIZE_STEP: f32 = 0.05;
+
+/// Called when the user issues a pane command. The layout is saved for the project afterwards.
+pub fn pane_command(state: Arc<state::State>, action: PaneAction) {
+    let mut layout = state.layout.lock().unwrap();
+    match action {
+        PaneAction::FocusNext => layout.cycle_focus(true),
+        PaneAction::FocusPrev => layout.cycle_focus(false),
+        PaneAction::Grow => layout.resize_focused(RESIZE_STEP),
+        PaneAction::Shrink => layout.resize_focused(-RESIZE_STEP),
+    }
+    if let Err(e) = layout::save_layout(&layout) {
+        println!("Failed to save layout: {}", e);
+    }
+}
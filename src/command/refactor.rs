@@ -0,0 +1,150 @@
+//! Handles processing of user refactor commands
+
+use std::sync::Arc;
+use state;
+use prompt::{PromptType as PT, Prompt as P};
+use refactor;
+
+/// Get the currently selected decl, if any, to use as a prompt default.
+fn curr_sel_decl(state: &state::State) -> Option<String> {
+    match *state.project.curr_sel.lock().unwrap() {
+        Some(ref sel) => sel.decl().map(|d| d.to_owned()),
+        None => None,
+    }
+}
+
+/// Called when the user issues a pull up command. Prompts for a class & member, then moves the
+/// member to the class' superclass.
+pub fn pull_up_member(state: Arc<state::State>) {
+    let state_clone = state.clone();
+    let curr_sel = curr_sel_decl(&state);
+    state::State::prompt(
+        state.clone(),
+        vec![
+            PT::Decl(P::new_exact("Class Name", false, curr_sel)),
+            PT::String(P::new("Member Name")),
+        ],
+        Box::new(move |data| if let Err(e) =
+            state_clone.project.pull_up_member(
+                &data[0].val,
+                &data[1].val,
+            )
+        {
+            println!("Failed to pull up {}: {:?}", data[1].val, e);
+        }),
+    );
+}
+
+/// Called when the user issues a push down command. Prompts for a class & member, then moves the
+/// member into all of the class' subclasses.
+pub fn push_down_member(state: Arc<state::State>) {
+    let state_clone = state.clone();
+    let curr_sel = curr_sel_decl(&state);
+    state::State::prompt(
+        state.clone(),
+        vec![
+            PT::Decl(P::new_exact("Class Name", false, curr_sel)),
+            PT::String(P::new("Member Name")),
+        ],
+        Box::new(move |data| if let Err(e) =
+            state_clone.project.push_down_member(
+                &data[0].val,
+                &data[1].val,
+            )
+        {
+            println!("Failed to push down {}: {:?}", data[1].val, e);
+        }),
+    );
+}
+
+/// Called when the user issues an encapsulate field command. Prompts for a class & field, then
+/// makes the field private and generates accessors for it.
+pub fn encapsulate_field(state: Arc<state::State>) {
+    let state_clone = state.clone();
+    let curr_sel = curr_sel_decl(&state);
+    state::State::prompt(
+        state.clone(),
+        vec![
+            PT::Decl(P::new_exact("Class Name", false, curr_sel)),
+            PT::String(P::new("Field Name")),
+        ],
+        Box::new(move |data| if let Err(e) =
+            state_clone.project.encapsulate_field(
+                &data[0].val,
+                &data[1].val,
+            )
+        {
+            println!("Failed to encapsulate {}: {:?}", data[1].val, e);
+        }),
+    );
+}
+
+/// Called when the user issues a change signature command. Prompts for a class & method, then
+/// prompts for the new return type & params, pre-filled with the current signature.
+pub fn change_signature(state: Arc<state::State>) {
+    let state_clone = state.clone();
+    let curr_sel = curr_sel_decl(&state);
+    state::State::prompt(
+        state.clone(),
+        vec![
+            PT::Decl(P::new_exact("Class Name", false, curr_sel)),
+            PT::String(P::new("Method Name")),
+        ],
+        Box::new(move |data| {
+            let (class_name, method_name) = (data[0].val.clone(), data[1].val.clone());
+            let method = match state_clone.project.find_method(&class_name, &method_name) {
+                Ok(m) => m,
+                Err(e) => {
+                    println!("Failed to change signature of {}: {:?}", method_name, e);
+                    return;
+                }
+            };
+            let state_clone_2 = state_clone.clone();
+            state::State::prompt(
+                state_clone.clone(),
+                vec![
+                    PT::Decl(P::new_with_default("Return Type", &method.return_type)),
+                    PT::String(P::new_exact(
+                        "Params",
+                        true,
+                        Some(refactor::params_to_str(&method.params)),
+                    )),
+                ],
+                Box::new(move |data| {
+                    let res = refactor::parse_signature_change(&method, &data[0].val, &data[1].val)
+                        .and_then(|change| {
+                            state_clone_2.project.change_signature(
+                                &class_name,
+                                &method_name,
+                                &change,
+                            )
+                        });
+                    if let Err(e) = res {
+                        println!("Failed to change signature of {}: {:?}", method_name, e);
+                    }
+                }),
+            );
+        }),
+    );
+}
+
+/// Called when the user issues a rename command. Prompts for a class & its new name.
+pub fn rename_decl(state: Arc<state::State>) {
+    let state_clone = state.clone();
+    let curr_sel = curr_sel_decl(&state);
+    state::State::prompt(
+        state.clone(),
+        vec![
+            PT::Decl(P::new_exact("Class Name", false, curr_sel)),
+            PT::String(P::new("New Name")),
+        ],
+        Box::new(move |data| if let Err(e) =
+            state_clone.project.rename_decl(
+                &data[0].val,
+                &data[1].val,
+            )
+        {
+            println!("Failed to rename {}: {:?}", data[0].val, e);
+        }),
+    );
+}
@@ -42,3 +42,41 @@ pub fn select_decl(state: Arc<state::State>) {
         }),
     );
 }
+
+/// Called when the user issues a select symbol command. Prompts for any package, decl or member,
+/// and selects it.
+pub fn select_symbol(state: Arc<state::State>) {
+    let state_clone = state.clone();
+    state::State::prompt(
+        state.clone(),
+        vec![PT::Symbol(P::new_empty_allowed("Symbol"))],
+        Box::new(move |data| {
+            let (name, completion_match) = (&data[0].val, data[0].completion_match);
+            let sel = if completion_match {
+                state_clone.project.find_symbol(name)
+            } else {
+                None
+            };
+            if let Some(ref sel) = sel {
+                state_clone.project.reveal(sel);
+            }
+            *state_clone.project.curr_sel.lock().unwrap() = sel;
+        }),
+    );
+}
+
+/// Called when the user issues a select theme command. Prompts for the name of a built-in theme,
+/// and switches to it. Settings in the theme file still apply on top of it.
+pub fn select_theme(state: Arc<state::State>) {
+    let state_clone = state.clone();
+    state::State::prompt(
+        state.clone(),
+        vec![PT::Theme(P::new("Theme"))],
+        Box::new(move |data| {
+            let name = data[0].val.trim();
+            if !state_clone.theme.lock().unwrap().set_base(name) {
+                println!("No theme named {}", name);
+            }
+        }),
+    );
+}
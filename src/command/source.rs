@@ -0,0 +1,176 @@
+//! Handles importing & exporting the project as java source
+
+use std::sync::Arc;
+use std::path::Path;
+use state;
+use prompt::{PromptType as PT, Prompt as P};
+
+/// Called when the user issues an import source command. Prompts for a directory, then adds the
+/// classes & package docs in the .java files under it to the project.
+pub fn import_source(state: Arc<state::State>) {
+    let state_clone = state.clone();
+    state::State::prompt(
+        state.clone(),
+        vec![PT::String(P::new("Source Directory"))],
+        Box::new(move |data| {
+            match state_clone.project.import_source(Path::new(&data[0].val)) {
+                Ok(n) => println!("Imported {} files from {}", n, data[0].val),
+                Err(e) => println!("Failed to import source from {}: {}", data[0].val, e),
+            }
+        }),
+    );
+}
+
+/// Called when the user issues an export source command. Prompts for a directory, then writes
+/// the project's classes & package docs under it.
+pub fn export_source(state: Arc<state::State>) {
+    let state_clone = state.clone();
+    state::State::prompt(
+        state.clone(),
+        vec![PT::String(P::new("Source Directory"))],
+        Box::new(move |data| if let Err(e) =
+            state_clone.project.export_source(Path::new(&data[0].val))
+        {
+            println!("Failed to export source to {}: {}", data[0].val, e);
+        }),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use java_model::*;
+    use state::DocTarget;
+    use std::env;
+    use std::fs;
+    use std::io::Write;
+    use std::process;
+
+    #[test]
+    fn test_source_round_trip() {
+        let mut dir = env::temp_dir();
+        dir.push(format!("pearl-source-test-{}", process::id()));
+        let dir_str = dir.to_str().unwrap().to_owned();
+
+        let state = Arc::new(state::State::new());
+        let mut class = Class::new_with_name("Foo");
+        class.doc = Some(DocComment::parse("A foo, not a */ bar.\n\n@since 1.0"));
+        class.members.push(ClassMember::Method(Method {
+            modifiers: vec![Modifier::Public],
+            return_type: "void".to_owned(),
+            name: "run".to_owned(),
+            params: vec![
+                Param {
+                    param_type: "String".to_owned(),
+                    name: "s".to_owned(),
+                    annotations: Vec::new(),
+                },
+            ],
+            body: vec!["go(s);".to_owned()],
+            doc: Some(DocComment::parse("Runs.\n\n@param s The s")),
+            annotations: Vec::new(),
+        }));
+        state.project.add_decl("com.tom", Declaration::Class(class));
+        state.project.add_package("com.other");
+        state.project.edit_doc(
+            &DocTarget::Package("com".to_owned()),
+            |d| *d = Some(DocComment::parse("Everything.")),
+        );
+        *state.pending_args.lock().unwrap() = Some(vec![dir_str.clone()]);
+        export_source(state.clone());
+
+        let imported = Arc::new(state::State::new());
+        *imported.pending_args.lock().unwrap() = Some(vec![dir_str.clone()]);
+        import_source(imported.clone());
+        // Empty packages have no files, so aren't imported
+        imported.project.add_package("com.other");
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(imported.project.diagnostics.lock().unwrap().len(), 0);
+        assert_eq!(
+            *imported.project.package_list.lock().unwrap(),
+            *state.project.package_list.lock().unwrap()
+        );
+
+        // Importing the same classes again is a conflict
+        fs::create_dir_all(&dir).unwrap();
+        let foo = state.project.package_list.lock().unwrap()[0].find_decl("com.tom.Foo").cloned();
+        match foo {
+            Some(Declaration::Class(ref c)) => {
+                let mut f = fs::File::create(dir.join("Foo.java")).unwrap();
+                f.write_all(class_to_source("com.tom", c).as_bytes()).unwrap();
+            }
+            None => panic!("Class not found"),
+        }
+        *imported.pending_args.lock().unwrap() = Some(vec![dir_str]);
+        import_source(imported.clone());
+        let _ = fs::remove_dir_all(&dir);
+        let diagnostics = imported.project.diagnostics.lock().unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "Class already exists");
+    }
+
+    #[test]
+    fn test_import_annotations() {
+        let mut dir = env::temp_dir();
+        dir.push(format!("pearl-annotation-test-{}", process::id()));
+        let dir_str = dir.to_str().unwrap().to_owned();
+        fs::create_dir_all(&dir).unwrap();
+        let src = "package com.tom;\n\n\
+                   @Entity\n\
+                   @Table(name = \"foos\", indexes = {@Index(\"a\"), @Index(\"b\")})\n\
+                   public class Foo {\n\
+                   \x20   @Nullable private String name;\n\
+                   \n\
+                   \x20   @Override\n\
+                   \x20   @SuppressWarnings({\"unchecked\", \"rawtypes\"})\n\
+                   \x20   public void run(@Named(\"x, y\") String s, int n) {\n\
+                   \x20       go(s);\n\
+                   \x20   }\n\
+                   }\n";
+        let mut f = fs::File::create(dir.join("Foo.java")).unwrap();
+        f.write_all(src.as_bytes()).unwrap();
+
+        let state = Arc::new(state::State::new());
+        *state.pending_args.lock().unwrap() = Some(vec![dir_str.clone()]);
+        import_source(state.clone());
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(state.project.diagnostics.lock().unwrap().len(), 0);
+        let foo = state.project.package_list.lock().unwrap()[0].find_decl("com.tom.Foo").cloned();
+        let foo = match foo {
+            Some(Declaration::Class(c)) => c,
+            None => panic!("Class not imported"),
+        };
+        let sources = |a: &[Annotation]| a.iter().map(|a| a.to_source()).collect::<Vec<_>>();
+        assert_eq!(
+            sources(&foo.annotations),
+            vec![
+                "@Entity",
+                "@Table(name = \"foos\", indexes = {@Index(\"a\"), @Index(\"b\")})",
+            ]
+        );
+        assert_eq!(sources(foo.members[0].annotations()), vec!["@Nullable"]);
+        assert_eq!(
+            sources(foo.members[1].annotations()),
+            vec!["@Override", "@SuppressWarnings({\"unchecked\", \"rawtypes\"})"]
+        );
+        match foo.members[1] {
+            ClassMember::Method(ref m) => {
+                assert_eq!(sources(&m.params[0].annotations), vec!["@Named(\"x, y\")"]);
+                assert_eq!(m.params[1].annotations.len(), 0);
+            }
+            _ => panic!("Expected a method"),
+        }
+
+        // The annotations survive being exported & imported again
+        *state.pending_args.lock().unwrap() = Some(vec![dir_str.clone()]);
+        export_source(state.clone());
+        let imported = Arc::new(state::State::new());
+        *imported.pending_args.lock().unwrap() = Some(vec![dir_str]);
+        import_source(imported.clone());
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(
+            *imported.project.package_list.lock().unwrap(),
+            *state.project.package_list.lock().unwrap()
+        );
+    }
+}
@@ -0,0 +1,182 @@
+//! Handles expanding user defined templates
+
+use std::sync::Arc;
+use state;
+use prompt::{PromptType as PT, Prompt as P};
+use state::Selection;
+use template::{Template, PlaceholderKind, TemplateItem};
+use java_model::{Package, Declaration};
+
+/// Check that expanded template items can all be added to the project, so that a bad template
+/// doesn't leave it partly changed. Members must go into an existing decl, or one added by an
+/// earlier item, and no decl or member may have the same name as one which already exists or is
+/// added by an earlier item.
+fn check_items(package_list: &[Package], items: &[TemplateItem]) -> Result<(), String> {
+    let find_decl = |name: &str| package_list.iter().filter_map(|p| p.find_decl(name)).next();
+    let mut new_decls = Vec::new();
+    let mut new_members = Vec::new();
+    for i in items {
+        match *i {
+            TemplateItem::Decl(ref pkg, ref decl) => {
+                if pkg.len() == 0 {
+                    return Err(format!("No package given for {}", decl.name()));
+                }
+                let name = format!("{}.{}", pkg, decl.name());
+                if new_decls.contains(&name) || find_decl(&name).is_some() {
+                    return Err(format!("{} already exists", name));
+                }
+                new_decls.push(name);
+            }
+            TemplateItem::Member(ref decl, ref member) => {
+                let exists = match find_decl(decl) {
+                    Some(&Declaration::Class(ref c)) => c.find_member(member.name()).is_some(),
+                    None if new_decls.contains(decl) => false,
+                    None => return Err(format!("Decl not found: {}", decl)),
+                };
+                let name = (decl.clone(), member.name().to_owned());
+                if exists || new_members.contains(&name) {
+                    return Err(format!("{}.{} already exists", decl, member.name()));
+                }
+                new_members.push(name);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Expand a template with the given placeholder values & add the result to the project.
+fn apply_template(state: &state::State, template: &Template, values: &[String]) {
+    let items = match template.expand(values) {
+        Ok(items) => items,
+        Err(e) => {
+            println!(
+                "Failed to expand template {}, line {}: {}",
+                template.name,
+                e.line,
+                e.msg
+            );
+            return;
+        }
+    };
+    if let Err(e) = check_items(&state.project.package_list.lock().unwrap(), &items) {
+        println!("Failed to expand template {}: {}", template.name, e);
+        return;
+    }
+    for i in items {
+        match i {
+            TemplateItem::Decl(pkg, decl) => state.project.add_decl(&pkg, decl),
+            TemplateItem::Member(decl, member) => {
+                if state.project.add_decl_member(&decl, member).is_err() {
+                    println!("Decl not found: {}", decl);
+                }
+            }
+        }
+    }
+}
+
+/// Called when the user issues a template command. Prompts for each of the template's
+/// placeholders, then adds the expanded decls & members to the project.
+pub fn expand_template(state: Arc<state::State>, name: &str) {
+    let template = match state.templates.iter().find(|t| t.name == name) {
+        Some(t) => t.clone(),
+        None => {
+            println!("Template not found: {}", name);
+            return;
+        }
+    };
+    if template.placeholders.len() == 0 {
+        apply_template(&state, &template, &[]);
+        return;
+    }
+
+    let curr_sel = state.project.curr_sel.lock().unwrap().clone();
+    let prompts = template
+        .placeholders
+        .iter()
+        .map(|p| {
+            // Resolve '@sel' defaults to the current selection, if it's the right kind
+            let default = match (p.default.as_ref().map(|d| d.as_ref()), &p.kind, &curr_sel) {
+                (Some("@sel"), &PlaceholderKind::Package, &Some(Selection::Package(ref s))) => {
+                    Some(s.clone())
+                }
+                (Some("@sel"), &PlaceholderKind::Decl, &Some(ref sel)) => {
+                    sel.decl().map(|d| d.to_owned())
+                }
+                (Some("@sel"), _, _) => None,
+                (d, _, _) => d.map(|d| d.to_owned()),
+            };
+            let prompt = P::new_exact(&p.name, false, default);
+            match p.kind {
+                PlaceholderKind::String => PT::String(prompt),
+                PlaceholderKind::Package => PT::Package(prompt),
+                PlaceholderKind::Decl => PT::Decl(prompt),
+            }
+        })
+        .collect();
+
+    let state_clone = state.clone();
+    state::State::prompt(
+        state.clone(),
+        prompts,
+        Box::new(move |data| {
+            let values: Vec<String> = data.iter().map(|d| d.val.clone()).collect();
+            apply_template(&state_clone, &template, &values);
+        }),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use java_model::*;
+
+    #[test]
+    fn test_check_items() {
+        let (mut pkg, _) = Package::new("com");
+        pkg.decl_list.push(Declaration::Class(Class::new_with_name("Foo")));
+        let mut pkgs = vec![pkg];
+        let member = ClassMember::Field(Field {
+            modifiers: Vec::new(),
+            field_type: "int".to_owned(),
+            name: "x".to_owned(),
+            doc: None,
+            annotations: Vec::new(),
+        });
+        let new_decl = TemplateItem::Decl(
+            "com".to_owned(),
+            Declaration::Class(Class::new_with_name("Bar")),
+        );
+        let items = vec![
+            TemplateItem::Member("com.Foo".to_owned(), member.clone()),
+            new_decl.clone(),
+            TemplateItem::Member("com.Bar".to_owned(), member.clone()),
+        ];
+        assert!(check_items(&pkgs, &items).is_ok());
+        // Nothing's added if any member has nowhere to go
+        let items = vec![
+            TemplateItem::Member("com.Bar".to_owned(), member.clone()),
+            new_decl.clone(),
+        ];
+        assert!(check_items(&pkgs, &items).is_err());
+        let items = vec![
+            TemplateItem::Decl("".to_owned(), Declaration::Class(Class::new_with_name("Baz"))),
+        ];
+        assert!(check_items(&pkgs, &items).is_err());
+
+        // Names can't clash with what's already there, or what the template adds
+        let dup_member = TemplateItem::Member("com.Foo".to_owned(), member.clone());
+        assert!(check_items(&pkgs, &[dup_member.clone()]).is_ok());
+        assert!(check_items(&pkgs, &[dup_member.clone(), dup_member]).is_err());
+        let mut foo = Class::new_with_name("Foo");
+        foo.members.push(member.clone());
+        let items = vec![TemplateItem::Decl("com".to_owned(), Declaration::Class(foo))];
+        assert!(check_items(&pkgs, &items).is_err());
+        let items = vec![new_decl.clone(), new_decl];
+        assert!(check_items(&pkgs, &items).is_err());
+        if let Some(&mut Declaration::Class(ref mut c)) = pkgs[0].find_decl_mut("com.Foo") {
+            c.members.push(member.clone());
+        }
+        let items = vec![TemplateItem::Member("com.Foo".to_owned(), member)];
+        assert!(check_items(&pkgs, &items).is_err());
+    }
+}
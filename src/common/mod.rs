@@ -2,14 +2,84 @@
 
 use cgmath::Vector2;
 use winit::ModifiersState;
+use input::{Modifiers, SHIFT, CTRL, ALT, LOGO};
+use std::process::Command;
 
-/// Given a modifiersstate struct, convert that to a bitflag. See the command module for more
+/// Given a modifiersstate struct, convert that to a bitflag. See `input::Modifiers` for more
 /// details.
-pub fn mods_to_bitflags(ms: ModifiersState) -> u8 {
-    return (if ms.shift { 0b1000 } else { 0 }) + (if ms.ctrl { 0b0100 } else { 0 }) +
-        (if ms.alt { 0b0010 } else { 0 }) + (if ms.logo { 0b0001 } else { 0 });
+pub fn mods_to_bitflags(ms: ModifiersState) -> Modifiers {
+    (if ms.shift { SHIFT } else { 0 }) | (if ms.ctrl { CTRL } else { 0 }) |
+        (if ms.alt { ALT } else { 0 }) | (if ms.logo { LOGO } else { 0 })
 }
 
+/// Read text from the system clipboard, using whichever clipboard tool is installed. Returns None
+/// if there's no clipboard tool, or the clipboard doesn't contain text.
+pub fn read_clipboard() -> Option<String> {
+    let tools: &[(&str, &[&str])] = if cfg!(target_os = "macos") {
+        &[("pbpaste", &[])]
+    } else {
+        &[
+            ("wl-paste", &["--no-newline"]),
+            ("xclip", &["-o", "-selection", "clipboard"]),
+            ("xsel", &["--clipboard", "--output"]),
+        ]
+    };
+    for &(tool, args) in tools {
+        if let Ok(output) = Command::new(tool).args(args).output() {
+            if output.status.success() {
+                return String::from_utf8(output.stdout).ok();
+            }
+        }
+    }
+    None
+}
+
+/// Split a string on top level commas - i.e. commas which aren't nested in brackets or string
+/// literals. If `types` is true, angle brackets are treated as brackets until the first top level
+/// '=' in each item, so that generic types (but not default expressions) can contain commas.
+pub fn split_top_level(s: &str, types: bool) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut curr = String::new();
+    let mut depth = 0i32;
+    let mut in_default = false;
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for c in s.chars() {
+        if let Some(q) = quote {
+            curr.push(c);
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        match c {
+            '"' | '\'' => quote = Some(c),
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            '<' if types && !in_default => depth += 1,
+            '>' if types && !in_default => depth -= 1,
+            '=' if depth == 0 => in_default = true,
+            ',' if depth == 0 => {
+                items.push(curr.trim().to_owned());
+                curr.clear();
+                in_default = false;
+                continue;
+            }
+            _ => (),
+        }
+        curr.push(c);
+    }
+    if curr.trim().len() > 0 || items.len() > 0 {
+        items.push(curr.trim().to_owned());
+    }
+    items
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Rect {
     pub pos: Vector2<f32>,
     pub size: Vector2<f32>,
@@ -35,4 +105,9 @@ impl Rect {
     pub fn bottom(&self) -> f32 {
         self.pos.y + self.size.y
     }
+
+    /// Returns true if the given point is inside this rect
+    pub fn contains(&self, p: Vector2<f32>) -> bool {
+        p.x >= self.left() && p.x < self.right() && p.y >= self.top() && p.y < self.bottom()
+    }
 }
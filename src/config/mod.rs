@@ -0,0 +1,78 @@
+//! Module for locating & reading the user's config files.
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+
+/// Get the directory containing the user's config files. This is `$PEARL_CONFIG_DIR` if set,
+/// otherwise `~/.config/pearl`.
+#[cfg(not(test))]
+pub fn config_dir() -> PathBuf {
+    if let Some(dir) = env::var_os("PEARL_CONFIG_DIR") {
+        return PathBuf::from(dir);
+    }
+    let mut dir = PathBuf::from(env::var_os("HOME").unwrap_or_default());
+    dir.push(".config");
+    dir.push("pearl");
+    dir
+}
+
+/// A config directory for a test, which is removed when the test's thread ends.
+#[cfg(test)]
+struct TestConfigDir(PathBuf);
+
+#[cfg(test)]
+impl Drop for TestConfigDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// In tests, the config directory is a temporary directory for each test's thread, which starts
+/// off empty. This keeps tests from depending on, or changing, the user's real config.
+#[cfg(test)]
+pub fn config_dir() -> PathBuf {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    static NEXT_DIR: AtomicUsize = AtomicUsize::new(0);
+    thread_local!(static DIR: TestConfigDir = {
+        let mut dir = env::temp_dir();
+        dir.push(format!(
+            "pearl-test-{}-{}",
+            ::std::process::id(),
+            NEXT_DIR.fetch_add(1, Ordering::SeqCst)
+        ));
+        TestConfigDir(dir)
+    });
+    DIR.with(|d| d.0.clone())
+}
+
+/// Get the path of a file in the config directory.
+pub fn config_path(name: &str) -> PathBuf {
+    let mut path = config_dir();
+    path.push(name);
+    path
+}
+
+/// Read a file in the config directory to a string. Returns None if the file doesn't exist or
+/// couldn't be read.
+pub fn read_config_file(name: &str) -> Option<String> {
+    let mut contents = String::new();
+    match File::open(config_path(name)) {
+        Ok(mut f) => {
+            if f.read_to_string(&mut contents).is_err() {
+                println!("Failed to read config file {}", name);
+                return None;
+            }
+        }
+        Err(_) => return None,
+    }
+    Some(contents)
+}
+
+/// Write a file in the config directory, creating the directory if needed.
+pub fn write_config_file(name: &str, contents: &str) -> io::Result<()> {
+    fs::create_dir_all(config_dir())?;
+    let mut f = File::create(config_path(name))?;
+    f.write_all(contents.as_bytes())
+}
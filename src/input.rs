@@ -1,5 +1,16 @@
 use winit::VirtualKeyCode as VKC;
-use winit::ModifiersState;
+use winit::{ModifiersState, WindowEvent, ElementState, MouseButton, MouseScrollDelta};
+use cgmath::Vector2;
+use common;
+use std::time::{Duration, Instant};
+
+/// The most time between 2 clicks for them to count as a double click
+const DOUBLE_CLICK_MS: u64 = 400;
+/// The furthest the mouse can move on each axis between 2 clicks for them to count as a double
+/// click
+const DOUBLE_CLICK_DIST: f32 = 4.0;
+/// The number of pixels to treat as a line, when the scroll wheel scrolls by pixels
+const SCROLL_LINE_PIXELS: f32 = 16.0;
 
 /// Modifiers represented using the 4 least significant bits of the given number.
 /// Order (from most significant to least) is: shift, ctrl, alt, logo (windows
@@ -8,48 +19,479 @@ use winit::ModifiersState;
 /// * `0b1000` would be just the shift key.
 pub type Modifiers = u8;
 
+/// Modifier bit for the shift key
+pub const SHIFT: Modifiers = 0b1000;
+/// Modifier bit for the ctrl key
+pub const CTRL: Modifiers = 0b0100;
+/// Modifier bit for the alt key
+pub const ALT: Modifiers = 0b0010;
+/// Modifier bit for the logo key
+pub const LOGO: Modifiers = 0b0001;
+
+/// Convert a char to an input chunk if possible. Assumes a US keyboard layout for chars which need
+/// shift to be held, e.g. `!` is `S-1`.
+pub fn char_to_input(c: char) -> Option<InputChunk> {
+    let (k, shift) = match c {
+        'a' | 'A' => (VKC::A, c.is_uppercase()),
+        'b' | 'B' => (VKC::B, c.is_uppercase()),
+        'c' | 'C' => (VKC::C, c.is_uppercase()),
+        'd' | 'D' => (VKC::D, c.is_uppercase()),
+        'e' | 'E' => (VKC::E, c.is_uppercase()),
+        'f' | 'F' => (VKC::F, c.is_uppercase()),
+        'g' | 'G' => (VKC::G, c.is_uppercase()),
+        'h' | 'H' => (VKC::H, c.is_uppercase()),
+        'i' | 'I' => (VKC::I, c.is_uppercase()),
+        'j' | 'J' => (VKC::J, c.is_uppercase()),
+        'k' | 'K' => (VKC::K, c.is_uppercase()),
+        'l' | 'L' => (VKC::L, c.is_uppercase()),
+        'm' | 'M' => (VKC::M, c.is_uppercase()),
+        'n' | 'N' => (VKC::N, c.is_uppercase()),
+        'o' | 'O' => (VKC::O, c.is_uppercase()),
+        'p' | 'P' => (VKC::P, c.is_uppercase()),
+        'q' | 'Q' => (VKC::Q, c.is_uppercase()),
+        'r' | 'R' => (VKC::R, c.is_uppercase()),
+        's' | 'S' => (VKC::S, c.is_uppercase()),
+        't' | 'T' => (VKC::T, c.is_uppercase()),
+        'u' | 'U' => (VKC::U, c.is_uppercase()),
+        'v' | 'V' => (VKC::V, c.is_uppercase()),
+        'w' | 'W' => (VKC::W, c.is_uppercase()),
+        'x' | 'X' => (VKC::X, c.is_uppercase()),
+        'y' | 'Y' => (VKC::Y, c.is_uppercase()),
+        'z' | 'Z' => (VKC::Z, c.is_uppercase()),
+        '0' => (VKC::Key0, false),
+        '1' => (VKC::Key1, false),
+        '2' => (VKC::Key2, false),
+        '3' => (VKC::Key3, false),
+        '4' => (VKC::Key4, false),
+        '5' => (VKC::Key5, false),
+        '6' => (VKC::Key6, false),
+        '7' => (VKC::Key7, false),
+        '8' => (VKC::Key8, false),
+        '9' => (VKC::Key9, false),
+        ')' => (VKC::Key0, true),
+        '!' => (VKC::Key1, true),
+        '@' => (VKC::Key2, true),
+        '#' => (VKC::Key3, true),
+        '$' => (VKC::Key4, true),
+        '%' => (VKC::Key5, true),
+        '^' => (VKC::Key6, true),
+        '&' => (VKC::Key7, true),
+        '*' => (VKC::Key8, true),
+        '(' => (VKC::Key9, true),
+        '-' => (VKC::Minus, false),
+        '_' => (VKC::Minus, true),
+        '=' => (VKC::Equals, false),
+        '+' => (VKC::Equals, true),
+        '[' => (VKC::LBracket, false),
+        '{' => (VKC::LBracket, true),
+        ']' => (VKC::RBracket, false),
+        '}' => (VKC::RBracket, true),
+        ';' => (VKC::Semicolon, false),
+        ':' => (VKC::Semicolon, true),
+        '\'' => (VKC::Apostrophe, false),
+        '"' => (VKC::Apostrophe, true),
+        ',' => (VKC::Comma, false),
+        '<' => (VKC::Comma, true),
+        '.' => (VKC::Period, false),
+        '>' => (VKC::Period, true),
+        '/' => (VKC::Slash, false),
+        '?' => (VKC::Slash, true),
+        '\\' => (VKC::Backslash, false),
+        '|' => (VKC::Backslash, true),
+        '`' => (VKC::Grave, false),
+        '~' => (VKC::Grave, true),
+        _ => return None,
+    };
+    Some(InputChunk(k, if shift { SHIFT } else { 0 }))
+}
+
+/// Convert the name of a special key (e.g. `Tab`, `RET`, `F5`) to a keycode.
+fn special_key(name: &str) -> Option<VKC> {
+    match name {
+        "Tab" | "TAB" => Some(VKC::Tab),
+        "RET" | "Return" | "Enter" => Some(VKC::Return),
+        "SPC" | "Space" => Some(VKC::Space),
+        "ESC" | "Esc" | "Escape" => Some(VKC::Escape),
+        "DEL" | "BS" | "Backspace" => Some(VKC::Back),
+        "Delete" => Some(VKC::Delete),
+        "Insert" => Some(VKC::Insert),
+        "Home" => Some(VKC::Home),
+        "End" => Some(VKC::End),
+        "PageUp" => Some(VKC::PageUp),
+        "PageDown" => Some(VKC::PageDown),
+        "Left" => Some(VKC::Left),
+        "Right" => Some(VKC::Right),
+        "Up" => Some(VKC::Up),
+        "Down" => Some(VKC::Down),
+        "F1" => Some(VKC::F1),
+        "F2" => Some(VKC::F2),
+        "F3" => Some(VKC::F3),
+        "F4" => Some(VKC::F4),
+        "F5" => Some(VKC::F5),
+        "F6" => Some(VKC::F6),
+        "F7" => Some(VKC::F7),
+        "F8" => Some(VKC::F8),
+        "F9" => Some(VKC::F9),
+        "F10" => Some(VKC::F10),
+        "F11" => Some(VKC::F11),
+        "F12" => Some(VKC::F12),
+        _ => None,
+    }
+}
+
+/// Get the name of a special key, as accepted by `special_key`.
+fn special_key_name(k: VKC) -> Option<&'static str> {
+    match k {
+        VKC::Tab => Some("Tab"),
+        VKC::Return => Some("RET"),
+        VKC::Space => Some("SPC"),
+        VKC::Escape => Some("ESC"),
+        VKC::Back => Some("DEL"),
+        VKC::Delete => Some("Delete"),
+        VKC::Insert => Some("Insert"),
+        VKC::Home => Some("Home"),
+        VKC::End => Some("End"),
+        VKC::PageUp => Some("PageUp"),
+        VKC::PageDown => Some("PageDown"),
+        VKC::Left => Some("Left"),
+        VKC::Right => Some("Right"),
+        VKC::Up => Some("Up"),
+        VKC::Down => Some("Down"),
+        VKC::F1 => Some("F1"),
+        VKC::F2 => Some("F2"),
+        VKC::F3 => Some("F3"),
+        VKC::F4 => Some("F4"),
+        VKC::F5 => Some("F5"),
+        VKC::F6 => Some("F6"),
+        VKC::F7 => Some("F7"),
+        VKC::F8 => Some("F8"),
+        VKC::F9 => Some("F9"),
+        VKC::F10 => Some("F10"),
+        VKC::F11 => Some("F11"),
+        VKC::F12 => Some("F12"),
+        _ => None,
+    }
+}
+
+/// Parse a key sequence, in emacs-like notation. Keys are separated by whitespace, and each key
+/// can have modifier prefixes - `C-` for ctrl, `M-` for alt, `S-` for shift and `s-` for logo.
+/// Special keys are given by name, e.g. `S-Tab`, `C-RET`, `F5`. Any other word is a sequence of
+/// chars, so `cc` is two keys.
+///
+/// # Example
+/// `C-x C-f`, `cc`, `M-S-Left`
+pub fn parse_key_seq(s: &str) -> Result<Vec<InputChunk>, String> {
+    let mut chunks = Vec::new();
+    for word in s.split_whitespace() {
+        let mut mods = 0;
+        let mut rest = word;
+        // Consume modifier prefixes, as long as there's a key after them
+        while rest.len() > 2 && rest.as_bytes()[1] == b'-' {
+            mods |= match rest.as_bytes()[0] {
+                b'C' => CTRL,
+                b'M' => ALT,
+                b'S' => SHIFT,
+                b's' => LOGO,
+                _ => return Err(format!("Unknown modifier in '{}'", word)),
+            };
+            rest = &rest[2..];
+        }
+        if let Some(k) = special_key(rest) {
+            chunks.push(InputChunk(k, mods));
+            continue;
+        }
+        // A chord can only have one key after it
+        if mods != 0 && rest.chars().count() != 1 {
+            return Err(format!("Unknown key in '{}'", word));
+        }
+        for c in rest.chars() {
+            match char_to_input(c) {
+                Some(InputChunk(k, m)) => chunks.push(InputChunk(k, m | mods)),
+                None => return Err(format!("Unknown key '{}'", c)),
+            }
+        }
+    }
+    if chunks.len() == 0 {
+        return Err("Empty key sequence".to_owned());
+    }
+    Ok(chunks)
+}
+
 /// A chunk of input, containing a key code and modifiers.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub struct InputChunk(pub VKC, pub Modifiers);
 
 impl InputChunk {
-    /// Create from winit::ModifiersState
+    /// Create from winit::ModifiersState. Some platforms report shifted punctuation as a key of
+    /// its own - e.g. on X11, winit 0.7 reports `:` as `Colon` rather than `S-;` - so these are
+    /// converted to the unshifted key with shift held, as in `char_to_input`.
     pub fn from_modifiers_state(k: VKC, m: ModifiersState) -> InputChunk {
-        let modifier = if m.shift { 8 } else { 0 } + if m.ctrl { 4 } else { 0 } +
-            if m.ctrl { 2 } else { 0 } + if m.ctrl { 1 } else { 0 };
-        InputChunk(k, modifier)
+        let mods = common::mods_to_bitflags(m);
+        match k {
+            VKC::Colon => InputChunk(VKC::Semicolon, mods | SHIFT),
+            VKC::At => InputChunk(VKC::Key2, mods | SHIFT),
+            VKC::Underline => InputChunk(VKC::Minus, mods | SHIFT),
+            _ => InputChunk(k, mods),
+        }
+    }
+
+    /// Convert this input chunk to the notation accepted by `parse_key_seq`, e.g. `C-x` or
+    /// `S-Tab`. Returns None for keys which can't be written, like modifier keys on their own.
+    pub fn to_key_seq(&self) -> Option<String> {
+        let (key, mods) = match special_key_name(self.0) {
+            Some(name) => (name.to_owned(), self.1),
+            None => {
+                // Find the char for this key, preferring one which includes the shift modifier
+                let find_char = |m| {
+                    (b' '..b'~' + 1).map(|b| b as char).find(|c| {
+                        char_to_input(*c) == Some(InputChunk(self.0, m))
+                    })
+                };
+                match find_char(self.1 & SHIFT) {
+                    Some(c) => (c.to_string(), self.1 & !SHIFT),
+                    None => {
+                        match find_char(0) {
+                            Some(c) => (c.to_string(), self.1),
+                            None => return None,
+                        }
+                    }
+                }
+            }
+        };
+        let mut s = String::new();
+        for &(bit, prefix) in &[(CTRL, "C-"), (ALT, "M-"), (SHIFT, "S-"), (LOGO, "s-")] {
+            if mods & bit != 0 {
+                s += prefix;
+            }
+        }
+        s += &key;
+        Some(s)
+    }
+
+    /// Get the digit this input chunk represents, if it's an unmodified number key
+    pub fn to_digit(&self) -> Option<usize> {
+        if self.1 != 0 {
+            return None;
+        }
+        match self.0 {
+            VKC::Key0 => Some(0),
+            VKC::Key1 => Some(1),
+            VKC::Key2 => Some(2),
+            VKC::Key3 => Some(3),
+            VKC::Key4 => Some(4),
+            VKC::Key5 => Some(5),
+            VKC::Key6 => Some(6),
+            VKC::Key7 => Some(7),
+            VKC::Key8 => Some(8),
+            VKC::Key9 => Some(9),
+            _ => None,
+        }
+    }
+
+    /// Convert this input chunk to a string for display. This is the same as `to_key_seq`, but
+    /// falls back on the name of the keycode for keys which can't be written.
+    pub fn to_str(&self) -> String {
+        match self.to_key_seq() {
+            Some(s) => s,
+            None => format!("{:?}", self.0),
+        }
     }
 
-    /// Convert this input chunk to a string
-    pub fn to_str(&self) -> &'static str {
+    /// Returns true if this is a modifier key pressed on its own, e.g. shift. These don't make
+    /// up part of a key sequence.
+    pub fn is_modifier(&self) -> bool {
         match self.0 {
-            VKC::A => if self.1 & 8 > 0 { "A" } else { "a" },
-            VKC::B => if self.1 & 8 > 0 { "B" } else { "b" },
-            VKC::C => if self.1 & 8 > 0 { "C" } else { "c" },
-            VKC::D => if self.1 & 8 > 0 { "D" } else { "d" },
-            VKC::E => if self.1 & 8 > 0 { "E" } else { "e" },
-            VKC::F => if self.1 & 8 > 0 { "F" } else { "f" },
-            VKC::G => if self.1 & 8 > 0 { "G" } else { "g" },
-            VKC::H => if self.1 & 8 > 0 { "H" } else { "h" },
-            VKC::I => if self.1 & 8 > 0 { "I" } else { "i" },
-            VKC::J => if self.1 & 8 > 0 { "J" } else { "j" },
-            VKC::K => if self.1 & 8 > 0 { "K" } else { "k" },
-            VKC::L => if self.1 & 8 > 0 { "L" } else { "l" },
-            VKC::M => if self.1 & 8 > 0 { "M" } else { "m" },
-            VKC::N => if self.1 & 8 > 0 { "N" } else { "n" },
-            VKC::O => if self.1 & 8 > 0 { "O" } else { "o" },
-            VKC::P => if self.1 & 8 > 0 { "P" } else { "p" },
-            VKC::Q => if self.1 & 8 > 0 { "Q" } else { "q" },
-            VKC::R => if self.1 & 8 > 0 { "R" } else { "r" },
-            VKC::S => if self.1 & 8 > 0 { "S" } else { "s" },
-            VKC::T => if self.1 & 8 > 0 { "T" } else { "t" },
-            VKC::U => if self.1 & 8 > 0 { "U" } else { "u" },
-            VKC::V => if self.1 & 8 > 0 { "V" } else { "v" },
-            VKC::W => if self.1 & 8 > 0 { "W" } else { "w" },
-            VKC::X => if self.1 & 8 > 0 { "X" } else { "x" },
-            VKC::Y => if self.1 & 8 > 0 { "Y" } else { "y" },
-            VKC::Z => if self.1 & 8 > 0 { "Z" } else { "z" },
-            _ => "",
+            VKC::LShift | VKC::RShift | VKC::LControl | VKC::RControl | VKC::LAlt |
+            VKC::RAlt | VKC::LWin | VKC::RWin => true,
+            _ => false,
         }
     }
 }
+
+/// A mouse action, which views can respond to. Positions are in pixels from the top left of the
+/// window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MouseEvent {
+    /// The left button was clicked
+    Click(Vector2<f32>),
+    /// The left button was clicked twice in quick succession. The first click is sent as a
+    /// `Click` first.
+    DoubleClick(Vector2<f32>),
+    /// The scroll wheel was scrolled by the given number of lines. Positive is up.
+    Scroll(Vector2<f32>, f32),
+}
+
+impl MouseEvent {
+    /// The position of the mouse when the event happened
+    pub fn pos(&self) -> Vector2<f32> {
+        match *self {
+            MouseEvent::Click(p) |
+            MouseEvent::DoubleClick(p) |
+            MouseEvent::Scroll(p, _) => p,
+        }
+    }
+}
+
+/// Turns window events into mouse events, keeping track of where the mouse is and when it was
+/// last clicked.
+pub struct MouseTracker {
+    pos: Vector2<f32>,
+    last_click: Option<(Instant, Vector2<f32>)>,
+}
+
+impl MouseTracker {
+    pub fn new() -> MouseTracker {
+        MouseTracker {
+            pos: Vector2::new(0.0, 0.0),
+            last_click: None,
+        }
+    }
+
+    /// Process a window event. Returns the mouse event it causes, if any.
+    pub fn process(&mut self, ev: &WindowEvent) -> Option<MouseEvent> {
+        match *ev {
+            WindowEvent::MouseMoved { position: (x, y), .. } => {
+                self.pos = Vector2::new(x as f32, y as f32);
+                None
+            }
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } => Some(self.click(Instant::now())),
+            WindowEvent::MouseWheel { delta: MouseScrollDelta::LineDelta(_, y), .. } => {
+                Some(MouseEvent::Scroll(self.pos, y))
+            }
+            WindowEvent::MouseWheel { delta: MouseScrollDelta::PixelDelta(_, y), .. } => {
+                Some(MouseEvent::Scroll(self.pos, y / SCROLL_LINE_PIXELS))
+            }
+            _ => None,
+        }
+    }
+
+    /// Handle a click at the current position, at the given time
+    fn click(&mut self, now: Instant) -> MouseEvent {
+        let double = match self.last_click {
+            Some((t, p)) => {
+                now.duration_since(t) <= Duration::from_millis(DOUBLE_CLICK_MS) &&
+                    (self.pos.x - p.x).abs() <= DOUBLE_CLICK_DIST &&
+                    (self.pos.y - p.y).abs() <= DOUBLE_CLICK_DIST
+            }
+            None => false,
+        };
+        if double {
+            // A third click starts a new double click
+            self.last_click = None;
+            MouseEvent::DoubleClick(self.pos)
+        } else {
+            self.last_click = Some((now, self.pos));
+            MouseEvent::Click(self.pos)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_keys() {
+        assert_eq!(
+            parse_key_seq("cC"),
+            Ok(vec![InputChunk(VKC::C, 0), InputChunk(VKC::C, SHIFT)])
+        );
+        assert_eq!(
+            parse_key_seq("C-x M-f S-Tab"),
+            Ok(vec![
+                InputChunk(VKC::X, CTRL),
+                InputChunk(VKC::F, ALT),
+                InputChunk(VKC::Tab, SHIFT),
+            ])
+        );
+        assert_eq!(
+            parse_key_seq("C-M-Left 1 ? -"),
+            Ok(vec![
+                InputChunk(VKC::Left, CTRL | ALT),
+                InputChunk(VKC::Key1, 0),
+                InputChunk(VKC::Slash, SHIFT),
+                InputChunk(VKC::Minus, 0),
+            ])
+        );
+        assert!(parse_key_seq("C-xy").is_err());
+        assert!(parse_key_seq("X-y").is_err());
+        assert!(parse_key_seq("").is_err());
+    }
+
+    #[test]
+    fn key_seq_round_trip() {
+        let keys = parse_key_seq("a A C-x M-S-Tab ? C-- RET F5 s-Left").unwrap();
+        let strs: Vec<String> = keys.iter().map(|k| k.to_key_seq().unwrap()).collect();
+        assert_eq!(strs.join(" "), "a A C-x M-S-Tab ? C-- RET F5 s-Left");
+        assert_eq!(InputChunk(VKC::LShift, SHIFT).to_key_seq(), None);
+    }
+
+    #[test]
+    fn modifiers() {
+        let ms = ModifiersState {
+            shift: false,
+            ctrl: true,
+            alt: false,
+            logo: false,
+        };
+        assert_eq!(InputChunk::from_modifiers_state(VKC::S, ms), InputChunk(VKC::S, CTRL));
+        let ms = ModifiersState {
+            shift: true,
+            ctrl: false,
+            alt: true,
+            logo: true,
+        };
+        assert_eq!(
+            InputChunk::from_modifiers_state(VKC::X, ms),
+            InputChunk(VKC::X, SHIFT | ALT | LOGO)
+        );
+        // Shifted punctuation reported as a key of its own matches the shifted form
+        let none = ModifiersState {
+            shift: false,
+            ctrl: false,
+            alt: false,
+            logo: false,
+        };
+        assert_eq!(
+            vec![
+                InputChunk::from_modifiers_state(VKC::Colon, none),
+                InputChunk::from_modifiers_state(VKC::At, ms),
+                InputChunk::from_modifiers_state(VKC::Underline, none),
+            ],
+            parse_key_seq(": M-S-s-@ _").unwrap()
+        );
+    }
+
+    #[test]
+    fn key_to_str() {
+        let keys = parse_key_seq("x X 5 % [ C-s M-x F11 Down").unwrap();
+        let strs: Vec<String> = keys.iter().map(|k| k.to_str()).collect();
+        assert_eq!(strs.join(" "), "x X 5 % [ C-s M-x F11 Down");
+        assert_eq!(InputChunk(VKC::LControl, CTRL).to_str(), "LControl");
+    }
+
+    #[test]
+    fn double_click() {
+        let mut mouse = MouseTracker::new();
+        let t = Instant::now();
+        let origin = Vector2::new(0.0, 0.0);
+        assert_eq!(mouse.click(t), MouseEvent::Click(origin));
+        assert_eq!(
+            mouse.click(t + Duration::from_millis(100)),
+            MouseEvent::DoubleClick(origin)
+        );
+        assert_eq!(mouse.click(t + Duration::from_millis(200)), MouseEvent::Click(origin));
+        // Too slow
+        assert_eq!(mouse.click(t + Duration::from_millis(1000)), MouseEvent::Click(origin));
+        // Too far
+        mouse.pos = Vector2::new(20.0, 0.0);
+        assert_eq!(
+            mouse.click(t + Duration::from_millis(1100)),
+            MouseEvent::Click(mouse.pos)
+        );
+    }
+}
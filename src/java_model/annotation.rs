@@ -0,0 +1,220 @@
+use common::split_top_level;
+
+/// The qualified names of the annotation types bundled with the JDK, used for completion.
+pub const JDK_ANNOTATIONS: &'static [&'static str] = &[
+    "java.lang.Override",
+    "java.lang.Deprecated",
+    "java.lang.SuppressWarnings",
+    "java.lang.SafeVarargs",
+    "java.lang.FunctionalInterface",
+    "java.lang.annotation.Documented",
+    "java.lang.annotation.Inherited",
+    "java.lang.annotation.Native",
+    "java.lang.annotation.Repeatable",
+    "java.lang.annotation.Retention",
+    "java.lang.annotation.Target",
+    "javax.annotation.Generated",
+    "javax.annotation.PostConstruct",
+    "javax.annotation.PreDestroy",
+    "javax.annotation.Resource",
+];
+
+/// The value of an annotation element.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnnotationValue {
+    /// A literal, stored as source - e.g. `"unchecked"`, `10`, `true`.
+    Literal(String),
+    /// An enum constant, e.g. `ElementType.METHOD`.
+    EnumConstant(String),
+    /// A class literal. Contains the type name, without the `.class`.
+    ClassLiteral(String),
+    /// An array of values, e.g. `{"a", "b"}`.
+    Array(Vec<AnnotationValue>),
+    /// A nested annotation.
+    Annotation(Annotation),
+}
+
+/// An annotation, e.g. `@SuppressWarnings(value = "unchecked")`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Annotation {
+    /// The annotation type, as written in source (possibly qualified)
+    pub type_name: String,
+    /// Pairs of element names and values. A single element named `value` is written without its
+    /// name.
+    pub elements: Vec<(String, AnnotationValue)>,
+}
+
+impl AnnotationValue {
+    /// Parse an element value from source. Returns None if the source is malformed.
+    pub fn parse(src: &str) -> Option<AnnotationValue> {
+        let src = src.trim();
+        if src.len() == 0 {
+            return None;
+        }
+        if src.starts_with('@') {
+            return Annotation::parse(src).map(AnnotationValue::Annotation);
+        }
+        if src.starts_with('{') {
+            if !src.ends_with('}') {
+                return None;
+            }
+            let mut values = Vec::new();
+            for v in split_top_level(&src[1..src.len() - 1], false) {
+                values.push(AnnotationValue::parse(&v)?);
+            }
+            return Some(AnnotationValue::Array(values));
+        }
+        if src.ends_with(".class") {
+            return Some(AnnotationValue::ClassLiteral(
+                src[..src.len() - 6].trim().to_owned(),
+            ));
+        }
+        let first = src.chars().next().unwrap();
+        let is_ident = (first.is_alphabetic() || first == '_' || first == '$') &&
+            src.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '$' || c == '.');
+        if is_ident && src != "true" && src != "false" && src != "null" {
+            Some(AnnotationValue::EnumConstant(src.to_owned()))
+        } else {
+            Some(AnnotationValue::Literal(src.to_owned()))
+        }
+    }
+
+    /// Convert to java source
+    pub fn to_source(&self) -> String {
+        match *self {
+            AnnotationValue::Literal(ref s) |
+            AnnotationValue::EnumConstant(ref s) => s.clone(),
+            AnnotationValue::ClassLiteral(ref s) => format!("{}.class", s),
+            AnnotationValue::Array(ref values) => {
+                let values: Vec<String> = values.iter().map(|v| v.to_source()).collect();
+                format!("{{{}}}", values.join(", "))
+            }
+            AnnotationValue::Annotation(ref a) => a.to_source(),
+        }
+    }
+}
+
+impl Annotation {
+    pub fn new(type_name: &str) -> Annotation {
+        Annotation {
+            type_name: type_name.to_owned(),
+            elements: Vec::new(),
+        }
+    }
+
+    /// Parse an annotation from source, e.g. `@Retention(RetentionPolicy.RUNTIME)`. The leading
+    /// '@' is optional. Returns None if the source is malformed.
+    pub fn parse(src: &str) -> Option<Annotation> {
+        let src = src.trim();
+        let src = if src.starts_with('@') { &src[1..] } else { src };
+        let (type_name, args) = match src.find('(') {
+            Some(ix) => {
+                if !src.ends_with(')') {
+                    return None;
+                }
+                (src[..ix].trim(), Some(&src[ix + 1..src.len() - 1]))
+            }
+            None => (src, None),
+        };
+        if type_name.len() == 0 || type_name.contains(char::is_whitespace) {
+            return None;
+        }
+        let mut annotation = Annotation::new(type_name);
+        let args = match args {
+            Some(a) => split_top_level(a, false),
+            None => return Some(annotation),
+        };
+        for a in args {
+            // Find a top level '=', which separates the element name from the value
+            let eq_ix = match a.find('=') {
+                Some(ix) => {
+                    let name = a[..ix].trim();
+                    if name.len() > 0 && name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                        Some(ix)
+                    } else {
+                        None
+                    }
+                }
+                None => None,
+            };
+            match eq_ix {
+                Some(ix) => {
+                    let value = AnnotationValue::parse(&a[ix + 1..])?;
+                    annotation.elements.push((a[..ix].trim().to_owned(), value));
+                }
+                None => {
+                    annotation.elements.push(
+                        ("value".to_owned(), AnnotationValue::parse(&a)?),
+                    );
+                }
+            }
+        }
+        Some(annotation)
+    }
+
+    /// Convert to java source, e.g. `@SuppressWarnings("unchecked")`.
+    pub fn to_source(&self) -> String {
+        let mut src = format!("@{}", self.type_name);
+        if self.elements.len() == 1 && self.elements[0].0 == "value" {
+            src = src + "(" + &self.elements[0].1.to_source() + ")";
+        } else if self.elements.len() > 0 {
+            let elements: Vec<String> = self.elements
+                .iter()
+                .map(|&(ref n, ref v)| format!("{} = {}", n, v.to_source()))
+                .collect();
+            src = src + "(" + &elements.join(", ") + ")";
+        }
+        src
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_values() {
+        assert_eq!(
+            AnnotationValue::parse("\"a, b\""),
+            Some(AnnotationValue::Literal("\"a, b\"".to_owned()))
+        );
+        assert_eq!(
+            AnnotationValue::parse("true"),
+            Some(AnnotationValue::Literal("true".to_owned()))
+        );
+        assert_eq!(
+            AnnotationValue::parse("ElementType.METHOD"),
+            Some(AnnotationValue::EnumConstant("ElementType.METHOD".to_owned()))
+        );
+        assert_eq!(
+            AnnotationValue::parse("String.class"),
+            Some(AnnotationValue::ClassLiteral("String".to_owned()))
+        );
+        assert_eq!(
+            AnnotationValue::parse("{1, @Foo}"),
+            Some(AnnotationValue::Array(vec![
+                AnnotationValue::Literal("1".to_owned()),
+                AnnotationValue::Annotation(Annotation::new("Foo")),
+            ]))
+        );
+        assert_eq!(AnnotationValue::parse("{1, 2"), None);
+    }
+
+    #[test]
+    fn round_trip() {
+        for src in &[
+            "@Override",
+            "@SuppressWarnings(\"unchecked\")",
+            "@Target({ElementType.METHOD, ElementType.FIELD})",
+            "@Foo(a = 1, b = String.class, c = @Bar(x = \"=\"))",
+        ]
+        {
+            let a = Annotation::parse(src).unwrap();
+            assert_eq!(&a.to_source(), src);
+        }
+        let a = Annotation::parse("@Foo(value = 1)").unwrap();
+        assert_eq!(a.to_source(), "@Foo(1)");
+        assert_eq!(Annotation::parse("@Foo(1"), None);
+        assert_eq!(Annotation::parse("@"), None);
+    }
+}
@@ -1,5 +1,8 @@
 use Modifier;
 use Field;
+use Method;
+use DocComment;
+use Annotation;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum MemberType {
@@ -11,6 +14,59 @@ pub enum MemberType {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ClassMember {
     Field(Field),
+    Method(Method),
+}
+
+impl ClassMember {
+    pub fn name(&self) -> &str {
+        match *self {
+            ClassMember::Field(ref f) => f.name.as_ref(),
+            ClassMember::Method(ref m) => m.name.as_ref(),
+        }
+    }
+
+    pub fn modifiers(&self) -> &[Modifier] {
+        match *self {
+            ClassMember::Field(ref f) => &f.modifiers[..],
+            ClassMember::Method(ref m) => &m.modifiers[..],
+        }
+    }
+
+    pub fn doc(&self) -> &Option<DocComment> {
+        match *self {
+            ClassMember::Field(ref f) => &f.doc,
+            ClassMember::Method(ref m) => &m.doc,
+        }
+    }
+
+    pub fn annotations(&self) -> &[Annotation] {
+        match *self {
+            ClassMember::Field(ref f) => &f.annotations[..],
+            ClassMember::Method(ref m) => &m.annotations[..],
+        }
+    }
+
+    pub fn annotations_mut(&mut self) -> &mut Vec<Annotation> {
+        match *self {
+            ClassMember::Field(ref mut f) => &mut f.annotations,
+            ClassMember::Method(ref mut m) => &mut m.annotations,
+        }
+    }
+
+    pub fn doc_mut(&mut self) -> &mut Option<DocComment> {
+        match *self {
+            ClassMember::Field(ref mut f) => &mut f.doc,
+            ClassMember::Method(ref mut m) => &mut m.doc,
+        }
+    }
+
+    /// Get the java signature of this member, e.g. `private int x`.
+    pub fn signature(&self) -> String {
+        match *self {
+            ClassMember::Field(ref f) => f.signature(),
+            ClassMember::Method(ref m) => m.signature(),
+        }
+    }
 }
 
 
@@ -23,6 +79,8 @@ pub struct Class {
     pub members: Vec<ClassMember>,
     pub inner_classes: Vec<Class>,
     pub modifiers: Vec<Modifier>,
+    pub doc: Option<DocComment>,
+    pub annotations: Vec<Annotation>,
 }
 
 impl Class {
@@ -35,6 +93,8 @@ impl Class {
             members: Vec::new(),
             inner_classes: Vec::new(),
             modifiers: Vec::new(),
+            doc: None,
+            annotations: Vec::new(),
         }
     }
 
@@ -44,4 +104,22 @@ impl Class {
             ..Class::new_empty()
         }
     }
+
+    /// Find a member by name. Returns None if no member has the given name.
+    pub fn find_member(&self, name: &str) -> Option<&ClassMember> {
+        self.members.iter().find(|m| m.name() == name)
+    }
+
+    /// Find a member by name. Returns None if no member has the given name.
+    pub fn find_member_mut(&mut self, name: &str) -> Option<&mut ClassMember> {
+        self.members.iter_mut().find(|m| m.name() == name)
+    }
+
+    /// Remove a member by name, returning it. Returns None if no member has the given name.
+    pub fn remove_member(&mut self, name: &str) -> Option<ClassMember> {
+        match self.members.iter().position(|m| m.name() == name) {
+            Some(ix) => Some(self.members.remove(ix)),
+            None => None,
+        }
+    }
 }
@@ -0,0 +1,181 @@
+//! Doc comments, with their block tags, and conversion to and from javadoc source.
+
+/// A block tag in a doc comment, e.g. `@param name The name`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DocTag {
+    /// `@param <name> <text>`
+    Param(String, String),
+    /// `@return <text>`
+    Return(String),
+    /// `@throws <type> <text>`
+    Throws(String, String),
+    /// Any other tag, e.g. `@see`. Contains the tag name (without the '@') and the text.
+    Other(String, String),
+}
+
+/// A doc comment (i.e. javadoc) attached to a declaration, member or package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocComment {
+    /// The main description, before any tags. May contain newlines.
+    pub description: String,
+    pub tags: Vec<DocTag>,
+}
+
+/// How `*/` is written in a javadoc comment. The javadoc tool shows `&#47;` as a '/'.
+const ESCAPED_END: &'static str = "*&#47;";
+
+/// Split a string into its first word and the rest, trimmed.
+fn split_word(s: &str) -> (&str, &str) {
+    let s = s.trim();
+    match s.find(char::is_whitespace) {
+        Some(ix) => (&s[..ix], s[ix..].trim()),
+        None => (s, ""),
+    }
+}
+
+impl DocComment {
+    /// Parse a doc comment from plain text (i.e. without the `/**` & `*/` or leading `*`s). Any
+    /// line starting with '@' starts a new tag, and following lines are added to that tag's
+    /// text.
+    pub fn parse(text: &str) -> DocComment {
+        let mut description = String::new();
+        // Raw tag strings, without the '@'
+        let mut raw_tags: Vec<String> = Vec::new();
+        for line in text.lines() {
+            if line.trim_left().starts_with('@') {
+                raw_tags.push(line.trim()[1..].to_owned());
+            } else if let Some(t) = raw_tags.last_mut() {
+                t.push('\n');
+                t.push_str(line.trim());
+            } else {
+                description.push_str(line.trim_right());
+                description.push('\n');
+            }
+        }
+
+        let tags = raw_tags
+            .iter()
+            .map(|t| {
+                let (name, rest) = split_word(t);
+                match name {
+                    "param" => {
+                        let (p, text) = split_word(rest);
+                        DocTag::Param(p.to_owned(), text.to_owned())
+                    }
+                    "return" => DocTag::Return(rest.to_owned()),
+                    "throws" | "exception" => {
+                        let (e, text) = split_word(rest);
+                        DocTag::Throws(e.to_owned(), text.to_owned())
+                    }
+                    _ => DocTag::Other(name.to_owned(), rest.to_owned()),
+                }
+            })
+            .collect();
+
+        DocComment {
+            description: description.trim().to_owned(),
+            tags: tags,
+        }
+    }
+
+    /// Convert to plain text, in the format accepted by `parse`.
+    pub fn to_text(&self) -> String {
+        let mut text = self.description.clone();
+        if self.tags.len() > 0 && text.len() > 0 {
+            text.push_str("\n\n");
+        }
+        for (ii, t) in self.tags.iter().enumerate() {
+            if ii > 0 {
+                text.push('\n');
+            }
+            let tag = match *t {
+                DocTag::Param(ref p, ref s) => format!("@param {} {}", p, s),
+                DocTag::Return(ref s) => format!("@return {}", s),
+                DocTag::Throws(ref e, ref s) => format!("@throws {} {}", e, s),
+                DocTag::Other(ref n, ref s) => format!("@{} {}", n, s),
+            };
+            text.push_str(tag.trim_right());
+        }
+        text
+    }
+
+    /// Parse a javadoc comment from source, including the `/**` and `*/`. Returns None if this
+    /// isn't a doc comment.
+    pub fn parse_javadoc(src: &str) -> Option<DocComment> {
+        let src = src.trim();
+        if !src.starts_with("/**") || !src.ends_with("*/") || src.len() < 5 {
+            return None;
+        }
+        let inner = &src[3..src.len() - 2];
+        let text: Vec<&str> = inner
+            .lines()
+            .map(|l| {
+                let l = l.trim_left();
+                let l = if l.starts_with('*') { &l[1..] } else { l };
+                if l.starts_with(' ') { &l[1..] } else { l }
+            })
+            .collect();
+        Some(DocComment::parse(&text.join("\n").replace(ESCAPED_END, "*/")))
+    }
+
+    /// Convert to a javadoc comment, with each line prefixed by the given indent. Any `*/` in the
+    /// text is escaped, so that it doesn't end the comment.
+    pub fn to_javadoc(&self, indent: &str) -> String {
+        let mut src = format!("{}/**\n", indent);
+        for l in self.to_text().replace("*/", ESCAPED_END).lines() {
+            if l.len() == 0 {
+                src.push_str(&format!("{} *\n", indent));
+            } else {
+                src.push_str(&format!("{} * {}\n", indent, l));
+            }
+        }
+        src.push_str(&format!("{} */", indent));
+        src
+    }
+
+    /// Get the first line of the description, useful for a short summary.
+    pub fn summary(&self) -> &str {
+        self.description.lines().next().unwrap_or("")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_text() {
+        let doc = DocComment::parse(
+            "Does a thing.\nMore detail.\n\n@param x The x\n  value\n@return Something\n\
+             @throws IOException If it fails\n@since 1.0",
+        );
+        assert_eq!(doc.description, "Does a thing.\nMore detail.");
+        assert_eq!(
+            doc.tags,
+            vec![
+                DocTag::Param("x".to_owned(), "The x\nvalue".to_owned()),
+                DocTag::Return("Something".to_owned()),
+                DocTag::Throws("IOException".to_owned(), "If it fails".to_owned()),
+                DocTag::Other("since".to_owned(), "1.0".to_owned()),
+            ]
+        );
+        assert_eq!(DocComment::parse(&doc.to_text()), doc);
+    }
+
+    #[test]
+    fn javadoc_round_trip() {
+        let src = "/**\n * Does a thing.\n *\n * @param x The x\n */";
+        let doc = DocComment::parse_javadoc(src).unwrap();
+        assert_eq!(doc.description, "Does a thing.");
+        assert_eq!(doc.to_javadoc(""), src);
+        let indented = doc.to_javadoc("    ");
+        assert_eq!(DocComment::parse_javadoc(&indented), Some(doc));
+        assert_eq!(DocComment::parse_javadoc("/* Not javadoc */"), None);
+
+        // A comment end in the text is escaped
+        let doc = DocComment::parse("Matches /* or */.\n@return */");
+        let src = doc.to_javadoc("");
+        assert_eq!(src, "/**\n * Matches /* or *&#47;.\n *\n * @return *&#47;\n */");
+        assert_eq!(DocComment::parse_javadoc(&src), Some(doc));
+    }
+}
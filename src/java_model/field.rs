@@ -1,4 +1,6 @@
 use Modifier;
+use DocComment;
+use Annotation;
 
 /// A field, containing modifiers, a name, and a type.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -6,4 +8,18 @@ pub struct Field {
     pub modifiers: Vec<Modifier>,
     pub field_type: String,
     pub name: String,
+    pub doc: Option<DocComment>,
+    pub annotations: Vec<Annotation>,
+}
+
+impl Field {
+    /// Get the java signature of this field, e.g. `private int x`.
+    pub fn signature(&self) -> String {
+        let mut sig = String::new();
+        for m in &self.modifiers {
+            sig.push_str(m.as_str());
+            sig.push(' ');
+        }
+        sig + &self.field_type + " " + &self.name
+    }
 }
@@ -0,0 +1,46 @@
+use Modifier;
+use DocComment;
+use Annotation;
+
+/// A method parameter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Param {
+    pub param_type: String,
+    pub name: String,
+    pub annotations: Vec<Annotation>,
+}
+
+/// A method, containing modifiers, a return type, a name and a list of parameters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Method {
+    pub modifiers: Vec<Modifier>,
+    pub return_type: String,
+    pub name: String,
+    pub params: Vec<Param>,
+    /// The lines of the method body. Stored as raw source until statements are modelled.
+    pub body: Vec<String>,
+    pub doc: Option<DocComment>,
+    pub annotations: Vec<Annotation>,
+}
+
+impl Method {
+    /// Get the java signature of this method, e.g. `public int foo(String x)`.
+    pub fn signature(&self) -> String {
+        let mut sig = String::new();
+        for m in &self.modifiers {
+            sig.push_str(m.as_str());
+            sig.push(' ');
+        }
+        let params: Vec<String> = self.params
+            .iter()
+            .map(|p| {
+                let mut param = String::new();
+                for a in &p.annotations {
+                    param = param + &a.to_source() + " ";
+                }
+                param + &p.param_type + " " + &p.name
+            })
+            .collect();
+        sig + &format!("{} {}({})", self.return_type, self.name, params.join(", "))
+    }
+}
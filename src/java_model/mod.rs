@@ -4,11 +4,20 @@ mod class;
 mod modifier;
 mod package;
 mod field;
+mod method;
+mod doc;
+mod annotation;
+mod source;
 
 pub use self::class::{MemberType, ClassMember, Class};
-pub use self::modifier::Modifier;
+pub use self::modifier::{Modifier, is_package_private};
 pub use self::package::Package;
 pub use self::field::Field;
+pub use self::method::{Method, Param};
+pub use self::doc::{DocComment, DocTag};
+pub use self::annotation::{Annotation, AnnotationValue, JDK_ANNOTATIONS};
+pub use self::source::{member_source_lines, class_source_lines, class_to_source,
+                       package_info_to_source, parse_class_source, parse_package_info};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Declaration {
@@ -1,4 +1,3 @@
-
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Modifier {
     Transient,
@@ -13,3 +12,54 @@ pub enum Modifier {
     Native,
     Strictfp,
 }
+
+impl Modifier {
+    /// Parse a modifier from its java keyword. Returns None if not a modifier.
+    pub fn parse(s: &str) -> Option<Modifier> {
+        match s {
+            "transient" => Some(Modifier::Transient),
+            "volatile" => Some(Modifier::Volatile),
+            "public" => Some(Modifier::Public),
+            "protected" => Some(Modifier::Protected),
+            "private" => Some(Modifier::Private),
+            "abstract" => Some(Modifier::Abstract),
+            "static" => Some(Modifier::Static),
+            "final" => Some(Modifier::Final),
+            "synchronized" => Some(Modifier::Synchronized),
+            "native" => Some(Modifier::Native),
+            "strictfp" => Some(Modifier::Strictfp),
+            _ => None,
+        }
+    }
+
+    /// Get the java keyword for this modifier
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Modifier::Transient => "transient",
+            Modifier::Volatile => "volatile",
+            Modifier::Public => "public",
+            Modifier::Protected => "protected",
+            Modifier::Private => "private",
+            Modifier::Abstract => "abstract",
+            Modifier::Static => "static",
+            Modifier::Final => "final",
+            Modifier::Synchronized => "synchronized",
+            Modifier::Native => "native",
+            Modifier::Strictfp => "strictfp",
+        }
+    }
+
+    /// True if this is one of the access modifiers (public, protected, private)
+    pub fn is_access(&self) -> bool {
+        match *self {
+            Modifier::Public | Modifier::Protected | Modifier::Private => true,
+            _ => false,
+        }
+    }
+}
+
+/// Returns true if the given list of modifiers has no access modifier - i.e. the item is
+/// package-private.
+pub fn is_package_private(modifiers: &[Modifier]) -> bool {
+    !modifiers.iter().any(|m| m.is_access())
+}
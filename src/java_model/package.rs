@@ -1,4 +1,4 @@
-use super::Declaration;
+use super::{Declaration, DocComment};
 use std;
 
 #[derive(PartialEq, Debug, Clone)]
@@ -7,6 +7,8 @@ pub struct Package {
     pub name: String,
     pub decl_list: Vec<Declaration>,
     pub package_list: Vec<Package>,
+    /// The package documentation, i.e. the doc comment in package-info.java
+    pub doc: Option<DocComment>,
 }
 
 impl Package {
@@ -31,6 +33,7 @@ impl Package {
             name: splits.next().unwrap().to_owned(),
             decl_list: Vec::new(),
             package_list: Vec::new(),
+            doc: None,
         };
         let pkg: *mut Package = root.add_subpackage(name).unwrap();
         // Only return pkg if it's not a pointer to the root (package is returned on the stack, so
@@ -0,0 +1,594 @@
+//! Conversion of classes & package docs to and from java source. Doc comments & annotations are
+//! written out in full, so that they survive a round trip. Inner classes & field initialisers
+//! aren't supported yet.
+
+use common::split_top_level;
+use Class;
+use ClassMember;
+use Field;
+use Method;
+use Param;
+use Modifier;
+use DocComment;
+use Annotation;
+
+/// The indent of each level of nesting in generated source
+pub const INDENT: &'static str = "    ";
+
+/// An error found while parsing java source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceErr {
+    /// The line the error was found on, starting from 1
+    pub line: usize,
+    pub msg: String,
+}
+
+/// Get the source lines of a member, with its doc comment & annotations, indented by the given
+/// amount.
+pub fn member_source_lines(m: &ClassMember, indent: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    if let Some(ref doc) = *m.doc() {
+        lines.extend(doc.to_javadoc(indent).lines().map(|l| l.to_owned()));
+    }
+    for a in m.annotations() {
+        lines.push(format!("{}{}", indent, a.to_source()));
+    }
+    match *m {
+        ClassMember::Field(_) => lines.push(format!("{}{};", indent, m.signature())),
+        ClassMember::Method(ref method) => {
+            let no_body = method.body.len() == 0 &&
+                (method.modifiers.contains(&Modifier::Abstract) ||
+                     method.modifiers.contains(&Modifier::Native));
+            if no_body {
+                lines.push(format!("{}{};", indent, m.signature()));
+            } else {
+                lines.push(format!("{}{} {{", indent, m.signature()));
+                for l in &method.body {
+                    lines.push(format!("{}{}{}", indent, INDENT, l));
+                }
+                lines.push(format!("{}}}", indent));
+            }
+        }
+    }
+    lines
+}
+
+/// Get the source lines of a class, with its doc comment & annotations.
+pub fn class_source_lines(c: &Class) -> Vec<String> {
+    let mut lines = Vec::new();
+    if let Some(ref doc) = c.doc {
+        lines.extend(doc.to_javadoc("").lines().map(|l| l.to_owned()));
+    }
+    for a in &c.annotations {
+        lines.push(a.to_source());
+    }
+    let mut header = String::new();
+    for m in &c.modifiers {
+        header = header + m.as_str() + " ";
+    }
+    header = header + "class " + &c.name;
+    if c.type_params.len() > 0 {
+        header = header + "<" + &c.type_params.join(", ") + ">";
+    }
+    if c.extends.len() > 0 {
+        header = header + " extends " + &c.extends;
+    }
+    if c.implements.len() > 0 {
+        header = header + " implements " + &c.implements.join(", ");
+    }
+    lines.push(header + " {");
+    for (ii, m) in c.members.iter().enumerate() {
+        if ii > 0 {
+            lines.push(String::new());
+        }
+        lines.extend(member_source_lines(m, INDENT));
+    }
+    lines.push("}".to_owned());
+    lines
+}
+
+/// Get the source of a class in the given package, i.e. the contents of its .java file.
+pub fn class_to_source(pkg: &str, c: &Class) -> String {
+    format!("package {};\n\n{}\n", pkg, class_source_lines(c).join("\n"))
+}
+
+/// Get the source of the package-info.java file of a package with the given doc.
+pub fn package_info_to_source(pkg: &str, doc: &Option<DocComment>) -> String {
+    match *doc {
+        Some(ref doc) => format!("{}\npackage {};\n", doc.to_javadoc(""), pkg),
+        None => format!("package {};\n", pkg),
+    }
+}
+
+/// Split the annotations from the start of some source, e.g. `@Override public void foo()`.
+/// Returns None if an annotation is malformed.
+fn split_annotations(src: &str) -> Option<(Vec<Annotation>, &str)> {
+    let mut annotations = Vec::new();
+    let mut rest = src.trim_left();
+    while rest.starts_with('@') {
+        let name_end = rest[1..]
+            .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$' || c == '.'))
+            .map(|ix| ix + 1)
+            .unwrap_or(rest.len());
+        let mut end = name_end;
+        if rest[name_end..].trim_left().starts_with('(') {
+            let open = name_end + rest[name_end..].find('(').unwrap();
+            end = open + matching_close(&rest[open..])? + 1;
+        }
+        annotations.push(Annotation::parse(&rest[..end])?);
+        rest = rest[end..].trim_left();
+    }
+    Some((annotations, rest))
+}
+
+/// Find the bracket closing the one at the start of `src`, skipping string literals. Returns
+/// None if it isn't closed.
+fn matching_close(src: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for (ii, c) in src.char_indices() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        match c {
+            '"' | '\'' => quote = Some(c),
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(ii);
+                }
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Get the change in brace depth over a line of source, skipping string literals & comments.
+fn brace_depth(line: &str) -> i32 {
+    let mut depth = 0;
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    let mut prev = ' ';
+    for c in line.chars() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        match c {
+            '"' | '\'' => quote = Some(c),
+            '/' if prev == '/' => break,
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            _ => (),
+        }
+        prev = c;
+    }
+    depth
+}
+
+/// Split the modifiers from the start of a declaration, e.g. `public static int x`.
+fn split_modifiers(src: &str) -> (Vec<Modifier>, &str) {
+    let mut modifiers = Vec::new();
+    let mut rest = src.trim();
+    loop {
+        let word_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        match Modifier::parse(&rest[..word_end]) {
+            Some(m) if word_end < rest.len() => {
+                modifiers.push(m);
+                rest = rest[word_end..].trim_left();
+            }
+            _ => return (modifiers, rest),
+        }
+    }
+}
+
+/// Split a declaration like `Map<String, Integer> counts` into its type & name.
+fn split_type_name(src: &str) -> Option<(String, String)> {
+    let src = src.trim();
+    let ix = src.rfind(char::is_whitespace)?;
+    let (t, name) = (src[..ix].trim(), src[ix..].trim());
+    if t.len() == 0 || !name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '$') {
+        return None;
+    }
+    Some((t.to_owned(), name.to_owned()))
+}
+
+/// Parses java source a line at a time
+struct SourceParser<'a> {
+    lines: Vec<&'a str>,
+    /// The index of the next line
+    ix: usize,
+}
+
+impl<'a> SourceParser<'a> {
+    fn err<T>(&self, msg: &str) -> Result<T, SourceErr> {
+        Err(SourceErr {
+            line: self.ix,
+            msg: msg.to_owned(),
+        })
+    }
+
+    /// Get the next line which isn't blank or a plain comment. Doc comments are returned whole.
+    fn next_line(&mut self) -> Result<Option<String>, SourceErr> {
+        while self.ix < self.lines.len() {
+            let line = self.lines[self.ix].trim();
+            self.ix += 1;
+            if line.len() == 0 || line.starts_with("//") {
+                continue;
+            }
+            if line.starts_with("/*") {
+                let mut comment = line.to_owned();
+                while !comment.ends_with("*/") || comment.len() < 4 {
+                    if self.ix >= self.lines.len() {
+                        return self.err("Unclosed comment");
+                    }
+                    comment.push('\n');
+                    comment.push_str(self.lines[self.ix].trim());
+                    self.ix += 1;
+                }
+                if comment.starts_with("/**") {
+                    return Ok(Some(comment));
+                }
+                continue;
+            }
+            return Ok(Some(line.to_owned()));
+        }
+        Ok(None)
+    }
+
+    /// Parse the lines of a method body, up to & including the closing brace. The indent of the
+    /// body is removed from each line.
+    fn parse_body(&mut self, indent: &str) -> Result<Vec<String>, SourceErr> {
+        let mut body = Vec::new();
+        let mut depth = 0;
+        while self.ix < self.lines.len() {
+            let line = self.lines[self.ix];
+            self.ix += 1;
+            depth += brace_depth(line);
+            if depth < 0 {
+                if line.trim() != "}" {
+                    return self.err("Expected '}' on its own line to end method");
+                }
+                return Ok(body);
+            }
+            let line = if line.starts_with(indent) {
+                &line[indent.len()..]
+            } else {
+                line.trim_left()
+            };
+            body.push(line.trim_right().to_owned());
+        }
+        self.err("Unclosed method body")
+    }
+
+    /// Parse a member declared on the current line, which has had its annotations removed.
+    fn parse_member(
+        &mut self,
+        decl: &str,
+        doc: Option<DocComment>,
+        annotations: Vec<Annotation>,
+    ) -> Result<ClassMember, SourceErr> {
+        let (modifiers, rest) = split_modifiers(decl);
+        let open = match rest.find(|c| c == '(' || c == '=') {
+            Some(ix) if &rest[ix..ix + 1] == "(" => ix,
+            Some(_) => return self.err("Field initialisers aren't supported"),
+            None => {
+                if !rest.ends_with(';') {
+                    return self.err("Expected ';' after field");
+                }
+                let (field_type, name) = match split_type_name(&rest[..rest.len() - 1]) {
+                    Some(tn) => tn,
+                    None => return self.err("Expected a field type & name"),
+                };
+                return Ok(ClassMember::Field(Field {
+                    modifiers: modifiers,
+                    field_type: field_type,
+                    name: name,
+                    doc: doc,
+                    annotations: annotations,
+                }));
+            }
+        };
+        let (return_type, name) = match split_type_name(&rest[..open]) {
+            Some(tn) => tn,
+            None => return self.err("Expected a return type & method name"),
+        };
+        let close = match matching_close(&rest[open..]) {
+            Some(ix) => open + ix,
+            None => return self.err("Expected ')' after params"),
+        };
+        let mut params = Vec::new();
+        for p in split_top_level(&rest[open + 1..close], true) {
+            let (param_annotations, p) = match split_annotations(&p) {
+                Some(split) => split,
+                None => return self.err("Malformed annotation"),
+            };
+            let (param_type, param_name) = match split_type_name(p) {
+                Some(tn) => tn,
+                None => return self.err("Expected a param type & name"),
+            };
+            params.push(Param {
+                param_type: param_type,
+                name: param_name,
+                annotations: param_annotations,
+            });
+        }
+        let body = match rest[close + 1..].trim() {
+            ";" => Vec::new(),
+            "{" => {
+                let indent = self.lines[self.ix - 1]
+                    .chars()
+                    .take_while(|c| c.is_whitespace())
+                    .collect::<String>() + INDENT;
+                self.parse_body(&indent)?
+            }
+            _ => return self.err("Expected '{' or ';' after method"),
+        };
+        Ok(ClassMember::Method(Method {
+            modifiers: modifiers,
+            return_type: return_type,
+            name: name,
+            params: params,
+            body: body,
+            doc: doc,
+            annotations: annotations,
+        }))
+    }
+
+    /// Parse a package declaration, e.g. `package com.tom;`. Returns the package name.
+    fn parse_package(&self, line: &str) -> Result<String, SourceErr> {
+        let name = line["package ".len()..].trim();
+        if !name.ends_with(';') || name[..name.len() - 1].trim().len() == 0 {
+            return self.err("Expected a package name followed by ';'");
+        }
+        Ok(name[..name.len() - 1].trim().to_owned())
+    }
+
+    /// Parse a class header, e.g. `public class Foo<T> extends Bar implements Baz {`.
+    fn parse_class_header(
+        &self,
+        decl: &str,
+        doc: Option<DocComment>,
+        annotations: Vec<Annotation>,
+    ) -> Result<Class, SourceErr> {
+        let (modifiers, rest) = split_modifiers(decl);
+        if !rest.starts_with("class ") || !rest.ends_with('{') {
+            return self.err("Expected a class");
+        }
+        let mut rest = rest["class ".len()..rest.len() - 1].trim();
+        let mut class = Class::new_empty();
+        class.modifiers = modifiers;
+        class.doc = doc;
+        class.annotations = annotations;
+        let name_end = rest.find(|c: char| c == '<' || c.is_whitespace()).unwrap_or(rest.len());
+        class.name = rest[..name_end].to_owned();
+        rest = rest[name_end..].trim_left();
+        if rest.starts_with('<') {
+            let mut depth = 0;
+            let mut close = None;
+            for (ii, c) in rest.char_indices() {
+                match c {
+                    '<' => depth += 1,
+                    '>' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            close = Some(ii);
+                            break;
+                        }
+                    }
+                    _ => (),
+                }
+            }
+            let close = match close {
+                Some(ix) => ix,
+                None => return self.err("Expected '>' after type params"),
+            };
+            class.type_params = split_top_level(&rest[1..close], true);
+            rest = rest[close + 1..].trim_left();
+        }
+        if rest.starts_with("extends ") {
+            rest = rest["extends ".len()..].trim_left();
+            let end = rest.find(" implements ").unwrap_or(rest.len());
+            class.extends = rest[..end].trim().to_owned();
+            rest = rest[end..].trim_left();
+        }
+        if rest.starts_with("implements ") {
+            class.implements = split_top_level(&rest["implements ".len()..], true);
+            rest = "";
+        }
+        if rest.len() > 0 || class.name.len() == 0 {
+            return self.err("Malformed class header");
+        }
+        Ok(class)
+    }
+}
+
+/// Parse the source of a .java file containing a single class. Returns the package name & the
+/// class.
+pub fn parse_class_source(src: &str) -> Result<(String, Class), SourceErr> {
+    let mut parser = SourceParser {
+        lines: src.lines().collect(),
+        ix: 0,
+    };
+    let mut pkg = None;
+    let mut class: Option<Class> = None;
+    let mut doc = None;
+    while let Some(line) = parser.next_line()? {
+        if line.starts_with("/**") {
+            doc = DocComment::parse_javadoc(&line);
+            continue;
+        }
+        if class.is_none() && line.starts_with("package ") {
+            pkg = Some(parser.parse_package(&line)?);
+            continue;
+        }
+        if class.is_none() && line.starts_with("import ") {
+            continue;
+        }
+        let (mut annotations, decl) = match split_annotations(&line) {
+            Some(split) => split,
+            None => return parser.err("Malformed annotation"),
+        };
+        // Annotations on their own line apply to the next declaration
+        let mut decl = decl.to_owned();
+        while decl.len() == 0 {
+            let next = match parser.next_line()? {
+                Some(next) => next,
+                None => return parser.err("Expected a declaration after annotations"),
+            };
+            let (mut more, rest) = match split_annotations(&next) {
+                Some(split) => split,
+                None => return parser.err("Malformed annotation"),
+            };
+            annotations.append(&mut more);
+            decl = rest.to_owned();
+        }
+        let doc = doc.take();
+        match class {
+            None => class = Some(parser.parse_class_header(&decl, doc, annotations)?),
+            Some(ref mut c) => {
+                if decl == "}" {
+                    return match (pkg, parser.next_line()?) {
+                        (Some(pkg), None) => Ok((pkg, c.clone())),
+                        (None, _) => parser.err("Expected a package declaration"),
+                        (_, Some(_)) => parser.err("Expected only one class"),
+                    };
+                }
+                let member = parser.parse_member(&decl, doc, annotations)?;
+                c.members.push(member);
+            }
+        }
+    }
+    parser.err("Expected a class")
+}
+
+/// Parse the source of a package-info.java file. Returns the package name & its doc.
+pub fn parse_package_info(src: &str) -> Result<(String, Option<DocComment>), SourceErr> {
+    let mut parser = SourceParser {
+        lines: src.lines().collect(),
+        ix: 0,
+    };
+    let mut doc = None;
+    while let Some(line) = parser.next_line()? {
+        if line.starts_with("/**") {
+            doc = DocComment::parse_javadoc(&line);
+        } else if line.starts_with("package ") {
+            return Ok((parser.parse_package(&line)?, doc));
+        } else {
+            return parser.err("Expected a package declaration");
+        }
+    }
+    parser.err("Expected a package declaration")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn class_round_trip() {
+        let mut class = Class::new_with_name("Foo");
+        class.modifiers = vec![Modifier::Public];
+        class.type_params = vec!["K".to_owned(), "V extends Map<K, String>".to_owned()];
+        class.extends = "Bar<K>".to_owned();
+        class.implements = vec!["Runnable".to_owned(), "Comparable<Foo>".to_owned()];
+        class.doc = Some(DocComment::parse("A foo.\n\n@since 1.0"));
+        class.annotations = vec![
+            Annotation::parse("@Entity").unwrap(),
+            Annotation::parse("@Table(name = \"foos\", indexes = {@Index(\"a\")})").unwrap(),
+        ];
+        class.members.push(ClassMember::Field(Field {
+            modifiers: vec![Modifier::Private, Modifier::Final],
+            field_type: "Map<K, V>".to_owned(),
+            name: "map".to_owned(),
+            doc: Some(DocComment::parse("The map")),
+            annotations: vec![Annotation::parse("@Nullable").unwrap()],
+        }));
+        class.members.push(ClassMember::Method(Method {
+            modifiers: vec![Modifier::Public],
+            return_type: "void".to_owned(),
+            name: "run".to_owned(),
+            params: vec![
+                Param {
+                    param_type: "String".to_owned(),
+                    name: "s".to_owned(),
+                    annotations: vec![Annotation::parse("@Named(\"x, y\")").unwrap()],
+                },
+                Param {
+                    param_type: "int".to_owned(),
+                    name: "n".to_owned(),
+                    annotations: Vec::new(),
+                },
+            ],
+            body: vec![
+                "if (n > 0) {".to_owned(),
+                "    print(\"}\");".to_owned(),
+                "}".to_owned(),
+            ],
+            doc: Some(DocComment::parse("Runs.\n\n@param s The s\n@param n The n")),
+            annotations: vec![Annotation::parse("@Override").unwrap()],
+        }));
+        class.members.push(ClassMember::Method(Method {
+            modifiers: vec![Modifier::Abstract],
+            return_type: "int".to_owned(),
+            name: "size".to_owned(),
+            params: Vec::new(),
+            body: Vec::new(),
+            doc: None,
+            annotations: Vec::new(),
+        }));
+
+        let src = class_to_source("com.tom", &class);
+        assert_eq!(parse_class_source(&src), Ok(("com.tom".to_owned(), class)));
+
+        // Annotations on the same line as the declaration, and plain comments
+        let src = "package com.tom;\nimport java.util.List;\n\n// A comment\n\
+                   @Deprecated class Foo {\n    /* Not a doc */\n\
+                   \x20   @Override public void run() {\n        go();\n    }\n}\n";
+        let (_, class) = parse_class_source(src).unwrap();
+        assert_eq!(class.annotations, vec![Annotation::new("Deprecated")]);
+        assert_eq!(class.members[0].annotations(), &[Annotation::new("Override")]);
+        match class.members[0] {
+            ClassMember::Method(ref m) => assert_eq!(m.body, vec!["go();"]),
+            _ => panic!("Expected a method"),
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        let src = "package com.tom;\n\nclass Foo {\n    int x = foo();\n}\n";
+        assert_eq!(parse_class_source(src).unwrap_err().line, 4);
+        let src = "package com.tom;\n\nclass Foo {\n    void run() {\n";
+        assert!(parse_class_source(src).is_err());
+        assert!(parse_class_source("class Foo {\n}\n").is_err());
+        assert!(parse_class_source("package ;\nclass Foo {\n}\n").is_err());
+        assert!(parse_package_info("package com.tom\n").is_err());
+    }
+
+    #[test]
+    fn package_info_round_trip() {
+        let doc = Some(DocComment::parse("Things.\n\n@author tom"));
+        let src = package_info_to_source("com.tom", &doc);
+        assert_eq!(parse_package_info(&src), Ok(("com.tom".to_owned(), doc)));
+        assert_eq!(
+            parse_package_info(&package_info_to_source("com", &None)),
+            Ok(("com".to_owned(), None))
+        );
+    }
+}
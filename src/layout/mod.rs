@@ -0,0 +1,414 @@
+//! The layout of the panes on screen, as a tree of splits. Layouts are saved to the `layouts`
+//! config file, with one line per project directory, so each project opens the way it was left.
+//!
+//! A layout is written in prefix notation - a pane name, or `h` (side by side) or `v` (one above
+//! the other) followed by the fraction of the space given to the first child, then both children.
+//! Each line of the file is a project directory, a tab, then its layout:
+//!
+//! ```text
+//! /home/tom/src/app	h 0.25 tree v 0.7 detail h 0.5 diagnostics preview
+//! ```
+
+use common::Rect;
+use cgmath::Vector2;
+use config;
+use std::env;
+use std::io;
+
+/// The name of the layout file in the config directory
+const LAYOUT_FILE: &'static str = "layouts";
+
+/// The smallest fraction of a split either side can be resized to
+const MIN_RATIO: f32 = 0.1;
+
+/// Every pane, each of which must appear exactly once in a layout
+const ALL_PANES: &'static [Pane] = &[Pane::Tree, Pane::Detail, Pane::Diagnostics, Pane::Preview];
+
+/// A pane which can be placed in the layout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Pane {
+    /// The package tree
+    Tree,
+    /// The detail of the selected package or decl
+    Detail,
+    /// Problems found by refactorings
+    Diagnostics,
+    /// The source of the selected decl or member
+    Preview,
+}
+
+impl Pane {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Pane::Tree => "tree",
+            Pane::Detail => "detail",
+            Pane::Diagnostics => "diagnostics",
+            Pane::Preview => "preview",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Pane> {
+        match name {
+            "tree" => Some(Pane::Tree),
+            "detail" => Some(Pane::Detail),
+            "diagnostics" => Some(Pane::Diagnostics),
+            "preview" => Some(Pane::Preview),
+            _ => None,
+        }
+    }
+}
+
+/// Which way a split divides its space
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitDir {
+    /// The children are side by side
+    Horizontal,
+    /// The first child is above the second
+    Vertical,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Pane(Pane),
+    Split {
+        dir: SplitDir,
+        /// The fraction of the space given to the first child
+        ratio: f32,
+        first: Box<Node>,
+        second: Box<Node>,
+    },
+}
+
+impl Node {
+    fn split(dir: SplitDir, ratio: f32, first: Node, second: Node) -> Node {
+        Node::Split {
+            dir: dir,
+            ratio: ratio,
+            first: Box::new(first),
+            second: Box::new(second),
+        }
+    }
+
+    /// Add the panes in this node to the list, along with the area they cover
+    fn push_rects(&self, bounds: Rect, rects: &mut Vec<(Pane, Rect)>) {
+        match *self {
+            Node::Pane(p) => rects.push((p, bounds)),
+            Node::Split {
+                dir,
+                ratio,
+                ref first,
+                ref second,
+            } => {
+                let (a, b) = split_rect(bounds, dir, ratio);
+                first.push_rects(a, rects);
+                second.push_rects(b, rects);
+            }
+        }
+    }
+
+    fn contains(&self, pane: Pane) -> bool {
+        match *self {
+            Node::Pane(p) => p == pane,
+            Node::Split {
+                ref first,
+                ref second,
+                ..
+            } => first.contains(pane) || second.contains(pane),
+        }
+    }
+
+    /// Resize the innermost split containing the given pane, growing the pane by `delta` of the
+    /// split's space. Returns false if the pane isn't in a split.
+    fn resize(&mut self, pane: Pane, delta: f32) -> bool {
+        match *self {
+            Node::Pane(_) => false,
+            Node::Split {
+                ref mut ratio,
+                ref mut first,
+                ref mut second,
+                ..
+            } => {
+                if first.resize(pane, delta) || second.resize(pane, delta) {
+                    return true;
+                }
+                let delta = if first.contains(pane) {
+                    delta
+                } else if second.contains(pane) {
+                    -delta
+                } else {
+                    return false;
+                };
+                // Keep the ratio to 2 decimal places, so it's saved neatly
+                let new_ratio = ((*ratio + delta) * 100.0).round() / 100.0;
+                *ratio = new_ratio.max(MIN_RATIO).min(1.0 - MIN_RATIO);
+                true
+            }
+        }
+    }
+
+    fn to_string(&self) -> String {
+        match *self {
+            Node::Pane(p) => p.name().to_owned(),
+            Node::Split {
+                dir,
+                ratio,
+                ref first,
+                ref second,
+            } => {
+                let dir = match dir {
+                    SplitDir::Horizontal => "h",
+                    SplitDir::Vertical => "v",
+                };
+                format!("{} {} {} {}", dir, ratio, first.to_string(), second.to_string())
+            }
+        }
+    }
+
+    /// Parse a node from the start of the given words
+    fn parse<'a, I: Iterator<Item = &'a str>>(words: &mut I) -> Result<Node, String> {
+        let word = match words.next() {
+            Some(w) => w,
+            None => return Err("Unexpected end of layout".to_owned()),
+        };
+        let dir = match word {
+            "h" => SplitDir::Horizontal,
+            "v" => SplitDir::Vertical,
+            _ => {
+                return Pane::from_name(word).map(Node::Pane).ok_or_else(|| {
+                    format!("Unknown pane '{}'", word)
+                })
+            }
+        };
+        let ratio = match words.next().map(|r| r.parse::<f32>()) {
+            Some(Ok(r)) if r >= MIN_RATIO && r <= 1.0 - MIN_RATIO => r,
+            _ => return Err("Expected a split ratio from 0.1 to 0.9".to_owned()),
+        };
+        let first = Node::parse(words)?;
+        let second = Node::parse(words)?;
+        Ok(Node::split(dir, ratio, first, second))
+    }
+}
+
+/// Divide a rect in two, giving `ratio` of it to the first part.
+fn split_rect(r: Rect, dir: SplitDir, ratio: f32) -> (Rect, Rect) {
+    match dir {
+        SplitDir::Horizontal => {
+            let w = (r.size.x * ratio).round();
+            (
+                Rect::new(r.left(), r.top(), w, r.size.y),
+                Rect::new(r.left() + w, r.top(), r.size.x - w, r.size.y),
+            )
+        }
+        SplitDir::Vertical => {
+            let h = (r.size.y * ratio).round();
+            (
+                Rect::new(r.left(), r.top(), r.size.x, h),
+                Rect::new(r.left(), r.top() + h, r.size.x, r.size.y - h),
+            )
+        }
+    }
+}
+
+/// The panes on screen, and which of them has focus.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layout {
+    pub root: Node,
+    /// The pane which gets keys in navigation mode
+    pub focus: Pane,
+}
+
+impl Layout {
+    /// The package tree on the left, with the detail pane on the right above the diagnostics &
+    /// preview panes.
+    pub fn new() -> Layout {
+        Layout {
+            root: Node::split(
+                SplitDir::Horizontal,
+                0.25,
+                Node::Pane(Pane::Tree),
+                Node::split(
+                    SplitDir::Vertical,
+                    0.7,
+                    Node::Pane(Pane::Detail),
+                    Node::split(
+                        SplitDir::Horizontal,
+                        0.5,
+                        Node::Pane(Pane::Diagnostics),
+                        Node::Pane(Pane::Preview),
+                    ),
+                ),
+            ),
+            focus: Pane::Tree,
+        }
+    }
+
+    /// Parse a layout, as written by `to_string`. Every pane must appear exactly once. The first
+    /// pane has focus.
+    pub fn parse(s: &str) -> Result<Layout, String> {
+        let mut words = s.split_whitespace();
+        let root = Node::parse(&mut words)?;
+        if words.next().is_some() {
+            return Err("Unexpected text after layout".to_owned());
+        }
+        let mut rects = Vec::new();
+        root.push_rects(Rect::new(0.0, 0.0, 0.0, 0.0), &mut rects);
+        for pane in ALL_PANES {
+            match rects.iter().filter(|&&(p, _)| p == *pane).count() {
+                0 => return Err(format!("Missing pane '{}'", pane.name())),
+                1 => (),
+                _ => return Err(format!("Pane '{}' appears more than once", pane.name())),
+            }
+        }
+        Ok(Layout {
+            root: root,
+            focus: rects[0].0,
+        })
+    }
+
+    pub fn to_string(&self) -> String {
+        self.root.to_string()
+    }
+
+    /// Get the area covered by each pane, when the layout fills the given bounds.
+    pub fn rects(&self, bounds: Rect) -> Vec<(Pane, Rect)> {
+        let mut rects = Vec::new();
+        self.root.push_rects(bounds, &mut rects);
+        rects
+    }
+
+    /// Get the pane which covers the given point
+    pub fn pane_at(&self, bounds: Rect, pos: Vector2<f32>) -> Option<Pane> {
+        self.rects(bounds)
+            .into_iter()
+            .find(|&(_, r)| r.contains(pos))
+            .map(|(p, _)| p)
+    }
+
+    /// Move the focus to the next pane, or the previous if `forward` is false. Panes are ordered
+    /// from the first child of each split to the second.
+    pub fn cycle_focus(&mut self, forward: bool) {
+        let panes: Vec<Pane> = self.rects(Rect::new(0.0, 0.0, 0.0, 0.0))
+            .into_iter()
+            .map(|(p, _)| p)
+            .collect();
+        let ix = panes.iter().position(|&p| p == self.focus).unwrap_or(0);
+        let ix = if forward {
+            (ix + 1) % panes.len()
+        } else {
+            (ix + panes.len() - 1) % panes.len()
+        };
+        self.focus = panes[ix];
+    }
+
+    /// Grow the focused pane by `delta` of the space in its split. A negative delta shrinks it.
+    pub fn resize_focused(&mut self, delta: f32) {
+        let focus = self.focus;
+        self.root.resize(focus, delta);
+    }
+}
+
+/// Get the name of the current project, which layouts are saved under. This is the directory the
+/// editor was started in.
+fn project_name() -> String {
+    env::current_dir()
+        .map(|d| d.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| ".".to_owned())
+}
+
+/// Load the layout saved for the current project, or the default layout if there isn't a valid
+/// one.
+pub fn load_layout() -> Layout {
+    let src = match config::read_config_file(LAYOUT_FILE) {
+        Some(src) => src,
+        None => return Layout::new(),
+    };
+    let project = project_name();
+    for (ii, line) in src.lines().enumerate() {
+        let mut parts = line.splitn(2, '\t');
+        if parts.next() != Some(&project[..]) {
+            continue;
+        }
+        match Layout::parse(parts.next().unwrap_or("")) {
+            Ok(layout) => return layout,
+            Err(e) => println!("Error in layout file, line {}: {}", ii + 1, e),
+        }
+    }
+    Layout::new()
+}
+
+/// Save the layout for the current project, keeping the layouts of other projects.
+pub fn save_layout(layout: &Layout) -> io::Result<()> {
+    let project = project_name();
+    let prefix = project.clone() + "\t";
+    let mut lines: Vec<String> = config::read_config_file(LAYOUT_FILE)
+        .unwrap_or_default()
+        .lines()
+        .filter(|l| l.len() > 0 && !l.starts_with(&prefix[..]))
+        .map(|l| l.to_owned())
+        .collect();
+    lines.push(prefix + &layout.to_string());
+    let mut contents = lines.join("\n");
+    contents.push('\n');
+    config::write_config_file(LAYOUT_FILE, &contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rects() {
+        let layout = Layout::new();
+        let rects = layout.rects(Rect::new(0.0, 0.0, 400.0, 100.0));
+        assert_eq!(
+            rects,
+            vec![
+                (Pane::Tree, Rect::new(0.0, 0.0, 100.0, 100.0)),
+                (Pane::Detail, Rect::new(100.0, 0.0, 300.0, 70.0)),
+                (Pane::Diagnostics, Rect::new(100.0, 70.0, 150.0, 30.0)),
+                (Pane::Preview, Rect::new(250.0, 70.0, 150.0, 30.0)),
+            ]
+        );
+        assert_eq!(
+            layout.pane_at(Rect::new(0.0, 0.0, 400.0, 100.0), Vector2::new(300.0, 80.0)),
+            Some(Pane::Preview)
+        );
+    }
+
+    #[test]
+    fn test_focus_resize() {
+        let mut layout = Layout::new();
+        layout.cycle_focus(false);
+        assert_eq!(layout.focus, Pane::Preview);
+        layout.cycle_focus(true);
+        layout.cycle_focus(true);
+        assert_eq!(layout.focus, Pane::Detail);
+
+        // Growing the detail pane takes space from the panes below it
+        layout.resize_focused(0.1);
+        layout.focus = Pane::Diagnostics;
+        layout.resize_focused(-0.2);
+        assert_eq!(layout.to_string(), "h 0.25 tree v 0.8 detail h 0.3 diagnostics preview");
+        layout.focus = Pane::Tree;
+        layout.resize_focused(-0.5);
+        assert_eq!(layout.to_string(), "h 0.1 tree v 0.8 detail h 0.3 diagnostics preview");
+    }
+
+    #[test]
+    fn test_parse() {
+        let layout = Layout::new();
+        assert_eq!(Layout::parse(&layout.to_string()), Ok(layout));
+        let layout = Layout::parse("v 0.5 preview h 0.5 tree v 0.5 diagnostics detail").unwrap();
+        assert_eq!(layout.focus, Pane::Preview);
+        // Every pane must appear exactly once
+        assert!(Layout::parse("v 0.5 preview tree").is_err());
+        assert!(Layout::parse("v 0.5 preview h 0.5 tree v 0.5 tree detail").is_err());
+        assert!(
+            Layout::parse("v 0.5 preview h 0.5 tree v 0.5 diagnostics h 0.5 detail tree").is_err()
+        );
+        assert!(Layout::parse("h 0.5 tree").is_err());
+        assert!(Layout::parse("h 2 tree detail").is_err());
+        assert!(Layout::parse("h 0.5 tree foo").is_err());
+        assert!(Layout::parse("tree detail").is_err());
+    }
+}
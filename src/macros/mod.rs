@@ -0,0 +1,285 @@
+//! Keyboard macros, which record the user's input so that it can be played back later. Playback
+//! sends the input through the same path as the user's, so counts, navigation mode keys & prompt
+//! editing all work, and the macro acts on whatever is selected when it's played. Macros are saved
+//! to the `macros` config file, so they persist between sessions.
+//!
+//! Each macro starts with a header giving its name, followed by lines of keys (in the notation
+//! accepted by `input::parse_key_seq`) and text typed into prompts. For example:
+//!
+//! ```text
+//! [add-id]
+//! key c f
+//! text \r
+//! text long\r
+//! text id\r
+//! ```
+//!
+//! Text lines can contain the escapes `\r`, `\n` and `\\`.
+
+use input::{parse_key_seq, InputChunk, CTRL, ALT, LOGO};
+use config;
+use std::io;
+
+/// The name of the macro file in the config directory
+const MACRO_FILE: &'static str = "macros";
+
+/// A single recorded input event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MacroEvent {
+    /// A key press
+    Key(InputChunk),
+    /// A char typed into a prompt
+    Char(char),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Macro {
+    pub name: String,
+    pub events: Vec<MacroEvent>,
+}
+
+/// An error in a macro file, with the line number (starting at 1) it occurred on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MacroErr {
+    pub line: usize,
+    pub msg: String,
+}
+
+/// A macro which is currently being recorded.
+pub struct MacroRecorder {
+    name: String,
+    events: Vec<MacroEvent>,
+    /// The index of the first event of the command currently being entered. When recording is
+    /// stopped, the command which stopped it is left out.
+    cmd_start: usize,
+}
+
+impl MacroRecorder {
+    pub fn new(name: &str) -> MacroRecorder {
+        MacroRecorder {
+            name: name.to_owned(),
+            events: Vec::new(),
+            cmd_start: 0,
+        }
+    }
+
+    /// Record a key press.
+    /// # Params
+    /// * `in_prompt` - True if a prompt is open. Keys which just type a char into the prompt are
+    /// left out, since the char is recorded separately.
+    /// * `new_cmd` - True if this key starts a new command in the command buffer.
+    pub fn key(&mut self, i: InputChunk, in_prompt: bool, new_cmd: bool) {
+        // Modifier keys on their own are ignored by the command buffer & prompts
+        if i.is_modifier() {
+            return;
+        }
+        if new_cmd {
+            self.cmd_start = self.events.len();
+        }
+        if in_prompt && i.1 & (CTRL | ALT | LOGO) == 0 {
+            if let Some(s) = i.to_key_seq() {
+                if s.chars().count() == 1 {
+                    return;
+                }
+            }
+        }
+        self.events.push(MacroEvent::Key(i));
+    }
+
+    /// Record a char. Chars are only recorded if a prompt is open, since they're ignored
+    /// otherwise.
+    pub fn char(&mut self, c: char, in_prompt: bool) {
+        if !in_prompt || (c.is_control() && c != '\r' && c != '\n') {
+            return;
+        }
+        self.events.push(MacroEvent::Char(c));
+    }
+
+    /// Stop recording, leaving out the command which stopped the recording.
+    pub fn finish(mut self) -> Macro {
+        self.events.truncate(self.cmd_start);
+        Macro {
+            name: self.name,
+            events: self.events,
+        }
+    }
+}
+
+fn err(line: usize, msg: &str) -> MacroErr {
+    MacroErr {
+        line: line,
+        msg: msg.to_owned(),
+    }
+}
+
+/// Parse the contents of a macro file.
+pub fn parse_macros(src: &str) -> Result<Vec<Macro>, MacroErr> {
+    let mut macros: Vec<Macro> = Vec::new();
+    for (ii, line) in src.lines().enumerate() {
+        let line_num = ii + 1;
+        if line.trim().len() == 0 || line.trim_left().starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') {
+            let line = line.trim();
+            if !line.ends_with(']') || line.len() < 3 {
+                return Err(err(line_num, "Bad macro header"));
+            }
+            macros.push(Macro {
+                name: line[1..line.len() - 1].trim().to_owned(),
+                events: Vec::new(),
+            });
+            continue;
+        }
+        let m = match macros.last_mut() {
+            Some(m) => m,
+            None => return Err(err(line_num, "Line outside of a macro")),
+        };
+        if line.starts_with("key ") {
+            match parse_key_seq(&line[4..]) {
+                Ok(keys) => m.events.extend(keys.into_iter().map(MacroEvent::Key)),
+                Err(e) => return Err(err(line_num, &e)),
+            }
+        } else if line.starts_with("text ") {
+            let mut chars = line[5..].chars();
+            while let Some(c) = chars.next() {
+                let c = if c == '\\' {
+                    match chars.next() {
+                        Some('r') => '\r',
+                        Some('n') => '\n',
+                        Some('\\') => '\\',
+                        _ => return Err(err(line_num, "Unknown escape")),
+                    }
+                } else {
+                    c
+                };
+                m.events.push(MacroEvent::Char(c));
+            }
+        } else {
+            return Err(err(line_num, "Unknown macro line"));
+        }
+    }
+    Ok(macros)
+}
+
+/// Convert macros to the format read by `parse_macros`. Keys which can't be written are left out.
+pub fn macros_to_string(macros: &[Macro]) -> String {
+    let mut s = String::new();
+    for m in macros {
+        s += &format!("[{}]\n", m.name);
+        let mut line = String::new();
+        for e in &m.events {
+            match *e {
+                MacroEvent::Key(i) => {
+                    let key = match i.to_key_seq() {
+                        Some(k) => k,
+                        None => continue,
+                    };
+                    if line.starts_with("text ") {
+                        s += &line;
+                        s += "\n";
+                        line.clear();
+                    }
+                    line += if line.len() == 0 { "key " } else { " " };
+                    line += &key;
+                }
+                MacroEvent::Char(c) => {
+                    if line.starts_with("key ") {
+                        s += &line;
+                        s += "\n";
+                        line.clear();
+                    }
+                    if line.len() == 0 {
+                        line += "text ";
+                    }
+                    match c {
+                        '\r' => line += "\\r",
+                        '\n' => line += "\\n",
+                        '\\' => line += "\\\\",
+                        c => line.push(c),
+                    }
+                    // End the line after a return, so each prompt answer is on its own line
+                    if c == '\r' {
+                        s += &line;
+                        s += "\n";
+                        line.clear();
+                    }
+                }
+            }
+        }
+        if line.len() > 0 {
+            s += &line;
+            s += "\n";
+        }
+    }
+    s
+}
+
+/// Load the macros from the config directory. Returns an empty list if there's no macro file, or
+/// it contains an error.
+pub fn load_macros() -> Vec<Macro> {
+    match config::read_config_file(MACRO_FILE) {
+        Some(src) => {
+            match parse_macros(&src) {
+                Ok(m) => m,
+                Err(e) => {
+                    println!("Error in macro file, line {}: {}", e.line, e.msg);
+                    Vec::new()
+                }
+            }
+        }
+        None => Vec::new(),
+    }
+}
+
+/// Save the macros to the config directory, replacing the macro file.
+pub fn save_macros(macros: &[Macro]) -> io::Result<()> {
+    config::write_config_file(MACRO_FILE, &macros_to_string(macros))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use winit::VirtualKeyCode as VKC;
+
+    #[test]
+    fn test_record() {
+        let mut rec = MacroRecorder::new("test");
+        // Keys in the command buffer are recorded, chars aren't
+        rec.key(InputChunk(VKC::C, 0), false, true);
+        rec.char('c', false);
+        rec.key(InputChunk(VKC::F, 0), false, false);
+        // Chars in prompts are recorded, keys which type them aren't
+        rec.key(InputChunk(VKC::A, 0), true, false);
+        rec.char('a', true);
+        rec.key(InputChunk(VKC::Tab, 0), true, false);
+        rec.char('\t', true);
+        rec.char('\r', true);
+        // The command stopping the recording is left out
+        rec.key(InputChunk(VKC::M, 0), false, true);
+        rec.key(InputChunk(VKC::R, 0), false, false);
+        let m = rec.finish();
+        assert_eq!(
+            m.events,
+            vec![
+                MacroEvent::Key(InputChunk(VKC::C, 0)),
+                MacroEvent::Key(InputChunk(VKC::F, 0)),
+                MacroEvent::Char('a'),
+                MacroEvent::Key(InputChunk(VKC::Tab, 0)),
+                MacroEvent::Char('\r'),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let src = "[add-id]\nkey c f\ntext a\\\\b\\r\nkey Tab\ntext  id\\n\\r\n";
+        let macros = parse_macros(src).unwrap();
+        assert_eq!(macros.len(), 1);
+        assert_eq!(macros[0].name, "add-id");
+        assert_eq!(macros[0].events.len(), 12);
+        assert_eq!(macros_to_string(&macros), src);
+        assert!(parse_macros("key c").is_err());
+        assert!(parse_macros("[m]\ntext \\x").is_err());
+    }
+}
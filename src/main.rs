@@ -7,6 +7,9 @@ extern crate quick_gfx as qgfx;
 extern crate cgmath;
 extern crate winit;
 extern crate smallvec;
+extern crate crossbeam;
+extern crate num_cpus;
+extern crate unicode_normalization;
 
 mod common;
 mod command;
@@ -16,34 +19,206 @@ mod java_model;
 mod state;
 mod input;
 mod search;
+mod refactor;
+mod config;
+mod template;
+mod macros;
+mod theme;
+mod layout;
 
 use std::collections::HashSet;
 use java_model::*;
+use common::Rect;
+use view::View;
 
-/// Poll the command buffer & execute the command
-fn poll_cmd_buffer(state: std::sync::Arc<state::State>) {
-    // Poll command buffer & execute command
+/// Poll the command buffer & execute the command. Returns true if a command was run.
+fn poll_cmd_buffer(state: std::sync::Arc<state::State>) -> bool {
+    // Poll command buffer & execute command. Commands stay queued while a prompt is open, so
+    // that counted commands prompt one after the other.
+    state.command_buffer.lock().unwrap().poll_keymap();
+    if state.curr_prompt.lock().unwrap().is_some() {
+        return false;
+    }
+    let cmd = state.command_buffer.lock().unwrap().poll_cmd();
+    let ran = cmd.is_some();
+    if let Some(cmd) = cmd {
+        run_cmd(state.clone(), cmd);
+    }
+    // Any args from the command line which weren't used by the command are discarded
+    state.pending_args.lock().unwrap().take();
+    ran
+}
+
+/// Run a command, recording the answers to its prompts so that it can be repeated
+fn run_cmd(state: std::sync::Arc<state::State>, cmd: command::Command) {
+    use command::*;
+    // The command line & repeat commands run other commands, which are recorded instead
+    *state.recording.lock().unwrap() = match cmd {
+        Command::CommandLine |
+        Command::Repeat |
+        Command::Macro(MacroCommand(MacroAction::Record)) => None,
+        _ => Some((cmd.clone(), Vec::new())),
+    };
+    dispatch_cmd(state.clone(), cmd);
+    state.finish_command();
+}
+
+/// Run the function for a command
+fn dispatch_cmd(state: std::sync::Arc<state::State>, cmd: command::Command) {
     use command::*;
-    match state.command_buffer.lock().unwrap().poll_cmd() {
-        Some(Command::Create(CreateCommand(CreateObject::Class))) => {
-            command::create_class(state.clone());
+    match cmd {
+        Command::Create(CreateCommand(CreateObject::Class)) => {
+            command::create_class(state);
+        }
+        Command::Create(CreateCommand(CreateObject::Field)) => {
+            command::create_field(state);
+        }
+        Command::Create(CreateCommand(CreateObject::Package)) => {
+            command::create_package(state);
+        }
+        Command::Select(SelectCommand(SelectObject::Package)) => {
+            command::select_package(state);
+        }
+        Command::Select(SelectCommand(SelectObject::Class)) => {
+            command::select_decl(state);
+        }
+        Command::Select(SelectCommand(SelectObject::Symbol)) => {
+            command::select_symbol(state);
+        }
+        Command::Select(SelectCommand(SelectObject::Theme)) => {
+            command::select_theme(state);
+        }
+        Command::Refactor(RefactorCommand(RefactorKind::PullUp)) => {
+            command::pull_up_member(state);
         }
-        Some(Command::Create(CreateCommand(CreateObject::Field))) => {
-            command::create_field(state.clone());
+        Command::Refactor(RefactorCommand(RefactorKind::PushDown)) => {
+            command::push_down_member(state);
         }
-        Some(Command::Create(CreateCommand(CreateObject::Package))) => {
-            command::create_package(state.clone());
+        Command::Refactor(RefactorCommand(RefactorKind::EncapsulateField)) => {
+            command::encapsulate_field(state);
         }
-        Some(Command::Select(SelectCommand(SelectObject::Package))) => {
-            command::select_package(state.clone());
+        Command::Refactor(RefactorCommand(RefactorKind::ChangeSignature)) => {
+            command::change_signature(state);
         }
-        Some(Command::Select(SelectCommand(SelectObject::Class))) => {
-            command::select_decl(state.clone());
+        Command::Refactor(RefactorCommand(RefactorKind::Rename)) => {
+            command::rename_decl(state);
+        }
+        Command::Doc(DocCommand(obj)) => {
+            command::edit_doc(state, obj);
+        }
+        Command::Annotate(AnnotateCommand(obj)) => {
+            command::annotate(state, obj);
+        }
+        Command::Template(name) => {
+            command::expand_template(state, &name);
+        }
+        Command::Macro(MacroCommand(MacroAction::Record)) => {
+            command::record_macro(state);
+        }
+        Command::Macro(MacroCommand(MacroAction::Play)) => {
+            command::play_macro(state);
+        }
+        Command::Pane(PaneCommand(action)) => {
+            command::pane_command(state, action);
+        }
+        Command::Source(SourceCommand(SourceAction::Import)) => {
+            command::import_source(state);
+        }
+        Command::Source(SourceCommand(SourceAction::Export)) => {
+            command::export_source(state);
+        }
+        Command::Navigate => {
+            command::navigate(state);
+        }
+        Command::CommandLine => {
+            command::command_line(state);
+        }
+        Command::Repeat => {
+            let last_cmd = state.last_cmd.lock().unwrap().clone();
+            match last_cmd {
+                Some((cmd, answers)) => {
+                    *state.pending_args.lock().unwrap() = Some(answers);
+                    run_cmd(state, cmd);
+                }
+                None => println!("No command to repeat"),
+            }
         }
-        None => (),
     }
 }
 
+/// The most macro events to play back each frame, so that the UI stays responsive & C-g can
+/// abort a long (or endless) macro
+const MACRO_EVENTS_PER_FRAME: usize = 500;
+
+/// Play back any queued macro events, through the same path as the user's input. The commands
+/// they queue are run after each event, so that commands & prompts run just as they did when the
+/// macro was recorded.
+fn poll_macro_playback(state: std::sync::Arc<state::State>, panes: &view::Panes) {
+    if !*state.playing_macro.lock().unwrap() {
+        if state.macro_playback.lock().unwrap().is_empty() {
+            return;
+        }
+        // Commands queued before the macro, like the rest of a counted play command, wait for
+        // it to finish
+        *state.playing_macro.lock().unwrap() = true;
+        *state.held_cmds.lock().unwrap() = state.command_buffer.lock().unwrap().take_cmds();
+    }
+    for _ in 0..MACRO_EVENTS_PER_FRAME {
+        let event = state.macro_playback.lock().unwrap().pop_front();
+        match event {
+            Some(e) => {
+                let focus = state.layout.lock().unwrap().focus;
+                state::State::input_event(state.clone(), e, panes.get(focus));
+                while poll_cmd_buffer(state.clone()) {}
+            }
+            None => {
+                *state.playing_macro.lock().unwrap() = false;
+                let held = std::mem::replace(&mut *state.held_cmds.lock().unwrap(), Vec::new());
+                state.command_buffer.lock().unwrap().push_cmds_front(held);
+                return;
+            }
+        }
+    }
+}
+
+/// Fonts to try when the theme's font can't be loaded
+const FALLBACK_FONTS: &'static [&'static str] = &[
+    theme::DEFAULT_FONT,
+    "/usr/share/fonts/truetype/dejavu/DejaVuSansMono-Bold.ttf",
+    "/usr/share/fonts/TTF/DejaVuSansMono-Bold.ttf",
+    "/usr/share/fonts/truetype/freefont/FreeMonoBold.ttf",
+    "/Library/Fonts/Courier New Bold.ttf",
+    "C:\\Windows\\Fonts\\courbd.ttf",
+];
+
+/// Load the font given by the theme, falling back to the first of `FALLBACK_FONTS` which can be
+/// loaded. Returns None if no font could be loaded.
+fn load_font(
+    g: &mut qgfx::QGFX,
+    theme: &theme::Theme,
+    charset: &[char],
+) -> Option<qgfx::FontHandle> {
+    let fonts = Some(&theme.font[..]).into_iter().chain(
+        FALLBACK_FONTS.iter().cloned().filter(|&f| f != theme.font),
+    );
+    for path in fonts {
+        if !std::path::Path::new(path).is_file() {
+            println!("Font file {} not found", path);
+            continue;
+        }
+        match g.cache_glyphs(path, theme.font_size, charset) {
+            Ok(fh) => {
+                if path != theme.font {
+                    println!("Using font {} instead of {}", path, theme.font);
+                }
+                return Some(fh);
+            }
+            Err(e) => println!("Failed to load font {}: {:?}", path, e),
+        }
+    }
+    None
+}
+
 fn main() {
     // Initialise state
     let state = std::sync::Arc::new(state::State::new());
@@ -57,11 +232,18 @@ fn main() {
     charsets.insert(qgfx::Charset::Uppercase);
     charsets.insert(qgfx::Charset::Numbers);
     charsets.insert(qgfx::Charset::Punctuation);
-    let fh = g.cache_glyphs(
-        "assets/FreeMonoBold.ttf",
-        12.0,
-        &qgfx::gen_charset(&charsets)[..],
-    ).unwrap();
+    let charset = qgfx::gen_charset(&charsets);
+    let mut theme = state.theme.lock().unwrap().theme().clone();
+    let fh = match load_font(&mut g, &theme, &charset[..]) {
+        Some(fh) => fh,
+        None => {
+            println!(
+                "No font could be loaded. Set one with a 'font' line in {}",
+                config::config_path(theme::THEME_FILE).display()
+            );
+            std::process::exit(1);
+        }
+    };
 
     let mut closed = false;
 
@@ -71,22 +253,49 @@ fn main() {
             state.project.add_decl(&format!("com.tom.package{}", jj), Declaration::Class(class));
         }
     }
-    state.project.regen_decl_completion_list();
-    state.project.regen_pkg_completion_list();
 
     // Create views
-    let package_view = view::PackageListView::new(state.clone(), fh);
-    let command_buffer_view = view::CommandBufferView::new(state.clone(), fh);
-    let prompt_input_view = view::PromptInputView::new(state.clone(), fh);
+    let mut panes = view::Panes::new(state.clone(), fh);
+    let mut command_buffer_view = view::CommandBufferView::new(state.clone(), fh);
+    let mut prompt_input_view = view::PromptInputView::new(state.clone(), fh);
+    let mut continuations_view = view::ContinuationsView::new(state.clone(), fh);
+
+    let mut mouse = input::MouseTracker::new();
+    // The area the panes were last laid out in, for finding which pane is under the mouse
+    let mut pane_area;
 
     while !closed {
+        // Load the font again if the theme's changed it. If it can't be loaded, keep the old one.
+        state.theme.lock().unwrap().poll();
+        let new_theme = state.theme.lock().unwrap().theme().clone();
+        if new_theme.font != theme.font || new_theme.font_size != theme.font_size {
+            if let Some(fh) = load_font(&mut g, &new_theme, &charset[..]) {
+                panes.set_font(fh);
+                command_buffer_view.font = fh;
+                prompt_input_view.font = fh;
+                continuations_view.font = fh;
+            }
+        }
+        theme = new_theme;
+
         {
+            // The panes share the screen above the bar, which holds the command buffer & prompt
             let (display_w, display_h) = g.get_display_size();
-            let screen_size = cgmath::Vector2::new(display_w as f32, display_h as f32);
+            let (display_w, display_h) = (display_w as f32, display_h as f32);
+            let bar_top = (display_h - theme.bar_height).max(0.0);
+            pane_area = Rect::new(0.0, 0.0, display_w, bar_top);
+            let bar = Rect::new(0.0, bar_top, display_w, theme.bar_height);
+            let layout = state.layout.lock().unwrap().clone();
             let mut controller = g.get_renderer_controller();
-            package_view.render(&mut controller, screen_size.clone());
-            command_buffer_view.render(&mut controller, screen_size.clone());
-            prompt_input_view.render(&mut controller, screen_size.clone());
+            for (pane, r) in layout.rects(pane_area) {
+                panes.get(pane).render(&mut controller, r);
+                if pane == layout.focus {
+                    view::render_focus(&mut controller, r, &theme);
+                }
+            }
+            command_buffer_view.render(&mut controller, bar);
+            continuations_view.render(&mut controller, bar);
+            prompt_input_view.render(&mut controller, bar);
             controller.flush();
         }
 
@@ -98,7 +307,25 @@ fn main() {
                 event: ev,
                 window_id: _,
             } => {
-                if !state::State::process_input(state.clone(), &ev) {
+                let focus = state.layout.lock().unwrap().focus;
+                if let Some(m) = mouse.process(&ev) {
+                    // The prompt is drawn over the panes, so it gets the first look. Clicking a
+                    // pane focuses it.
+                    if prompt_input_view.mouse_input(m) {
+                        return;
+                    }
+                    let pane = state.layout.lock().unwrap().pane_at(pane_area, m.pos());
+                    if let Some(pane) = pane {
+                        match m {
+                            input::MouseEvent::Click(_) |
+                            input::MouseEvent::DoubleClick(_) => {
+                                state.layout.lock().unwrap().focus = pane;
+                            }
+                            _ => (),
+                        }
+                        panes.get(pane).mouse_input(m);
+                    }
+                } else if !state::State::process_input(state.clone(), &ev, panes.get(focus)) {
                     match ev {
                         qgfx::WindowEvent::Closed => closed = true,
                         _ => (),
@@ -109,5 +336,6 @@ fn main() {
         });
 
         poll_cmd_buffer(state.clone());
+        poll_macro_playback(state.clone(), &panes);
     }
 }
@@ -0,0 +1,131 @@
+//! History of the values accepted by prompts, kept per prompt label & saved to the `history`
+//! config file.
+//!
+//! The file has a header for each prompt label, followed by its values, most recent first:
+//!
+//! ```text
+//! [Package]
+//! com.tom.model
+//! com.tom.view
+//! ```
+//!
+//! Values starting with `[` or `\` are escaped with a `\`.
+
+use config;
+use std::io;
+
+/// The name of the history file in the config directory
+const HISTORY_FILE: &'static str = "history";
+
+/// The most values to keep for each prompt label
+const MAX_ENTRIES: usize = 50;
+
+pub struct History {
+    /// Prompt labels, along with their values, most recent first
+    entries: Vec<(String, Vec<String>)>,
+}
+
+impl History {
+    pub fn new() -> History {
+        History { entries: Vec::new() }
+    }
+
+    /// Get the values accepted by prompts with the given label, most recent first
+    pub fn get(&self, label: &str) -> &[String] {
+        match self.entries.iter().find(|&&(ref l, _)| l == label) {
+            Some(&(_, ref vals)) => &vals[..],
+            None => &[],
+        }
+    }
+
+    /// Add a value accepted by a prompt with the given label, moving it to the front if it's
+    /// already in the history.
+    pub fn add(&mut self, label: &str, val: &str) {
+        if val.len() == 0 || val.contains('\n') {
+            return;
+        }
+        if !self.entries.iter().any(|&(ref l, _)| l == label) {
+            self.entries.push((label.to_owned(), Vec::new()));
+        }
+        let vals = &mut self.entries.iter_mut().find(|&&mut (ref l, _)| l == label).unwrap().1;
+        vals.retain(|v| v != val);
+        vals.insert(0, val.to_owned());
+        vals.truncate(MAX_ENTRIES);
+    }
+
+    /// Parse the contents of a history file. Lines before the first header are ignored.
+    pub fn parse(src: &str) -> History {
+        let mut history = History::new();
+        for line in src.lines() {
+            if line.starts_with('[') && line.ends_with(']') {
+                history.entries.push((line[1..line.len() - 1].to_owned(), Vec::new()));
+            } else if let Some(&mut (_, ref mut vals)) = history.entries.last_mut() {
+                let val = if line.starts_with('\\') { &line[1..] } else { line };
+                if val.len() > 0 {
+                    vals.push(val.to_owned());
+                }
+            }
+        }
+        history
+    }
+
+    /// Convert to the format read by `parse`
+    pub fn to_string(&self) -> String {
+        let mut s = String::new();
+        for &(ref label, ref vals) in &self.entries {
+            s += &format!("[{}]\n", label);
+            for v in vals {
+                if v.starts_with('[') || v.starts_with('\\') {
+                    s.push('\\');
+                }
+                s += v;
+                s.push('\n');
+            }
+        }
+        s
+    }
+}
+
+/// Load the prompt history from the config directory. Returns an empty history if there's no
+/// history file.
+pub fn load_history() -> History {
+    match config::read_config_file(HISTORY_FILE) {
+        Some(src) => History::parse(&src),
+        None => History::new(),
+    }
+}
+
+/// Save the prompt history to the config directory.
+pub fn save_history(history: &History) -> io::Result<()> {
+    config::write_config_file(HISTORY_FILE, &history.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add() {
+        let mut history = History::new();
+        history.add("Package", "com.a");
+        history.add("Package", "com.b");
+        history.add("Package", "com.a");
+        history.add("Package", "");
+        history.add("Name", "Foo");
+        assert_eq!(history.get("Package"), &["com.a", "com.b"]);
+        assert_eq!(history.get("Name"), &["Foo"]);
+        assert_eq!(history.get("Other").len(), 0);
+        for ii in 0..MAX_ENTRIES + 1 {
+            history.add("Name", &ii.to_string());
+        }
+        assert_eq!(history.get("Name").len(), MAX_ENTRIES);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let src = "[Package]\ncom.a\n[Annotation]\n\\[x\n\\\\y\n";
+        let history = History::parse(src);
+        assert_eq!(history.get("Annotation"), &["[x", "\\y"]);
+        assert_eq!(history.to_string(), src);
+    }
+}
@@ -2,11 +2,18 @@ use state::State;
 use std::sync::Arc;
 use winit::VirtualKeyCode as VKC;
 use std::boxed::Box;
-use input::InputChunk;
+use input::{InputChunk, SHIFT, CTRL, ALT};
+use common;
+use search::{self, SearchMatch};
 
 mod prompt_type;
+mod history;
 
-pub use self::prompt_type::PromptType;
+pub use self::prompt_type::{PromptType, to_completions};
+
+/// The most completions to show for a prompt
+pub const MAX_COMPLETIONS: usize = 10;
+pub use self::history::*;
 
 /// A prompt. The first string is the text of the prompt - what is displayed to the user - and the
 /// boolean indicates whether or not to accept empty input. True for empty allowed - false if empty
@@ -23,7 +30,6 @@ impl Prompt {
         Prompt(val.to_owned(), true, None)
     }
     /// Specify a default value
-    #[allow(dead_code)]
     pub fn new_with_default(val: &str, default: &str) -> Prompt {
         Prompt(val.to_owned(), false, Some(default.to_owned()))
     }
@@ -49,16 +55,63 @@ pub struct PromptInput {
     curr_prompt: usize,
 
     /// A list of the current completions
-    curr_completions: Vec<String>,
+    curr_completions: Vec<Completion>,
 
     /// Contains the index of the completion to use, or None if no completion selected
     active_completion: Option<usize>,
+
+    /// Set when S-<RET> is pressed in a multi-line prompt, so that the following return char
+    /// inserts a newline rather than finishing the prompt.
+    newline_pending: bool,
+
+    /// Set when an alt chord is used for editing, so that any char it also produced isn't
+    /// inserted. Cleared on the next key press.
+    skip_char: bool,
+
+    /// The text last killed with C-w or C-u, which is inserted by C-y
+    kill_buf: String,
+
+    /// The values previously accepted by each prompt, most recent first
+    history: Vec<Vec<String>>,
+
+    /// The prompt whose history is being browsed, and the index of the value shown
+    history_pos: Option<(usize, usize)>,
+
+    /// The input before browsing the history, which is restored when browsing past the most
+    /// recent value
+    history_draft: String,
+
+    /// The prompt & value of the completion last picked with `pick_completion`, so that a
+    /// double click accepts what the first click picked
+    picked: Option<(usize, String)>,
+}
+
+/// A suggested value for a prompt
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Completion {
+    pub val: String,
+    /// The byte positions of the chars in `val` which matched the input
+    pub positions: Vec<usize>,
+    /// Extra info shown after the completion, e.g. what kind of symbol it is
+    pub detail: Option<String>,
+}
+
+impl Completion {
+    pub fn from_match(m: SearchMatch) -> Completion {
+        Completion {
+            val: m.text.to_owned(),
+            positions: m.positions,
+            detail: None,
+        }
+    }
 }
 
 /// The result of a prompt
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct PromptResult {
     pub val: String,
+    /// The position of the cursor in `val`, in bytes
+    pub cursor: usize,
     /// Only applies to prompts which can be completed. If false, the user entered a custom value
     /// which did not have a completion.
     pub completion_match: bool,
@@ -67,10 +120,115 @@ pub struct PromptResult {
 impl PromptResult {
     pub fn new(val: String) -> PromptResult {
         PromptResult {
+            cursor: val.len(),
             val: val,
             completion_match: false,
         }
     }
+
+    /// Replace the value, moving the cursor to the end
+    pub fn set_val(&mut self, val: String) {
+        self.cursor = val.len();
+        self.val = val;
+    }
+
+    /// Insert a string at the cursor
+    pub fn insert(&mut self, s: &str) {
+        self.val.insert_str(self.cursor, s);
+        self.cursor += s.len();
+    }
+
+    /// Get the position of the char before the cursor
+    fn prev_pos(&self) -> usize {
+        match self.val[..self.cursor].chars().next_back() {
+            Some(c) => self.cursor - c.len_utf8(),
+            None => 0,
+        }
+    }
+
+    /// Get the position of the char after the cursor
+    fn next_pos(&self) -> usize {
+        match self.val[self.cursor..].chars().next() {
+            Some(c) => self.cursor + c.len_utf8(),
+            None => self.val.len(),
+        }
+    }
+
+    pub fn left(&mut self) {
+        self.cursor = self.prev_pos();
+    }
+
+    pub fn right(&mut self) {
+        self.cursor = self.next_pos();
+    }
+
+    /// Move to the start of the current line
+    pub fn home(&mut self) {
+        self.cursor = self.val[..self.cursor].rfind('\n').map(|ix| ix + 1).unwrap_or(0);
+    }
+
+    /// Move to the end of the current line
+    pub fn end(&mut self) {
+        self.cursor += self.val[self.cursor..].find('\n').unwrap_or(
+            self.val.len() - self.cursor,
+        );
+    }
+
+    /// Get the position of the start of the word before the cursor
+    fn word_start(&self) -> usize {
+        let before = &self.val[..self.cursor];
+        let end = before.trim_right_matches(|c| !is_word_char(c)).len();
+        before[..end].trim_right_matches(is_word_char).len()
+    }
+
+    /// Get the position of the end of the word after the cursor
+    fn word_end(&self) -> usize {
+        let after = &self.val[self.cursor..];
+        let start = after.len() - after.trim_left_matches(|c| !is_word_char(c)).len();
+        let rest = after[start..].trim_left_matches(is_word_char);
+        self.val.len() - rest.len()
+    }
+
+    pub fn word_left(&mut self) {
+        self.cursor = self.word_start();
+    }
+
+    pub fn word_right(&mut self) {
+        self.cursor = self.word_end();
+    }
+
+    /// Delete the char before the cursor
+    pub fn backspace(&mut self) {
+        let start = self.prev_pos();
+        self.val.drain(start..self.cursor);
+        self.cursor = start;
+    }
+
+    /// Delete the char after the cursor
+    pub fn delete(&mut self) {
+        let end = self.next_pos();
+        self.val.drain(self.cursor..end);
+    }
+
+    /// Delete the word before the cursor, returning the deleted text
+    pub fn kill_word(&mut self) -> String {
+        let start = self.word_start();
+        let killed = self.val.drain(start..self.cursor).collect();
+        self.cursor = start;
+        killed
+    }
+
+    /// Delete from the start of the line to the cursor, returning the deleted text
+    pub fn kill_line(&mut self) -> String {
+        let end = self.cursor;
+        self.home();
+        self.val.drain(self.cursor..end).collect()
+    }
+}
+
+/// Returns true if the char is part of a word, for word motions
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
 }
 
 impl PromptInput {
@@ -95,6 +253,11 @@ impl PromptInput {
             }
             curr_prompt += 1;
         }
+        // If everything has a default, start on the last prompt
+        if curr_prompt >= prompts.len() {
+            curr_prompt = prompts.len() - 1;
+        }
+        let prompts_history = vec![Vec::new(); prompts.len()];
 
         PromptInput {
             inputs: inputs,
@@ -103,27 +266,77 @@ impl PromptInput {
             curr_prompt: curr_prompt,
             curr_completions: Vec::new(),
             active_completion: None,
+            newline_pending: false,
+            skip_char: false,
+            kill_buf: String::new(),
+            history: prompts_history,
+            history_pos: None,
+            history_draft: String::new(),
+            picked: None,
         }
     }
 
+    /// Set the values previously accepted by each prompt, most recent first. These can be
+    /// browsed with Up & Down, and are ranked above other completions.
+    pub fn set_history(&mut self, history: Vec<Vec<String>>) {
+        self.history = history;
+        self.history.resize(self.prompts.len(), Vec::new());
+    }
+
     pub fn get_active_completion(&self) -> Option<usize> {
         self.active_completion
     }
 
-    pub fn get_completions(&self) -> &[String] {
+    pub fn get_completions(&self) -> &[Completion] {
         &self.curr_completions[..]
     }
 
+    /// Select the active completion, if any, so that it can be edited
+    fn take_completion(&mut self) {
+        if let Some(ix) = self.active_completion.take() {
+            let completion = self.curr_completions[ix].val.clone();
+            self.inputs[self.curr_prompt].set_val(completion);
+        }
+    }
+
+    /// Use the completion with the given index, as if it had been tabbed to
+    pub fn pick_completion(&mut self, ix: usize) {
+        if ix < self.curr_completions.len() {
+            self.active_completion = Some(ix);
+            self.take_completion();
+            self.picked = Some((self.curr_prompt, self.inputs[self.curr_prompt].val.clone()));
+        }
+    }
+
+    /// Accept the completion last picked with `pick_completion`, as if return had been pressed.
+    /// Nothing happens if the input has changed since it was picked. Returns true if the prompt
+    /// finished here, in which case `finish` should be called to run the callback.
+    pub fn accept_picked(&mut self) -> bool {
+        match self.picked.take() {
+            Some((prompt, ref val)) if prompt == self.curr_prompt &&
+                                       *val == self.inputs[self.curr_prompt].val => {
+                self.active_completion = None;
+                self.char_input('\r')
+            }
+            _ => false,
+        }
+    }
+
     /// Key input for 'control' inputs, like S-<TAB> for example
     pub fn key_input(&mut self, i: InputChunk) {
+        self.skip_char = false;
+        if self.edit_key_input(i) || self.history_key_input(i) {
+            self.skip_char = i.1 & ALT != 0;
+            return;
+        }
         match (i.0, i.1) {
-            (VKC::Tab, 0b1000) => {
+            (VKC::Tab, SHIFT) => {
                 if self.active_completion.is_some() {
-                    // Decrement completion
-                    if self.active_completion.unwrap() >= self.curr_completions.len() - 1 {
+                    // Go back to the previous (better) completion
+                    if self.active_completion.unwrap() == 0 {
                         self.active_completion = None
                     } else {
-                        self.active_completion = Some(self.active_completion.unwrap() + 1);
+                        self.active_completion = Some(self.active_completion.unwrap() - 1);
                     }
                 } else {
                     // Return to prev prompt
@@ -132,53 +345,132 @@ impl PromptInput {
                     }
                 }
             }
-            (VKC::Tab, 0b0000) => {
-                // Select completion
+            (VKC::Tab, 0) => {
+                // Select completion, starting from the best
                 if self.active_completion.is_none() {
                     if self.curr_completions.len() > 0 {
-                        self.active_completion = Some(self.curr_completions.len() - 1);
+                        self.active_completion = Some(0);
                     }
-                } else if self.active_completion.unwrap() <= 0 {
+                } else if self.active_completion.unwrap() + 1 >= self.curr_completions.len() {
                     self.active_completion = None;
                 } else {
-                    self.active_completion = Some(self.active_completion.unwrap() - 1);
+                    self.active_completion = Some(self.active_completion.unwrap() + 1);
                 }
             }
-            (VKC::Back, _) => {
-                if self.inputs[self.curr_prompt].val.len() > 0 {
-                    self.inputs[self.curr_prompt].val.pop();
+            (VKC::Return, SHIFT) => {
+                if let PromptType::MultiLine(_) = self.prompts[self.curr_prompt] {
+                    self.newline_pending = true;
                 }
             }
             _ => (),
         }
     }
 
-    /// Call to input a char in the prompt. If the user finished the prompt with this input, the
-    /// callback will be called.
+    /// Handle keys for browsing the history of the current prompt. Returns false if the key isn't
+    /// a history key.
+    fn history_key_input(&mut self, i: InputChunk) -> bool {
+        let older = match (i.0, i.1) {
+            (VKC::Up, 0) | (VKC::P, ALT) => true,
+            (VKC::Down, 0) | (VKC::N, ALT) => false,
+            _ => return false,
+        };
+        let history = &self.history[self.curr_prompt];
+        // Browsing starts again from the input if it's been edited since it was recalled, or the
+        // user has moved to another prompt, so that the edited input is kept as the draft
+        let input = &self.inputs[self.curr_prompt].val;
+        let pos = match self.history_pos {
+            Some((prompt, pos)) if prompt == self.curr_prompt && history[pos] == *input => {
+                Some(pos)
+            }
+            _ => None,
+        };
+        let new_pos = match (pos, older) {
+            (None, true) if history.len() > 0 => Some(0),
+            (Some(p), true) if p + 1 < history.len() => Some(p + 1),
+            (Some(p), false) if p > 0 => Some(p - 1),
+            (Some(_), false) => None,
+            _ => return true,
+        };
+        if pos.is_none() {
+            self.history_draft = input.clone();
+        }
+        let val = match new_pos {
+            Some(p) => history[p].clone(),
+            None => self.history_draft.clone(),
+        };
+        self.history_pos = new_pos.map(|p| (self.curr_prompt, p));
+        self.inputs[self.curr_prompt].set_val(val);
+        self.active_completion = None;
+        true
+    }
+
+    /// Handle keys for moving the cursor & editing the current input. Returns false if the key
+    /// isn't an editing key.
+    fn edit_key_input(&mut self, i: InputChunk) -> bool {
+        let multi_line = match self.prompts[self.curr_prompt] {
+            PromptType::MultiLine(_) => true,
+            _ => false,
+        };
+        match (i.0, i.1) {
+            (VKC::Left, 0) | (VKC::B, CTRL) |
+            (VKC::Right, 0) | (VKC::F, CTRL) |
+            (VKC::Home, 0) | (VKC::A, CTRL) |
+            (VKC::End, 0) | (VKC::E, CTRL) |
+            (VKC::Left, CTRL) | (VKC::B, ALT) |
+            (VKC::Right, CTRL) | (VKC::F, ALT) |
+            (VKC::Back, _) | (VKC::Delete, 0) | (VKC::D, CTRL) |
+            (VKC::W, CTRL) | (VKC::U, CTRL) | (VKC::Y, CTRL) |
+            (VKC::V, CTRL) | (VKC::Insert, SHIFT) => self.take_completion(),
+            _ => return false,
+        }
+        let input = &mut self.inputs[self.curr_prompt];
+        match (i.0, i.1) {
+            (VKC::Left, 0) | (VKC::B, CTRL) => input.left(),
+            (VKC::Right, 0) | (VKC::F, CTRL) => input.right(),
+            (VKC::Home, 0) | (VKC::A, CTRL) => input.home(),
+            (VKC::End, 0) | (VKC::E, CTRL) => input.end(),
+            (VKC::Left, CTRL) | (VKC::B, ALT) => input.word_left(),
+            (VKC::Right, CTRL) | (VKC::F, ALT) => input.word_right(),
+            (VKC::Back, _) => input.backspace(),
+            (VKC::Delete, 0) | (VKC::D, CTRL) => input.delete(),
+            (VKC::W, CTRL) => self.kill_buf = input.kill_word(),
+            (VKC::U, CTRL) => self.kill_buf = input.kill_line(),
+            (VKC::Y, CTRL) => input.insert(&self.kill_buf),
+            _ => {
+                // Paste from the clipboard. Single line prompts get a single line.
+                if let Some(s) = common::read_clipboard() {
+                    let s = s.trim_right_matches(|c| c == '\r' || c == '\n');
+                    if multi_line {
+                        input.insert(s);
+                    } else {
+                        input.insert(&s.replace(|c| c == '\r' || c == '\n', " "));
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    /// Call to input a char in the prompt.
     ///
-    /// Returns true if prompt finished here.
+    /// Returns true if prompt finished here, in which case `finish` should be called to run the
+    /// callback.
     pub fn char_input(&mut self, c: char) -> bool {
+        if self.skip_char {
+            self.skip_char = false;
+            if !c.is_control() {
+                return false;
+            }
+        }
         match c {
+            '\r' | '\n' if self.newline_pending => {
+                self.newline_pending = false;
+                self.inputs[self.curr_prompt].insert("\n");
+            }
             '\r' | '\n' => {
-                if self.active_completion.is_some() {
-                    self.inputs[self.curr_prompt].val =
-                        self.curr_completions[self.active_completion.unwrap()].clone();
-                    self.active_completion = None;
-                }
+                self.take_completion();
                 self.curr_prompt += 1;
                 if self.curr_prompt >= self.prompts.len() {
-                    // Loop through inputs, set the completion flag
-                    for i in &mut self.inputs {
-                        let mut completion_found = false;
-                        for c in &self.curr_completions {
-                            if c == &i.val {
-                                completion_found = true;
-                                break;
-                            }
-                        }
-                        i.completion_match = if completion_found { true } else { false }
-                    }
-                    (self.callback)(&self.inputs[..]);
                     return true;
                 }
             }
@@ -186,22 +478,67 @@ impl PromptInput {
                 if !c.is_control() {
                     // If we have a completion selected, then we select that before inserting the
                     // next char
-                    if self.active_completion.is_some() {
-                        self.inputs[self.curr_prompt].val =
-                            self.curr_completions[self.active_completion.unwrap()].clone();
-                        self.active_completion = None;
-                    }
-                    self.inputs[self.curr_prompt].val.push(c);
+                    self.take_completion();
+                    let mut buf = [0; 4];
+                    self.inputs[self.curr_prompt].insert(c.encode_utf8(&mut buf));
                 }
             }
         }
         return false;
     }
 
-    /// Update the completions on this prompt
+    /// Fill in the inputs with the given values, in order, and move to the first prompt without a
+    /// value. Returns whether every prompt was filled, along with any values left over.
+    pub fn fill(&mut self, mut vals: Vec<String>) -> (bool, Vec<String>) {
+        let leftover = if vals.len() > self.prompts.len() {
+            vals.split_off(self.prompts.len())
+        } else {
+            Vec::new()
+        };
+        let num_filled = vals.len();
+        for (ii, v) in vals.into_iter().enumerate() {
+            self.inputs[ii] = PromptResult::new(v);
+        }
+        if num_filled >= self.prompts.len() {
+            return (true, leftover);
+        }
+        if num_filled > 0 {
+            self.curr_prompt = num_filled;
+        }
+        (false, leftover)
+    }
+
+    /// Consume this prompt, calling the callback with the user's inputs. This is separate from
+    /// `char_input` so that the callback can be run once the prompt has been removed from the
+    /// state, allowing callbacks to open new prompts.
+    pub fn finish(mut self, state: Arc<State>) {
+        // Check against every possible completion, not just the few which were shown
+        for (p, i) in self.prompts.iter().zip(self.inputs.iter_mut()) {
+            i.completion_match = p.is_completion(state.clone(), &i.val);
+        }
+        (self.callback)(&self.inputs[..]);
+    }
+
+    /// Update the completions on this prompt. Values from the history which match the input
+    /// come first, most recent first. String prompts have no other completions, so any matching
+    /// value from the history is used.
     pub fn update_completions(&mut self, state: Arc<State>) {
-        self.curr_completions =
-            self.prompts[self.curr_prompt].complete(state, &self.inputs[self.curr_prompt].val)
+        let input = &self.inputs[self.curr_prompt].val;
+        let prompt = &self.prompts[self.curr_prompt];
+        let mut completions = prompt.complete(state.clone(), input);
+        // Recent values are only offered if they're still valid, e.g. the class hasn't been removed
+        let mut recent: Vec<Completion> = self.history[self.curr_prompt]
+            .iter()
+            .filter_map(|h| search::match_str(input, h).map(Completion::from_match))
+            .filter(|h| match *prompt {
+                PromptType::String(_) => true,
+                _ => prompt.is_completion(state.clone(), &h.val),
+            })
+            .collect();
+        completions.retain(|c| !recent.iter().any(|r| r.val == c.val));
+        recent.extend(completions);
+        recent.truncate(MAX_COMPLETIONS);
+        self.curr_completions = recent;
     }
 
     /// Get the index of the current prompt we're editing
@@ -213,4 +550,182 @@ impl PromptInput {
     pub fn get_curr_input(&self) -> &str {
         &self.inputs[self.curr_prompt].val
     }
+
+    /// Get the position of the cursor in the current user's input, in bytes
+    pub fn get_curr_cursor(&self) -> usize {
+        self.inputs[self.curr_prompt].cursor
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_motions() {
+        let mut r = PromptResult::new("foo_bar.baz qux".to_owned());
+        r.word_left();
+        assert_eq!(r.cursor, 12);
+        r.word_left();
+        assert_eq!(r.cursor, 8);
+        r.word_left();
+        assert_eq!(r.cursor, 0);
+        r.word_right();
+        assert_eq!(r.cursor, 7);
+        r.end();
+        assert_eq!(r.cursor, 15);
+        r.home();
+        assert_eq!(r.cursor, 0);
+
+        // Motions are by char, and home/end are per line
+        let mut r = PromptResult::new("ab\néx".to_owned());
+        r.left();
+        r.left();
+        assert_eq!(&r.val[r.cursor..], "éx");
+        r.home();
+        assert_eq!(r.cursor, 3);
+        r.left();
+        r.end();
+        assert_eq!(r.cursor, 2);
+    }
+
+    #[test]
+    fn test_editing() {
+        let mut r = PromptResult::new("int fooBar".to_owned());
+        assert_eq!(r.kill_word(), "fooBar");
+        r.insert("x");
+        r.left();
+        r.left();
+        r.backspace();
+        assert_eq!(r.val, "in x");
+        r.delete();
+        assert_eq!(r.val, "inx");
+        r.right();
+        assert_eq!(r.kill_line(), "inx");
+        assert_eq!(r.val, "");
+        assert_eq!(r.cursor, 0);
+        r.backspace();
+        r.delete();
+        assert_eq!(r.val, "");
+    }
+
+    #[test]
+    fn test_history() {
+        let prompts = vec![PromptType::String(Prompt::new("Name"))];
+        let mut p = PromptInput::new(prompts, Box::new(|_| ()));
+        p.set_history(vec![vec!["Foo".to_owned(), "Bar".to_owned()]]);
+        p.char_input('x');
+        p.key_input(InputChunk(VKC::Up, 0));
+        assert_eq!(p.get_curr_input(), "Foo");
+        p.key_input(InputChunk(VKC::P, ALT));
+        p.key_input(InputChunk(VKC::Up, 0));
+        assert_eq!(p.get_curr_input(), "Bar");
+        p.key_input(InputChunk(VKC::Down, 0));
+        p.key_input(InputChunk(VKC::N, ALT));
+        assert_eq!(p.get_curr_input(), "x");
+
+        // Editing a recalled value makes it the new draft
+        p.key_input(InputChunk(VKC::Up, 0));
+        p.key_input(InputChunk(VKC::Up, 0));
+        p.char_input('y');
+        p.key_input(InputChunk(VKC::Up, 0));
+        assert_eq!(p.get_curr_input(), "Foo");
+        p.key_input(InputChunk(VKC::Down, 0));
+        assert_eq!(p.get_curr_input(), "Bary");
+        p.key_input(InputChunk(VKC::Down, 0));
+        assert_eq!(p.get_curr_input(), "Bary");
+    }
+
+    #[test]
+    fn test_completion_match() {
+        use std::sync::Mutex;
+        use java_model::{Class, Declaration};
+        let state = Arc::new(State::new());
+        // More classes than can be shown, so that the exact match isn't in the shown completions
+        for ii in 0..MAX_COMPLETIONS * 2 {
+            let class = Class::new_with_name(&format!("Foo{}", ii));
+            state.project.add_decl("com", Declaration::Class(class));
+        }
+        let prompts = vec![
+            PromptType::Decl(Prompt::new("Class")),
+            PromptType::Decl(Prompt::new("Other")),
+        ];
+        let matches = Arc::new(Mutex::new(Vec::new()));
+        let matches_clone = matches.clone();
+        let mut p = PromptInput::new(
+            prompts,
+            Box::new(move |data| {
+                *matches_clone.lock().unwrap() = data.iter().map(|r| r.completion_match).collect()
+            }),
+        );
+        let val = format!("com.Foo{}", MAX_COMPLETIONS * 2 - 1);
+        p.update_completions(state.clone());
+        assert!(!p.get_completions().iter().any(|c| c.val == val));
+        assert_eq!(p.fill(vec![val, "com.Bar".to_owned()]), (true, Vec::new()));
+        p.finish(state);
+        assert_eq!(*matches.lock().unwrap(), vec![true, false]);
+    }
+
+    #[test]
+    fn test_history_completions() {
+        use java_model::{Class, Declaration};
+        let state = Arc::new(State::new());
+        for ii in 0..MAX_COMPLETIONS * 2 {
+            let class = Class::new_with_name(&format!("Foo{}", ii));
+            state.project.add_decl("com", Declaration::Class(class));
+        }
+        let prompts = vec![PromptType::Decl(Prompt::new("Class"))];
+        let mut p = PromptInput::new(prompts, Box::new(|_| ()));
+        let val = format!("com.Foo{}", MAX_COMPLETIONS * 2 - 1);
+        p.set_history(vec![vec![val.clone(), "com.Gone".to_owned()]]);
+        // A recent value is offered first even if it wouldn't be one of the best matches, but not
+        // if it's no longer valid
+        p.update_completions(state.clone());
+        let completions: Vec<&str> = p.get_completions().iter().map(|c| &c.val[..]).collect();
+        assert_eq!(completions.len(), MAX_COMPLETIONS);
+        assert_eq!(completions[0], val);
+        assert!(!completions.contains(&"com.Gone"));
+    }
+
+    #[test]
+    fn test_accept_picked() {
+        let state = Arc::new(State::new());
+        let new_prompt = || {
+            let prompts = vec![
+                PromptType::String(Prompt::new("Name")),
+                PromptType::String(Prompt::new("Type")),
+            ];
+            let mut p = PromptInput::new(prompts, Box::new(|_| ()));
+            p.set_history(vec![vec!["Foo".to_owned(), "Bar".to_owned()], vec!["int".to_owned()]]);
+            p
+        };
+        let mut p = new_prompt();
+        // Nothing has been picked yet
+        assert!(!p.accept_picked());
+        assert_eq!(p.get_curr_prompt(), 0);
+
+        p.update_completions(state.clone());
+        p.pick_completion(1);
+        assert_eq!(p.get_curr_input(), "Bar");
+        // The completions now only match the picked value, so the row which was clicked is gone -
+        // the double click accepts what the first click picked, moving to the next prompt
+        p.update_completions(state.clone());
+        assert_eq!(p.get_completions().len(), 1);
+        assert!(!p.accept_picked());
+        assert_eq!(p.get_curr_prompt(), 1);
+        assert_eq!(p.inputs[0].val, "Bar");
+        // Accepting the last prompt finishes it
+        p.update_completions(state.clone());
+        p.pick_completion(0);
+        assert!(p.accept_picked());
+
+        // Editing after picking means the double click is ignored
+        let mut p = new_prompt();
+        p.update_completions(state.clone());
+        p.pick_completion(0);
+        p.char_input('x');
+        assert!(!p.accept_picked());
+        assert_eq!(p.get_curr_prompt(), 0);
+        assert_eq!(p.get_curr_input(), "Foox");
+    }
 }
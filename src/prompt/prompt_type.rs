@@ -1,6 +1,10 @@
-use super::Prompt;
+use super::{Prompt, Completion, MAX_COMPLETIONS};
 use state::State;
 use std::sync::Arc;
+use search::{SearchBuffer, SearchMatch};
+use command;
+use state::Selection;
+use theme::THEME_NAMES;
 
 /// A section of a PromptInput. Wraps a prompt, and adds useful info regarding autocompletions -
 /// for example, a Package(Prompt) will be subject to package autocompletion.
@@ -12,6 +16,18 @@ pub enum PromptType {
     Package(Prompt),
     /// Declaration prompt. This allows tab completion for qualified class / interface / etc names.
     Decl(Prompt),
+    /// Multi-line string prompt. S-<RET> inserts a newline rather than finishing the prompt.
+    MultiLine(Prompt),
+    /// Annotation prompt. This allows tab completion for annotation types in the project & JDK.
+    Annotation(Prompt),
+    /// Macro prompt. This allows tab completion for the names of recorded macros.
+    Macro(Prompt),
+    /// Command line prompt. This allows tab completion for command names & their arguments.
+    CommandLine(Prompt),
+    /// Symbol prompt. This allows tab completion for any package, decl or member in the project.
+    Symbol(Prompt),
+    /// Theme prompt. This allows tab completion for the names of the built-in themes.
+    Theme(Prompt),
 }
 
 impl PromptType {
@@ -20,6 +36,12 @@ impl PromptType {
             PromptType::String(ref p) => &p.0,
             PromptType::Package(ref p) => &p.0,
             PromptType::Decl(ref p) => &p.0,
+            PromptType::MultiLine(ref p) => &p.0,
+            PromptType::Annotation(ref p) => &p.0,
+            PromptType::Macro(ref p) => &p.0,
+            PromptType::CommandLine(ref p) => &p.0,
+            PromptType::Symbol(ref p) => &p.0,
+            PromptType::Theme(ref p) => &p.0,
         }
     }
 
@@ -28,36 +50,115 @@ impl PromptType {
             PromptType::String(ref p) => &p.2,
             PromptType::Package(ref p) => &p.2,
             PromptType::Decl(ref p) => &p.2,
+            PromptType::MultiLine(ref p) => &p.2,
+            PromptType::Annotation(ref p) => &p.2,
+            PromptType::Macro(ref p) => &p.2,
+            PromptType::CommandLine(ref p) => &p.2,
+            PromptType::Symbol(ref p) => &p.2,
+            PromptType::Theme(ref p) => &p.2,
         }
     }
 
     /// Suggest completionsof a given string based on the type of this type and the current
-    /// program state. Returns vec of len 0 if no completion available.
-    pub fn complete(&self, state: Arc<State>, input: &str) -> Vec<String> {
+    /// program state. Returns vec of len 0 if no completion available. Completions are ranked
+    /// best first, and at most MAX_COMPLETIONS are returned.
+    pub fn complete(&self, state: Arc<State>, input: &str) -> Vec<Completion> {
         match *self {
-            PromptType::String(_) => Vec::new(),
+            PromptType::String(_) |
+            PromptType::MultiLine(_) => Vec::new(),
             PromptType::Package(_) => {
-                state
-                    .project
-                    .pkg_completion_list
-                    .lock()
-                    .unwrap()
-                    .find_all_subsequences(input)
-                    .into_iter()
-                    .map(|s| s.to_owned())
-                    .collect()
+                to_completions(state.project.pkg_completion_list.lock().unwrap().find_ranked(
+                    input,
+                    MAX_COMPLETIONS,
+                ))
             }
             PromptType::Decl(_) => {
-                state
-                    .project
-                    .decl_completion_list
-                    .lock()
-                    .unwrap()
-                    .find_all_subsequences(input)
-                    .into_iter()
-                    .map(|s| s.to_owned())
-                    .collect()
+                to_completions(state.project.decl_completion_list.lock().unwrap().find_ranked(
+                    input,
+                    MAX_COMPLETIONS,
+                ))
             }
+            PromptType::Annotation(_) => {
+                let decl_list = state.project.decl_completion_list.lock().unwrap();
+                let mut completions =
+                    state.project.jdk_annotation_list.find_ranked(input, MAX_COMPLETIONS);
+                completions.extend(decl_list.find_ranked(input, MAX_COMPLETIONS));
+                completions.sort_by(|a, b| b.score.cmp(&a.score));
+                completions.truncate(MAX_COMPLETIONS);
+                to_completions(completions)
+            }
+            PromptType::Macro(_) => {
+                let mut names = SearchBuffer::new();
+                let macro_names: Vec<String> =
+                    state.macros.lock().unwrap().iter().map(|m| m.name.clone()).collect();
+                names.add_strings_owned(&macro_names[..]);
+                to_completions(names.find_ranked(input, MAX_COMPLETIONS))
+            }
+            PromptType::CommandLine(_) => command::complete_command_line(state, input),
+            PromptType::Symbol(_) => {
+                let mut completions = to_completions(
+                    state.project.symbol_completion_list.lock().unwrap().find_ranked(
+                        input,
+                        MAX_COMPLETIONS,
+                    ),
+                );
+                // Show what kind of symbol each completion is, and what contains it
+                for c in completions.iter_mut() {
+                    c.detail = state.project.find_symbol(&c.val).map(|sel| symbol_detail(&sel));
+                }
+                completions
+            }
+            PromptType::Theme(_) => {
+                let mut names = SearchBuffer::new();
+                names.add_strings(THEME_NAMES);
+                to_completions(names.find_ranked(input, MAX_COMPLETIONS))
+            }
+        }
+    }
+
+    /// Returns true if the given value is one of the possible completions of this prompt. This
+    /// checks every completion, not just the best few returned by `complete`.
+    pub fn is_completion(&self, state: Arc<State>, val: &str) -> bool {
+        match *self {
+            PromptType::String(_) |
+            PromptType::MultiLine(_) => false,
+            PromptType::Package(_) => {
+                state.project.pkg_completion_list.lock().unwrap().contains(val)
+            }
+            PromptType::Decl(_) => {
+                state.project.decl_completion_list.lock().unwrap().contains(val)
+            }
+            PromptType::Annotation(_) => {
+                state.project.jdk_annotation_list.contains(val) ||
+                    state.project.decl_completion_list.lock().unwrap().contains(val)
+            }
+            PromptType::Macro(_) => state.macros.lock().unwrap().iter().any(|m| m.name == val),
+            PromptType::CommandLine(_) => {
+                command::complete_command_line(state, val).iter().any(|c| c.val == val)
+            }
+            PromptType::Symbol(_) => {
+                state.project.symbol_completion_list.lock().unwrap().contains(val)
+            }
+            PromptType::Theme(_) => THEME_NAMES.contains(&val),
         }
     }
 }
+
+/// Describe the kind of a selected symbol, and what contains it - e.g. `method in com.tom.Foo`.
+fn symbol_detail(sel: &Selection) -> String {
+    let parent = |name: &str| match name.rfind('.') {
+        Some(ix) => format!(" in {}", &name[..ix]),
+        None => String::new(),
+    };
+    match *sel {
+        Selection::Package(ref p) => format!("package{}", parent(p)),
+        Selection::Decl(ref d) => format!("class{}", parent(d)),
+        Selection::Field(ref d, _) => format!("field in {}", d),
+        Selection::Method(ref d, _) => format!("method in {}", d),
+    }
+}
+
+/// Turn the results of a ranked search into completions
+pub fn to_completions(matches: Vec<SearchMatch>) -> Vec<Completion> {
+    matches.into_iter().map(Completion::from_match).collect()
+}
@@ -0,0 +1,498 @@
+//! Refactoring to hide a field behind a getter and setter.
+
+use java_model::*;
+use super::*;
+
+/// Capitalise the first char of a field name, for use in an accessor name.
+fn capitalise(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) => c.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Get the getter name for a field - `isFoo` for booleans, otherwise `getFoo`.
+pub fn getter_name(field: &Field) -> String {
+    if field.field_type == "boolean" {
+        format!("is{}", capitalise(&field.name))
+    } else {
+        format!("get{}", capitalise(&field.name))
+    }
+}
+
+/// Get the setter name for a field.
+pub fn setter_name(field: &Field) -> String {
+    format!("set{}", capitalise(&field.name))
+}
+
+/// How an access to the field is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Access {
+    /// An access which isn't to the encapsulated field
+    Other,
+    /// An access which should go through the accessors
+    Rewrite,
+    /// An access which should go through the accessors, but can't be rewritten
+    Unknown,
+}
+
+/// Find the end of the expression assigned in `rest`, i.e. the next `;` outside brackets & string
+/// literals. Returns None if the statement doesn't end on this line.
+fn assigned_expr_end(rest: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for (ii, ch) in rest.char_indices() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if ch == '\\' {
+                escaped = true;
+            } else if ch == q {
+                quote = None;
+            }
+            continue;
+        }
+        match ch {
+            '"' | '\'' => quote = Some(ch),
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            ';' if depth == 0 => return Some(ii),
+            _ => (),
+        }
+        if depth < 0 {
+            return None;
+        }
+    }
+    None
+}
+
+/// Rewrite the accesses to a field in a line of source. Reads become calls to `getter`, and
+/// assignment statements become calls to `setter` (if there is one). `resolve` is given the
+/// receiver of each access (see `member_receiver`) and says how to treat it. Accesses which can't
+/// be rewritten are left alone, and a message is returned for each.
+fn rewrite_accesses(
+    line: &str,
+    field_name: &str,
+    getter: &str,
+    setter: Option<&str>,
+    resolve: &Fn(Option<&str>) -> Access,
+) -> (String, Vec<String>) {
+    let mut out = String::with_capacity(line.len());
+    let mut errors = Vec::new();
+    let mut rest = line;
+    let mut quote: Option<char> = None;
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+    while let Some(c) = rest.chars().next() {
+        // Skip string literals
+        if let Some(q) = quote {
+            let len = c.len_utf8();
+            if c == '\\' && rest.len() > 1 {
+                let next_len = rest[1..].chars().next().unwrap().len_utf8();
+                out.push_str(&rest[..1 + next_len]);
+                rest = &rest[1 + next_len..];
+                continue;
+            }
+            if c == q {
+                quote = None;
+            }
+            out.push_str(&rest[..len]);
+            rest = &rest[len..];
+            continue;
+        }
+        if c == '"' || c == '\'' {
+            quote = Some(c);
+        }
+        let prev_is_ident = out.chars().last().map(&is_ident) == Some(true);
+        if prev_is_ident || !rest.starts_with(field_name) {
+            out.push(c);
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+        let after = &rest[field_name.len()..];
+        if after.chars().next().map(&is_ident) == Some(true) || after.trim_left().starts_with('(') {
+            out.push(c);
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+        let receiver = member_receiver(&out);
+        let access = resolve(receiver.map(|(_, r)| r));
+        if access == Access::Other {
+            out.push_str(field_name);
+            rest = after;
+            continue;
+        }
+        let desc = match receiver {
+            Some((_, r)) => format!("{}.{}", r, field_name),
+            None => field_name.to_owned(),
+        };
+        if access == Access::Unknown {
+            errors.push(format!("Can't tell if {} is the encapsulated field", desc));
+            out.push_str(field_name);
+            rest = after;
+            continue;
+        }
+        let before = &out[..receiver.map(|(start, _)| start).unwrap_or(out.len())];
+        let op = after.trim_left();
+        let increment = op.starts_with("++") || op.starts_with("--") ||
+            before.trim_right().ends_with("++") ||
+            before.trim_right().ends_with("--");
+        let compound = ["+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=", "<<=", ">>="]
+            .iter()
+            .any(|o| op.starts_with(o));
+        if increment || compound {
+            errors.push(format!("Can't rewrite update of {}", desc));
+            out.push_str(field_name);
+            rest = after;
+            continue;
+        }
+        if !(op.starts_with('=') && !op.starts_with("==")) {
+            out.push_str(getter);
+            out.push_str("()");
+            rest = after;
+            continue;
+        }
+        // An assignment, which can only be rewritten if it's a whole statement
+        let statement_start = before.trim_right().len() == 0 ||
+            before.trim_right().ends_with(|c: char| c == ';' || c == '{' || c == '}');
+        let value = &op[1..];
+        let value_end = assigned_expr_end(value);
+        match (setter, statement_start, value_end) {
+            (Some(setter), true, Some(end)) => {
+                let expr = value[..end].trim();
+                let (new_value, mut value_errors) =
+                    rewrite_accesses(expr, field_name, getter, Some(setter), resolve);
+                errors.append(&mut value_errors);
+                out.push_str(setter);
+                out.push('(');
+                out.push_str(&new_value);
+                out.push(')');
+                rest = &value[end..];
+            }
+            (None, _, _) => {
+                errors.push(format!("Can't assign to {}, which has no setter", desc));
+                out.push_str(field_name);
+                rest = after;
+            }
+            _ => {
+                errors.push(format!("Can't rewrite assignment to {}", desc));
+                out.push_str(field_name);
+                rest = after;
+            }
+        }
+    }
+    (out, errors)
+}
+
+/// Make the given field private, and add a public getter and setter for it. Final fields only
+/// get a getter. Returns the names of the getter & setter.
+fn add_accessors(
+    pkgs: &mut [Package],
+    class_name: &str,
+    field_name: &str,
+) -> Result<(String, Option<String>), RefactorErr> {
+    let class = find_class_mut(pkgs, class_name).ok_or(RefactorErr::DeclNotFound(
+        class_name.to_owned(),
+    ))?;
+    let field = match class.find_member(field_name) {
+        Some(&ClassMember::Field(ref f)) => f.clone(),
+        _ => return Err(RefactorErr::MemberNotFound(field_name.to_owned())),
+    };
+
+    let is_static = field.modifiers.contains(&Modifier::Static);
+    let is_final = field.modifiers.contains(&Modifier::Final);
+    // Qualifier used to access the field from within the accessors
+    let qualifier = if is_static { class.name.clone() } else { "this".to_owned() };
+    let mut accessor_modifiers = vec![Modifier::Public];
+    if is_static {
+        accessor_modifiers.push(Modifier::Static);
+    }
+
+    let mut accessors = vec![
+        Method {
+            modifiers: accessor_modifiers.clone(),
+            return_type: field.field_type.clone(),
+            name: getter_name(&field),
+            params: Vec::new(),
+            body: vec![format!("return {}.{};", qualifier, field.name)],
+            doc: None,
+            annotations: Vec::new(),
+        },
+    ];
+    if !is_final {
+        accessors.push(Method {
+            modifiers: accessor_modifiers,
+            return_type: "void".to_owned(),
+            name: setter_name(&field),
+            params: vec![
+                Param {
+                    param_type: field.field_type.clone(),
+                    name: field.name.clone(),
+                    annotations: Vec::new(),
+                },
+            ],
+            body: vec![format!("{}.{} = {};", qualifier, field.name, field.name)],
+            doc: None,
+            annotations: Vec::new(),
+        });
+    }
+    for a in &accessors {
+        if class.find_member(&a.name).is_some() {
+            return Err(RefactorErr::NameConflict(class_name.to_owned()));
+        }
+    }
+
+    for m in class.members.iter_mut() {
+        match *m {
+            ClassMember::Field(ref mut f) if f.name == field_name => {
+                f.modifiers.retain(|m| !m.is_access());
+                f.modifiers.insert(0, Modifier::Private);
+            }
+            _ => (),
+        }
+    }
+    class.members.extend(accessors.into_iter().map(ClassMember::Method));
+    let setter = if is_final { None } else { Some(setter_name(&field)) };
+    Ok((getter_name(&field), setter))
+}
+
+/// Make the given field private, and generate a public getter and setter for it. Final fields
+/// only get a getter.
+///
+/// Accesses to the field from outside the class are rewritten to use the accessors - reads become
+/// getter calls, and assignment statements become setter calls. Accesses are found by name, like
+/// calls in `change_signature`, so only accesses through `this.` & `super.` in subclasses, or
+/// through a param, field or class name of a type in the class hierarchy, are rewritten. Returns
+/// a diagnostic for each access which couldn't be rewritten.
+pub fn encapsulate_field(
+    pkgs: &mut [Package],
+    class_name: &str,
+    field_name: &str,
+) -> Result<Vec<Diagnostic>, RefactorErr> {
+    let hierarchy = class_hierarchy(pkgs, class_name);
+    let (getter, setter) = add_accessors(pkgs, class_name, field_name)?;
+
+    // Rewrite the bodies while the project can be looked at to resolve receivers, then swap them
+    // in afterwards. The class itself can still use the field directly.
+    let mut diagnostics = Vec::new();
+    let mut new_bodies = Vec::new();
+    for c in all_decl_names(pkgs) {
+        let class = match find_class(pkgs, &c) {
+            Some(class) if c != class_name => class,
+            _ => continue,
+        };
+        let in_hierarchy = hierarchy.contains(&c);
+        // A subclass' own field of the same name hides the encapsulated one
+        let shadowed = class.find_member(field_name).is_some();
+        for (member_ix, m) in class.members.iter().enumerate() {
+            let m = match *m {
+                ClassMember::Method(ref m) => m,
+                _ => continue,
+            };
+            let resolve = |r: Option<&str>| match r {
+                None if in_hierarchy && !shadowed => {
+                    if m.params.iter().any(|p| p.name == field_name) {
+                        Access::Other
+                    } else {
+                        Access::Unknown
+                    }
+                }
+                Some("this") if in_hierarchy && !shadowed => Access::Rewrite,
+                Some("super") if in_hierarchy => Access::Rewrite,
+                None | Some("this") | Some("super") => Access::Other,
+                Some("") => Access::Unknown,
+                Some(r) => {
+                    match resolve_receiver(pkgs, &c, m, r) {
+                        Some(Some(ref t)) if hierarchy.contains(t) => Access::Rewrite,
+                        Some(_) => Access::Other,
+                        None => Access::Unknown,
+                    }
+                }
+            };
+            let mut body = Vec::with_capacity(m.body.len());
+            for (ii, line) in m.body.iter().enumerate() {
+                let (new_line, errors) = rewrite_accesses(
+                    line,
+                    field_name,
+                    &getter,
+                    setter.as_ref().map(|s| &s[..]),
+                    &resolve,
+                );
+                body.push(new_line);
+                for e in errors {
+                    diagnostics.push(Diagnostic {
+                        location: format!("{}.{}:{}", c, m.name, ii + 1),
+                        message: e,
+                    });
+                }
+            }
+            new_bodies.push((c.clone(), member_ix, body));
+        }
+    }
+    for (c, member_ix, body) in new_bodies {
+        let class = find_class_mut(pkgs, &c).unwrap();
+        if let ClassMember::Method(ref mut m) = class.members[member_ix] {
+            m.body = body;
+        }
+    }
+    Ok(diagnostics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup(field: Field) -> Vec<Package> {
+        let mut p = Package::new("com.tom").0;
+        let mut class = Class::new_with_name("Foo");
+        class.members.push(ClassMember::Field(field));
+        p.add_decl("com.tom", Declaration::Class(class));
+        vec![p]
+    }
+
+    #[test]
+    fn encapsulate() {
+        let mut pkgs = setup(Field {
+            modifiers: vec![Modifier::Public, Modifier::Static],
+            field_type: "boolean".to_owned(),
+            name: "enabled".to_owned(),
+            doc: None,
+            annotations: Vec::new(),
+        });
+        encapsulate_field(&mut pkgs, "com.tom.Foo", "enabled").unwrap();
+        let class = find_class(&pkgs, "com.tom.Foo").unwrap();
+        assert_eq!(
+            class.find_member("enabled").unwrap().modifiers(),
+            &[Modifier::Private, Modifier::Static]
+        );
+        match class.find_member("isEnabled") {
+            Some(&ClassMember::Method(ref m)) => {
+                assert_eq!(m.return_type, "boolean");
+                assert_eq!(m.body, vec!["return Foo.enabled;"]);
+            }
+            _ => panic!("Getter not generated"),
+        }
+        assert!(class.find_member("setEnabled").is_some());
+        assert_eq!(
+            encapsulate_field(&mut pkgs, "com.tom.Foo", "enabled"),
+            Err(RefactorErr::NameConflict("com.tom.Foo".to_owned()))
+        );
+    }
+
+    #[test]
+    fn encapsulate_final() {
+        let mut pkgs = setup(Field {
+            modifiers: vec![Modifier::Final],
+            field_type: "String".to_owned(),
+            name: "name".to_owned(),
+            doc: None,
+            annotations: Vec::new(),
+        });
+        encapsulate_field(&mut pkgs, "com.tom.Foo", "name").unwrap();
+        let class = find_class(&pkgs, "com.tom.Foo").unwrap();
+        assert!(class.find_member("getName").is_some());
+        assert!(class.find_member("setName").is_none());
+    }
+
+    fn method(name: &str, params: &[(&str, &str)], body: Vec<&str>) -> ClassMember {
+        ClassMember::Method(Method {
+            modifiers: Vec::new(),
+            return_type: "void".to_owned(),
+            name: name.to_owned(),
+            params: params
+                .iter()
+                .map(|&(t, n)| {
+                    Param {
+                        param_type: t.to_owned(),
+                        name: n.to_owned(),
+                        annotations: Vec::new(),
+                    }
+                })
+                .collect(),
+            body: body.into_iter().map(|s| s.to_owned()).collect(),
+            doc: None,
+            annotations: Vec::new(),
+        })
+    }
+
+    fn body(pkgs: &[Package], class_name: &str, ix: usize) -> Vec<String> {
+        match find_class(pkgs, class_name).unwrap().members[ix] {
+            ClassMember::Method(ref m) => m.body.clone(),
+            _ => panic!("Not a method"),
+        }
+    }
+
+    #[test]
+    fn encapsulate_accesses() {
+        let mut pkgs = setup(Field {
+            modifiers: vec![Modifier::Public],
+            field_type: "int".to_owned(),
+            name: "count".to_owned(),
+            doc: None,
+            annotations: Vec::new(),
+        });
+        find_class_mut(&mut pkgs, "com.tom.Foo").unwrap().members.push(method(
+            "reset",
+            &[],
+            vec!["count = 0;"],
+        ));
+        let mut child = Class::new_with_name("Child");
+        child.extends = "Foo".to_owned();
+        child.members.push(method(
+            "bump",
+            &[],
+            vec!["this.count = this.count + 1;", "count = 2;"],
+        ));
+        pkgs[0].add_decl("com.tom", Declaration::Class(child));
+        let mut other = Class::new_with_name("Other");
+        other.members.push(method(
+            "use",
+            &[("Foo", "foo"), ("Other", "o"), ("int", "count")],
+            vec![
+                "int x = foo.count * 2;",
+                "foo.count = f(x, \"a;b\");",
+                "foo.count++;",
+                "if (foo.count == 1) { foo.count = 3; }",
+                "y = foo.count = 4;",
+                "o.count = count;",
+                "\"foo.count\";",
+                "bar.count = 1;",
+                "int é = foo.count; // café",
+            ],
+        ));
+        pkgs[0].add_decl("com.tom", Declaration::Class(other));
+
+        let diagnostics = encapsulate_field(&mut pkgs, "com.tom.Foo", "count").unwrap();
+        // The class itself can still use the field
+        assert_eq!(body(&pkgs, "com.tom.Foo", 1), vec!["count = 0;"]);
+        assert_eq!(
+            body(&pkgs, "com.tom.Child", 0),
+            vec!["this.setCount(this.getCount() + 1);", "count = 2;"]
+        );
+        assert_eq!(
+            body(&pkgs, "com.tom.Other", 0),
+            vec![
+                "int x = foo.getCount() * 2;",
+                "foo.setCount(f(x, \"a;b\"));",
+                "foo.count++;",
+                "if (foo.getCount() == 1) { foo.setCount(3); }",
+                "y = foo.count = 4;",
+                "o.count = count;",
+                "\"foo.count\";",
+                "bar.count = 1;",
+                "int é = foo.getCount(); // café",
+            ]
+        );
+        let locations: Vec<&str> = diagnostics.iter().map(|d| &d.location[..]).collect();
+        assert_eq!(
+            locations,
+            vec![
+                "com.tom.Child.bump:2",
+                "com.tom.Other.use:3",
+                "com.tom.Other.use:5",
+                "com.tom.Other.use:8",
+            ]
+        );
+    }
+}
@@ -0,0 +1,225 @@
+//! Refactorings which move members along the class hierarchy.
+
+use java_model::*;
+use super::*;
+
+/// Find the qualified name of the project-local superclass of the given class.
+pub fn find_superclass(pkgs: &[Package], class_name: &str) -> Result<String, RefactorErr> {
+    let class = find_class(pkgs, class_name).ok_or(
+        RefactorErr::DeclNotFound(class_name.to_owned()),
+    )?;
+    if class.extends.trim().len() == 0 {
+        return Err(RefactorErr::NoSuperclass(class_name.to_owned()));
+    }
+    let pkg_name = split_qualified(class_name).unwrap().0;
+    resolve_type(pkgs, pkg_name, &class.extends).ok_or(RefactorErr::SuperclassNotFound(
+        class.extends.clone(),
+    ))
+}
+
+/// Find the qualified names of all project-local classes directly extending the given class.
+pub fn find_subclasses(pkgs: &[Package], class_name: &str) -> Vec<String> {
+    all_decl_names(pkgs)
+        .into_iter()
+        .filter(|n| match find_superclass(pkgs, n) {
+            Ok(ref s) => s == class_name,
+            Err(_) => false,
+        })
+        .collect()
+}
+
+/// Check that a member declared in the decl `owner` would be accessible from the related decl
+/// `other`. Private members can't be seen from other classes, and package-private members can't
+/// be seen outside their package. Returns a conflict naming `other`.
+fn check_visibility(member: &ClassMember, owner: &str, other: &str) -> Result<(), RefactorErr> {
+    let (owner_pkg, other_pkg) = (
+        split_qualified(owner).unwrap().0,
+        split_qualified(other).unwrap().0,
+    );
+    if member.modifiers().contains(&Modifier::Private) ||
+        (is_package_private(member.modifiers()) && owner_pkg != other_pkg)
+    {
+        return Err(RefactorErr::VisibilityConflict(other.to_owned()));
+    }
+    Ok(())
+}
+
+/// Move a member from the given class to its project-local superclass.
+pub fn pull_up_member(
+    pkgs: &mut [Package],
+    class_name: &str,
+    member_name: &str,
+) -> Result<(), RefactorErr> {
+    let superclass = find_superclass(pkgs, class_name)?;
+    let member = find_class(pkgs, class_name)
+        .unwrap()
+        .find_member(member_name)
+        .ok_or(RefactorErr::MemberNotFound(member_name.to_owned()))?
+        .clone();
+    if find_class(pkgs, &superclass)
+        .ok_or(RefactorErr::SuperclassNotFound(superclass.clone()))?
+        .find_member(member_name)
+        .is_some()
+    {
+        return Err(RefactorErr::NameConflict(superclass));
+    }
+    // The class we're pulling up from must still be able to see the member
+    check_visibility(&member, &superclass, class_name)?;
+
+    find_class_mut(pkgs, class_name).unwrap().remove_member(
+        member_name,
+    );
+    find_class_mut(pkgs, &superclass).unwrap().members.push(
+        member,
+    );
+    Ok(())
+}
+
+/// Copy a member from the given class into all of its project-local subclasses, then remove it
+/// from the class.
+pub fn push_down_member(
+    pkgs: &mut [Package],
+    class_name: &str,
+    member_name: &str,
+) -> Result<(), RefactorErr> {
+    let member = find_class(pkgs, class_name)
+        .ok_or(RefactorErr::DeclNotFound(class_name.to_owned()))?
+        .find_member(member_name)
+        .ok_or(RefactorErr::MemberNotFound(member_name.to_owned()))?
+        .clone();
+    let subclasses = find_subclasses(pkgs, class_name);
+    if subclasses.len() == 0 {
+        return Err(RefactorErr::NoSubclasses(class_name.to_owned()));
+    }
+    for s in &subclasses {
+        if find_class(pkgs, s).unwrap().find_member(member_name).is_some() {
+            return Err(RefactorErr::NameConflict(s.clone()));
+        }
+        // Private members were never visible to the subclass, so only check for package moves.
+        if is_package_private(member.modifiers()) {
+            check_visibility(&member, class_name, s)?;
+        }
+    }
+
+    for s in &subclasses {
+        find_class_mut(pkgs, s).unwrap().members.push(member.clone());
+    }
+    find_class_mut(pkgs, class_name).unwrap().remove_member(
+        member_name,
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(name: &str, modifiers: Vec<Modifier>) -> ClassMember {
+        ClassMember::Field(Field {
+            modifiers: modifiers,
+            field_type: "int".to_owned(),
+            name: name.to_owned(),
+            doc: None,
+            annotations: Vec::new(),
+        })
+    }
+
+    /// com.tom.Base, with com.tom.Child and com.other.Remote extending it
+    fn setup() -> Vec<Package> {
+        let mut p = Package::new("com.tom").0;
+        p.add_decl("com.tom", Declaration::Class(Class::new_with_name("Base")));
+        let mut child = Class::new_with_name("Child");
+        child.extends = "Base".to_owned();
+        child.members.push(field("a", vec![Modifier::Protected]));
+        child.members.push(field("b", vec![Modifier::Private]));
+        child.members.push(field("c", vec![]));
+        p.add_decl("com.tom", Declaration::Class(child));
+        let mut remote = Class::new_with_name("Remote");
+        remote.extends = "com.tom.Base".to_owned();
+        p.add_decl("com.other", Declaration::Class(remote));
+        vec![p]
+    }
+
+    #[test]
+    fn resolve_superclass() {
+        let pkgs = setup();
+        assert_eq!(
+            find_superclass(&pkgs, "com.tom.Child"),
+            Ok("com.tom.Base".to_owned())
+        );
+        assert_eq!(
+            find_superclass(&pkgs, "com.other.Remote"),
+            Ok("com.tom.Base".to_owned())
+        );
+        assert_eq!(
+            find_superclass(&pkgs, "com.tom.Base"),
+            Err(RefactorErr::NoSuperclass("com.tom.Base".to_owned()))
+        );
+        assert_eq!(
+            find_subclasses(&pkgs, "com.tom.Base"),
+            vec!["com.tom.Child", "com.other.Remote"]
+        );
+    }
+
+    #[test]
+    fn pull_up() {
+        let mut pkgs = setup();
+        pull_up_member(&mut pkgs, "com.tom.Child", "a").unwrap();
+        assert!(find_class(&pkgs, "com.tom.Base").unwrap().find_member("a").is_some());
+        assert!(find_class(&pkgs, "com.tom.Child").unwrap().find_member("a").is_none());
+        // Same package, so package-private is fine
+        pull_up_member(&mut pkgs, "com.tom.Child", "c").unwrap();
+        assert_eq!(
+            pull_up_member(&mut pkgs, "com.tom.Child", "b"),
+            Err(RefactorErr::VisibilityConflict("com.tom.Child".to_owned()))
+        );
+        assert_eq!(
+            pull_up_member(&mut pkgs, "com.tom.Child", "x"),
+            Err(RefactorErr::MemberNotFound("x".to_owned()))
+        );
+    }
+
+    #[test]
+    fn pull_up_conflict() {
+        let mut pkgs = setup();
+        find_class_mut(&mut pkgs, "com.tom.Base").unwrap().members.push(
+            field("a", vec![]),
+        );
+        assert_eq!(
+            pull_up_member(&mut pkgs, "com.tom.Child", "a"),
+            Err(RefactorErr::NameConflict("com.tom.Base".to_owned()))
+        );
+        assert!(find_class(&pkgs, "com.tom.Child").unwrap().find_member("a").is_some());
+    }
+
+    #[test]
+    fn push_down() {
+        let mut pkgs = setup();
+        {
+            let base = find_class_mut(&mut pkgs, "com.tom.Base").unwrap();
+            base.members.push(field("d", vec![Modifier::Public]));
+            base.members.push(field("e", vec![]));
+        }
+        push_down_member(&mut pkgs, "com.tom.Base", "d").unwrap();
+        assert!(find_class(&pkgs, "com.tom.Base").unwrap().find_member("d").is_none());
+        assert!(find_class(&pkgs, "com.tom.Child").unwrap().find_member("d").is_some());
+        assert!(find_class(&pkgs, "com.other.Remote").unwrap().find_member("d").is_some());
+        assert_eq!(
+            push_down_member(&mut pkgs, "com.tom.Base", "e"),
+            Err(RefactorErr::VisibilityConflict("com.other.Remote".to_owned()))
+        );
+        assert_eq!(
+            push_down_member(&mut pkgs, "com.tom.Child", "a"),
+            Err(RefactorErr::NoSubclasses("com.tom.Child".to_owned()))
+        );
+    }
+
+    #[test]
+    fn cyclic_hierarchy() {
+        let mut pkgs = setup();
+        find_class_mut(&mut pkgs, "com.tom.Base").unwrap().extends = "Child".to_owned();
+        let mut hierarchy = class_hierarchy(&pkgs, "com.tom.Base");
+        hierarchy.sort();
+        assert_eq!(hierarchy, vec!["com.other.Remote", "com.tom.Base", "com.tom.Child"]);
+    }
+}
@@ -0,0 +1,204 @@
+//! Module containing refactorings which operate on the whole project model. These work on a slice
+//! of root packages (i.e. the contents of `Project::package_list`), so that they can be tested
+//! without needing the rest of the application state.
+
+mod hierarchy;
+mod encapsulate;
+mod signature;
+mod rename;
+
+pub use self::hierarchy::*;
+pub use self::encapsulate::*;
+pub use self::signature::*;
+pub use self::rename::*;
+
+use java_model::*;
+use std::collections::HashSet;
+
+/// An error returned when a refactoring could not be applied. When an error is returned, the
+/// model has not been modified.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RefactorErr {
+    /// The given fully qualified decl name could not be found
+    DeclNotFound(String),
+    /// The decl was found, but didn't contain the given member
+    MemberNotFound(String),
+    /// The class doesn't extend anything
+    NoSuperclass(String),
+    /// The class extends something, but it isn't defined in this project
+    SuperclassNotFound(String),
+    /// The class has no subclasses defined in this project
+    NoSubclasses(String),
+    /// The given (qualified) decl already has a member with the same name
+    NameConflict(String),
+    /// Moving the member would make it inaccessible from the given (qualified) decl
+    VisibilityConflict(String),
+    /// There's more than one member with the given name (i.e. an overloaded method)
+    AmbiguousMember(String),
+    /// The given part of a method signature couldn't be parsed
+    BadSignature(String),
+    /// The given name isn't a valid name
+    BadName(String),
+}
+
+/// A problem found while applying a refactoring, which the user will need to fix by hand.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Where the problem is, e.g. `com.tom.Foo.bar:3` for line 3 of method bar
+    pub location: String,
+    pub message: String,
+}
+
+/// Split a fully qualified decl name into a package name and a decl name. Returns None if the
+/// name is in the default package.
+pub fn split_qualified(name: &str) -> Option<(&str, &str)> {
+    let mut splits = name.rsplitn(2, '.');
+    let decl_name = splits.next().unwrap();
+    splits.next().map(|pkg_name| (pkg_name, decl_name))
+}
+
+/// Generate a list of all fully qualified decl names in the given packages.
+pub fn all_decl_names(pkgs: &[Package]) -> Vec<String> {
+    let mut names = Vec::new();
+    for p in pkgs {
+        names.append(&mut p.gen_decl_completion_list());
+    }
+    names
+}
+
+/// Find a decl by fully qualified name in a list of root packages.
+pub fn find_decl<'a>(pkgs: &'a [Package], name: &str) -> Option<&'a Declaration> {
+    if split_qualified(name).is_none() {
+        return None;
+    }
+    pkgs.iter().filter_map(|p| p.find_decl(name)).next()
+}
+
+/// Find a decl by fully qualified name in a list of root packages.
+pub fn find_decl_mut<'a>(pkgs: &'a mut [Package], name: &str) -> Option<&'a mut Declaration> {
+    if split_qualified(name).is_none() {
+        return None;
+    }
+    pkgs.iter_mut().filter_map(|p| p.find_decl_mut(name)).next()
+}
+
+/// Find a class by fully qualified name in a list of root packages.
+pub fn find_class<'a>(pkgs: &'a [Package], name: &str) -> Option<&'a Class> {
+    match find_decl(pkgs, name) {
+        Some(&Declaration::Class(ref c)) => Some(c),
+        _ => None,
+    }
+}
+
+/// Find a class by fully qualified name in a list of root packages.
+pub fn find_class_mut<'a>(pkgs: &'a mut [Package], name: &str) -> Option<&'a mut Class> {
+    if split_qualified(name).is_none() {
+        return None;
+    }
+    for p in pkgs.iter_mut() {
+        match p.find_decl_mut(name) {
+            Some(&mut Declaration::Class(ref mut c)) => return Some(c),
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Get the receiver of a member access whose name starts at the end of `before`, along with
+/// where the receiver starts in `before` - e.g. `this` for `x = this.foo`. Returns None for an
+/// unqualified access. A receiver which isn't a (qualified) name, such as the result of a call, is
+/// returned as an empty string.
+pub fn member_receiver(before: &str) -> Option<(usize, &str)> {
+    let trimmed = before.trim_right();
+    if !trimmed.ends_with('.') {
+        return None;
+    }
+    let trimmed = trimmed[..trimmed.len() - 1].trim_right();
+    let start = trimmed
+        .char_indices()
+        .rev()
+        .find(|&(_, c)| !(c.is_alphanumeric() || c == '_' || c == '$' || c == '.'))
+        .map(|(ix, c)| ix + c.len_utf8())
+        .unwrap_or(0);
+    let name = &trimmed[start..];
+    if name.len() == 0 || name.starts_with('.') {
+        Some((start, ""))
+    } else {
+        Some((start, name))
+    }
+}
+
+/// Find the type of the receiver of a member access, in method `m` of class `class_name`. The
+/// receiver must be a param, field or class name. Returns the qualified name of the type, or
+/// `Some(None)` if it's a type from outside the project. Returns None if the receiver can't be
+/// found, e.g. for a local variable.
+pub fn resolve_receiver(
+    pkgs: &[Package],
+    class_name: &str,
+    m: &Method,
+    receiver: &str,
+) -> Option<Option<String>> {
+    let class = find_class(pkgs, class_name).unwrap();
+    let pkg = split_qualified(class_name).map(|(p, _)| p).unwrap_or("");
+    let var_type = m.params
+        .iter()
+        .find(|p| p.name == receiver)
+        .map(|p| &p.param_type)
+        .or_else(|| {
+            class
+                .members
+                .iter()
+                .filter_map(|mem| match *mem {
+                    ClassMember::Field(ref f) if f.name == receiver => Some(&f.field_type),
+                    _ => None,
+                })
+                .next()
+        });
+    match var_type {
+        Some(t) => Some(resolve_type(pkgs, pkg, t)),
+        // A static access
+        None => resolve_type(pkgs, pkg, receiver).map(Some),
+    }
+}
+
+/// Find a class & all of its project-local subclasses, transitively. Each class is listed once,
+/// even if the model has cyclic `extends`.
+pub fn class_hierarchy(pkgs: &[Package], class_name: &str) -> Vec<String> {
+    let mut to_visit = vec![class_name.to_owned()];
+    let mut visited = HashSet::new();
+    let mut hierarchy = Vec::new();
+    while let Some(c) = to_visit.pop() {
+        if !visited.insert(c.clone()) {
+            continue;
+        }
+        to_visit.append(&mut find_subclasses(pkgs, &c));
+        hierarchy.push(c);
+    }
+    hierarchy
+}
+
+/// Resolve a type name, as written in the source of a decl in the package `from_pkg`, to the
+/// fully qualified name of a project-local decl. Type arguments are ignored.
+///
+/// Simple names are looked up in `from_pkg` first, then anywhere in the project as long as the
+/// name is unambiguous. Returns None if the type isn't defined in this project.
+pub fn resolve_type(pkgs: &[Package], from_pkg: &str, type_name: &str) -> Option<String> {
+    let type_name = type_name.split('<').next().unwrap().trim();
+    if type_name.len() == 0 {
+        return None;
+    }
+    if type_name.contains('.') {
+        return find_decl(pkgs, type_name).map(|_| type_name.to_owned());
+    }
+    let local = format!("{}.{}", from_pkg, type_name);
+    if find_decl(pkgs, &local).is_some() {
+        return Some(local);
+    }
+    let mut candidates = all_decl_names(pkgs).into_iter().filter(|n| {
+        split_qualified(n).map(|(_, d)| d == type_name).unwrap_or(false)
+    });
+    match (candidates.next(), candidates.next()) {
+        (Some(n), None) => Some(n),
+        _ => None,
+    }
+}
@@ -0,0 +1,116 @@
+//! Refactoring to rename a declaration, updating references to it.
+
+use java_model::*;
+use super::*;
+
+/// If the given type (as written in the package `from_pkg`) refers to the decl `old`, return the
+/// type renamed to `new_name`. Type arguments aren't checked.
+fn rename_type(
+    pkgs: &[Package],
+    from_pkg: &str,
+    type_name: &str,
+    old: &str,
+    new_name: &str,
+) -> Option<String> {
+    if resolve_type(pkgs, from_pkg, type_name).as_ref().map(|s| s.as_ref()) != Some(old) {
+        return None;
+    }
+    let base_len = type_name.find('<').unwrap_or(type_name.len());
+    let base = type_name[..base_len].trim();
+    let renamed = match split_qualified(base) {
+        Some((pkg, _)) => format!("{}.{}", pkg, new_name),
+        None => new_name.to_owned(),
+    };
+    Some(renamed + &type_name[base_len..])
+}
+
+/// Rename the given decl. References to it in the extends / implements lists of classes, and
+/// in the types of fields, methods & params, are updated.
+pub fn rename_decl(pkgs: &mut [Package], decl_name: &str, new_name: &str) -> Result<(), RefactorErr> {
+    let pkg_name = match split_qualified(decl_name) {
+        Some((p, _)) if find_decl(pkgs, decl_name).is_some() => p.to_owned(),
+        _ => return Err(RefactorErr::DeclNotFound(decl_name.to_owned())),
+    };
+    let new_qualified = format!("{}.{}", pkg_name, new_name);
+    if new_name.len() == 0 || new_name.contains('.') {
+        return Err(RefactorErr::BadName(new_name.to_owned()));
+    }
+    if find_decl(pkgs, &new_qualified).is_some() {
+        return Err(RefactorErr::NameConflict(new_qualified));
+    }
+
+    // Work out the updated classes first, since resolving types needs the old name
+    let mut updated = Vec::new();
+    for c in all_decl_names(pkgs) {
+        let mut class = match find_class(pkgs, &c) {
+            Some(class) => class.clone(),
+            None => continue,
+        };
+        let from_pkg = split_qualified(&c).unwrap().0;
+        {
+            let pkgs = &*pkgs;
+            let rename = |t: &mut String| if let Some(renamed) =
+                rename_type(pkgs, from_pkg, t, decl_name, new_name)
+            {
+                *t = renamed;
+            };
+            rename(&mut class.extends);
+            for i in class.implements.iter_mut() {
+                rename(i);
+            }
+            for m in class.members.iter_mut() {
+                match *m {
+                    ClassMember::Field(ref mut f) => rename(&mut f.field_type),
+                    ClassMember::Method(ref mut m) => {
+                        rename(&mut m.return_type);
+                        for p in m.params.iter_mut() {
+                            rename(&mut p.param_type);
+                        }
+                    }
+                }
+            }
+        }
+        updated.push((c, class));
+    }
+
+    for (c, class) in updated {
+        *find_class_mut(pkgs, &c).unwrap() = class;
+    }
+    match find_decl_mut(pkgs, decl_name) {
+        Some(&mut Declaration::Class(ref mut c)) => c.name = new_name.to_owned(),
+        None => unreachable!(),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rename() {
+        let mut p = Package::new("com.tom").0;
+        p.add_decl("com.tom", Declaration::Class(Class::new_with_name("Foo")));
+        let mut bar = Class::new_with_name("Bar");
+        bar.extends = "Foo".to_owned();
+        bar.members.push(ClassMember::Field(Field {
+            modifiers: Vec::new(),
+            field_type: "com.tom.Foo".to_owned(),
+            name: "foo".to_owned(),
+            doc: None,
+            annotations: Vec::new(),
+        }));
+        p.add_decl("com.tom", Declaration::Class(bar));
+        let mut pkgs = vec![p];
+
+        assert_eq!(
+            rename_decl(&mut pkgs, "com.tom.Foo", "Bar"),
+            Err(RefactorErr::NameConflict("com.tom.Bar".to_owned()))
+        );
+        rename_decl(&mut pkgs, "com.tom.Foo", "Baz").unwrap();
+        assert!(find_decl(&pkgs, "com.tom.Foo").is_none());
+        let bar = find_class(&pkgs, "com.tom.Bar").unwrap();
+        assert_eq!(bar.extends, "Baz");
+        assert_eq!(bar.find_member("foo").unwrap().signature(), "com.tom.Baz foo");
+    }
+}
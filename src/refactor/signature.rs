@@ -0,0 +1,520 @@
+//! Refactoring to change the signature of a method, updating overriding methods & call sites.
+
+use java_model::*;
+use super::*;
+use common::split_top_level;
+
+/// A parameter in a changed method signature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParamChange {
+    /// A parameter which already existed, given by its index in the old parameter list. The type
+    /// may have been changed.
+    Existing { old_ix: usize, param: Param },
+    /// A new parameter. Call sites will pass the given default argument expression.
+    Added { param: Param, default_arg: String },
+}
+
+/// A description of a new method signature, in terms of the old one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureChange {
+    pub return_type: String,
+    pub params: Vec<ParamChange>,
+}
+
+/// Render a method's parameter list in the form accepted by `parse_signature_change`, e.g.
+/// `String name, int count`.
+pub fn params_to_str(params: &[Param]) -> String {
+    params
+        .iter()
+        .map(|p| format!("{} {}", p.param_type, p.name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Parse a new signature for the given method. The params are a comma separated list of
+/// `Type name` or `Type name = default`. Params are matched up with the old params by name - any
+/// param with a new name is a new param, and must be given a default argument expression.
+pub fn parse_signature_change(
+    old: &Method,
+    return_type: &str,
+    params: &str,
+) -> Result<SignatureChange, RefactorErr> {
+    let mut changes: Vec<ParamChange> = Vec::new();
+    for item in split_top_level(params, true) {
+        let (decl, default_arg) = match item.find('=') {
+            Some(ix) => (item[..ix].trim(), Some(item[ix + 1..].trim())),
+            None => (item.trim(), None),
+        };
+        let split_ix = match decl.rfind(char::is_whitespace) {
+            Some(ix) => ix,
+            None => return Err(RefactorErr::BadSignature(item.clone())),
+        };
+        let param = Param {
+            param_type: decl[..split_ix].trim().to_owned(),
+            name: decl[split_ix..].trim().to_owned(),
+            annotations: Vec::new(),
+        };
+        if changes.iter().any(|c| match *c {
+            ParamChange::Existing { param: ref p, .. } |
+            ParamChange::Added { param: ref p, .. } => p.name == param.name,
+        })
+        {
+            return Err(RefactorErr::BadSignature(item.clone()));
+        }
+        match old.params.iter().position(|p| p.name == param.name) {
+            Some(old_ix) => changes.push(ParamChange::Existing {
+                old_ix: old_ix,
+                param: param,
+            }),
+            None => {
+                match default_arg {
+                    Some(d) if d.len() > 0 => changes.push(ParamChange::Added {
+                        param: param,
+                        default_arg: d.to_owned(),
+                    }),
+                    _ => return Err(RefactorErr::BadSignature(item.clone())),
+                }
+            }
+        }
+    }
+    if return_type.trim().len() == 0 {
+        return Err(RefactorErr::BadSignature(return_type.to_owned()));
+    }
+    Ok(SignatureChange {
+        return_type: return_type.trim().to_owned(),
+        params: changes,
+    })
+}
+
+/// Find a method by name, failing if it's overloaded.
+pub fn find_method<'a>(
+    pkgs: &'a [Package],
+    class_name: &str,
+    method_name: &str,
+) -> Result<&'a Method, RefactorErr> {
+    let class = find_class(pkgs, class_name).ok_or(RefactorErr::DeclNotFound(
+        class_name.to_owned(),
+    ))?;
+    let mut methods = class.members.iter().filter_map(|m| match *m {
+        ClassMember::Method(ref m) if m.name == method_name => Some(m),
+        _ => None,
+    });
+    match (methods.next(), methods.next()) {
+        (Some(m), None) => Ok(m),
+        (Some(_), Some(_)) => Err(RefactorErr::AmbiguousMember(method_name.to_owned())),
+        _ => Err(RefactorErr::MemberNotFound(method_name.to_owned())),
+    }
+}
+
+/// Apply a signature change to a method. Existing params keep the name they had in this method,
+/// so that overriding methods' bodies remain valid.
+fn apply_change(method: &mut Method, change: &SignatureChange) {
+    let old_params = method.params.clone();
+    method.return_type = change.return_type.clone();
+    method.params = change
+        .params
+        .iter()
+        .map(|c| match *c {
+            ParamChange::Existing { old_ix, ref param } => Param {
+                param_type: param.param_type.clone(),
+                name: old_params[old_ix].name.clone(),
+                annotations: old_params[old_ix].annotations.clone(),
+            },
+            ParamChange::Added { ref param, .. } => param.clone(),
+        })
+        .collect();
+}
+
+/// Rewrite all calls to the given method name in a line of source. `resolve` is given the
+/// receiver of each call (see `member_receiver`), and returns whether the call is to the changed
+/// method, or None if that can't be told. Calls which can't be adapted are left alone, and a
+/// message is returned for each.
+fn rewrite_calls(
+    line: &str,
+    method_name: &str,
+    old_arity: usize,
+    change: &SignatureChange,
+    resolve: &Fn(Option<&str>) -> Option<bool>,
+) -> (String, Vec<String>) {
+    let mut out = String::with_capacity(line.len());
+    let mut errors = Vec::new();
+    let mut rest = line;
+    let mut quote: Option<char> = None;
+    while let Some(c) = rest.chars().next() {
+        // Skip string literals
+        if let Some(q) = quote {
+            let len = c.len_utf8();
+            if c == '\\' && rest.len() > 1 {
+                let next_len = rest[1..].chars().next().unwrap().len_utf8();
+                out.push_str(&rest[..1 + next_len]);
+                rest = &rest[1 + next_len..];
+                continue;
+            }
+            if c == q {
+                quote = None;
+            }
+            out.push_str(&rest[..len]);
+            rest = &rest[len..];
+            continue;
+        }
+        if c == '"' || c == '\'' {
+            quote = Some(c);
+        }
+        let prev_is_ident = out.chars().last().map(|p| p.is_alphanumeric() || p == '_' || p == '$');
+        if prev_is_ident != Some(true) && rest.starts_with(method_name) &&
+            rest[method_name.len()..].trim_left().starts_with('(')
+        {
+            let open_ix = method_name.len() + rest[method_name.len()..].find('(').unwrap();
+            // Find the matching close bracket
+            let mut depth = 0;
+            let mut close_ix = None;
+            let mut arg_quote: Option<char> = None;
+            let mut escaped = false;
+            for (ii, ch) in rest[open_ix..].char_indices() {
+                if let Some(q) = arg_quote {
+                    if escaped {
+                        escaped = false;
+                    } else if ch == '\\' {
+                        escaped = true;
+                    } else if ch == q {
+                        arg_quote = None;
+                    }
+                    continue;
+                }
+                match ch {
+                    '"' | '\'' => arg_quote = Some(ch),
+                    '(' => depth += 1,
+                    ')' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            close_ix = Some(open_ix + ii);
+                            break;
+                        }
+                    }
+                    _ => (),
+                }
+            }
+            let close_ix = match close_ix {
+                Some(ix) => ix,
+                None => {
+                    errors.push(format!("Call to {} spans multiple lines", method_name));
+                    out.push_str(rest);
+                    break;
+                }
+            };
+            let args = split_top_level(&rest[open_ix + 1..close_ix], false);
+            let receiver = member_receiver(&out).map(|(_, r)| r);
+            let is_target = resolve(receiver);
+            if is_target == Some(false) {
+                out.push_str(&rest[..close_ix + 1]);
+            } else if is_target.is_none() {
+                errors.push(format!(
+                    "Can't tell if call to {} on '{}' is to the changed method",
+                    method_name,
+                    receiver.unwrap_or("")
+                ));
+                out.push_str(&rest[..close_ix + 1]);
+            } else if args.len() != old_arity {
+                errors.push(format!(
+                    "Call to {} has {} args, expected {}",
+                    method_name,
+                    args.len(),
+                    old_arity
+                ));
+                out.push_str(&rest[..close_ix + 1]);
+            } else {
+                let new_args: Vec<&str> = change
+                    .params
+                    .iter()
+                    .map(|c| match *c {
+                        ParamChange::Existing { old_ix, .. } => args[old_ix].as_ref(),
+                        ParamChange::Added { ref default_arg, .. } => default_arg.as_ref(),
+                    })
+                    .collect();
+                out.push_str(method_name);
+                out.push('(');
+                out.push_str(&new_args.join(", "));
+                out.push(')');
+            }
+            rest = &rest[close_ix + 1..];
+            continue;
+        }
+        out.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    (out, errors)
+}
+
+/// Work out whether a call with the given receiver, in method `m` of class `class_name`, is to a
+/// method in `hierarchy`. Unqualified, `this.` & `super.` calls are only to the method from within
+/// the hierarchy. Any other receiver must be a param, field or class name whose type is in the
+/// hierarchy. Returns None if the receiver's type can't be found, e.g. for a local variable.
+fn call_in_hierarchy(
+    pkgs: &[Package],
+    hierarchy: &[String],
+    class_name: &str,
+    m: &Method,
+    receiver: Option<&str>,
+) -> Option<bool> {
+    let receiver = match receiver {
+        None | Some("this") | Some("super") => {
+            return Some(hierarchy.iter().any(|c| c == class_name))
+        }
+        Some("") => return None,
+        Some(r) => r,
+    };
+    resolve_receiver(pkgs, class_name, m, receiver).map(|t| match t {
+        Some(t) => hierarchy.contains(&t),
+        // A type from outside the project can't be in the hierarchy
+        None => false,
+    })
+}
+
+/// Change the signature of a method. All overriding methods in project-local subclasses are
+/// changed too, and calls to the method in any method body in the project are rewritten.
+///
+/// Call sites are found by name, since method bodies are stored as raw source. A call is only
+/// rewritten if it's to a method in the changed hierarchy (see `call_in_hierarchy`). Returns a
+/// diagnostic for each call which couldn't be adapted, or whose receiver couldn't be resolved.
+pub fn change_signature(
+    pkgs: &mut [Package],
+    class_name: &str,
+    method_name: &str,
+    change: &SignatureChange,
+) -> Result<Vec<Diagnostic>, RefactorErr> {
+    let old_types: Vec<String> = find_method(pkgs, class_name, method_name)?
+        .params
+        .iter()
+        .map(|p| p.param_type.clone())
+        .collect();
+
+    // Find all transitive subclasses, which may contain overriding methods
+    let hierarchy = class_hierarchy(pkgs, class_name);
+
+    for c in &hierarchy {
+        let class = find_class_mut(pkgs, c).unwrap();
+        for m in class.members.iter_mut() {
+            match *m {
+                ClassMember::Method(ref mut m)
+                    if m.name == method_name &&
+                           m.params.iter().map(|p| &p.param_type).eq(old_types.iter()) => {
+                    apply_change(m, change)
+                }
+                _ => (),
+            }
+        }
+    }
+
+    // Rewrite the bodies while the project can be looked at to resolve receivers, then swap them
+    // in afterwards
+    let mut diagnostics = Vec::new();
+    let mut new_bodies = Vec::new();
+    for c in all_decl_names(pkgs) {
+        let class = match find_class(pkgs, &c) {
+            Some(class) => class,
+            None => continue,
+        };
+        for (member_ix, m) in class.members.iter().enumerate() {
+            let m = match *m {
+                ClassMember::Method(ref m) => m,
+                _ => continue,
+            };
+            let resolve = |r: Option<&str>| call_in_hierarchy(pkgs, &hierarchy, &c, m, r);
+            let mut body = Vec::with_capacity(m.body.len());
+            for (ii, line) in m.body.iter().enumerate() {
+                let (new_line, errors) =
+                    rewrite_calls(line, method_name, old_types.len(), change, &resolve);
+                body.push(new_line);
+                for e in errors {
+                    diagnostics.push(Diagnostic {
+                        location: format!("{}.{}:{}", c, m.name, ii + 1),
+                        message: e,
+                    });
+                }
+            }
+            new_bodies.push((c.clone(), member_ix, body));
+        }
+    }
+    for (c, member_ix, body) in new_bodies {
+        let class = find_class_mut(pkgs, &c).unwrap();
+        if let ClassMember::Method(ref mut m) = class.members[member_ix] {
+            m.body = body;
+        }
+    }
+    Ok(diagnostics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn method(name: &str, params: &[(&str, &str)], body: Vec<&str>) -> ClassMember {
+        ClassMember::Method(Method {
+            modifiers: Vec::new(),
+            return_type: "void".to_owned(),
+            name: name.to_owned(),
+            params: params
+                .iter()
+                .map(|&(t, n)| {
+                    Param {
+                        param_type: t.to_owned(),
+                        name: n.to_owned(),
+                        annotations: Vec::new(),
+                    }
+                })
+                .collect(),
+            body: body.into_iter().map(|s| s.to_owned()).collect(),
+            doc: None,
+            annotations: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn split() {
+        assert_eq!(
+            split_top_level("Map<String, Integer> m, int x = max(1, 2)", true),
+            vec!["Map<String, Integer> m", "int x = max(1, 2)"]
+        );
+        assert_eq!(
+            split_top_level("a < b, \"x,y\", f(c, d)", false),
+            vec!["a < b", "\"x,y\"", "f(c, d)"]
+        );
+        assert_eq!(split_top_level("  ", false).len(), 0);
+    }
+
+    #[test]
+    fn parse() {
+        let old = match method("foo", &[("int", "a"), ("String", "b")], vec![]) {
+            ClassMember::Method(m) => m,
+            _ => unreachable!(),
+        };
+        let change = parse_signature_change(&old, "int", "String b, long a, boolean c = true")
+            .unwrap();
+        assert_eq!(change.params.len(), 3);
+        assert_eq!(
+            change.params[1],
+            ParamChange::Existing {
+                old_ix: 0,
+                param: Param {
+                    param_type: "long".to_owned(),
+                    name: "a".to_owned(),
+                    annotations: Vec::new(),
+                },
+            }
+        );
+        assert!(parse_signature_change(&old, "int", "boolean c").is_err());
+        assert!(parse_signature_change(&old, "int", "int a, int a").is_err());
+    }
+
+    #[test]
+    fn change_sig() {
+        let mut p = Package::new("com.tom").0;
+        let mut base = Class::new_with_name("Base");
+        base.members.push(method("foo", &[("int", "a"), ("String", "b")], vec![]));
+        p.add_decl("com.tom", Declaration::Class(base));
+        let mut child = Class::new_with_name("Child");
+        child.extends = "Base".to_owned();
+        child.members.push(method(
+            "foo",
+            &[("int", "x"), ("String", "y")],
+            vec![],
+        ));
+        child.members.push(method(
+            "bar",
+            &[],
+            vec!["foo(1, g(\"a,b\"));", "this.foo(1);", "foo(2,"],
+        ));
+        p.add_decl("com.tom", Declaration::Class(child));
+        let mut pkgs = vec![p];
+
+        let change = {
+            let old = find_method(&pkgs, "com.tom.Base", "foo").unwrap();
+            parse_signature_change(old, "int", "String b, boolean c = false").unwrap()
+        };
+        let diagnostics = change_signature(&mut pkgs, "com.tom.Base", "foo", &change).unwrap();
+
+        let child_foo = match find_class(&pkgs, "com.tom.Child").unwrap().members[0] {
+            ClassMember::Method(ref m) => m.clone(),
+            _ => unreachable!(),
+        };
+        assert_eq!(child_foo.return_type, "int");
+        assert_eq!(params_to_str(&child_foo.params), "String y, boolean c");
+        let bar = match find_class(&pkgs, "com.tom.Child").unwrap().members[1] {
+            ClassMember::Method(ref m) => m.clone(),
+            _ => unreachable!(),
+        };
+        assert_eq!(
+            bar.body,
+            vec!["foo(g(\"a,b\"), false);", "this.foo(1);", "foo(2,"]
+        );
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].location, "com.tom.Child.bar:2");
+    }
+
+    #[test]
+    fn change_sig_receivers() {
+        let mut p = Package::new("com.tom").0;
+        let mut base = Class::new_with_name("Base");
+        base.members.push(method("foo", &[("int", "a"), ("String", "b")], vec![]));
+        p.add_decl("com.tom", Declaration::Class(base));
+        // An unrelated class with a method of the same name
+        let mut other = Class::new_with_name("Other");
+        other.members.push(method("foo", &[("int", "a"), ("String", "b")], vec![]));
+        other.members.push(ClassMember::Field(Field {
+            modifiers: Vec::new(),
+            field_type: "Base".to_owned(),
+            name: "base".to_owned(),
+            doc: None,
+            annotations: Vec::new(),
+        }));
+        other.members.push(method(
+            "bar",
+            &[("Other", "o"), ("Base", "b")],
+            vec![
+                "foo(1, \"x\");",
+                "this.foo(1, \"x\");",
+                "o.foo(1, \"x\");",
+                "foo(1);",
+                "base.foo(1, \"x\");",
+                "b.foo(2, \"y\");",
+                "Base.foo(3, \"z\");",
+                "local.foo(1, \"x\");",
+                "get().foo(1, \"x\");",
+            ],
+        ));
+        p.add_decl("com.tom", Declaration::Class(other));
+        let mut pkgs = vec![p];
+
+        let change = {
+            let old = find_method(&pkgs, "com.tom.Base", "foo").unwrap();
+            parse_signature_change(old, "void", "String b, int a").unwrap()
+        };
+        let diagnostics = change_signature(&mut pkgs, "com.tom.Base", "foo", &change).unwrap();
+
+        let other = find_class(&pkgs, "com.tom.Other").unwrap();
+        match other.members[0] {
+            ClassMember::Method(ref m) => assert_eq!(params_to_str(&m.params), "int a, String b"),
+            _ => unreachable!(),
+        }
+        let bar = match other.members[2] {
+            ClassMember::Method(ref m) => m.clone(),
+            _ => unreachable!(),
+        };
+        assert_eq!(
+            bar.body,
+            vec![
+                "foo(1, \"x\");",
+                "this.foo(1, \"x\");",
+                "o.foo(1, \"x\");",
+                "foo(1);",
+                "base.foo(\"x\", 1);",
+                "b.foo(\"y\", 2);",
+                "Base.foo(\"z\", 3);",
+                "local.foo(1, \"x\");",
+                "get().foo(1, \"x\");",
+            ]
+        );
+        let locations: Vec<&str> = diagnostics.iter().map(|d| &d.location[..]).collect();
+        assert_eq!(locations, vec!["com.tom.Other.bar:8", "com.tom.Other.bar:9"]);
+    }
+}
@@ -1,18 +1,359 @@
 //! Module containing algorithms / data structures for searching lists of stuff.
 
+use crossbeam;
+use num_cpus;
+use unicode_normalization::char::{decompose_canonical, is_combining_mark};
+use std::sync::Mutex;
+use std::collections::HashMap;
+
+/// Ranked searches over fewer strings than this aren't worth splitting between threads
+const PARALLEL_MIN_ENTRIES: usize = 20000;
+/// The buffer is compacted once this many bytes (and at least half of it) belong to removed
+/// strings
+const COMPACT_MIN_GARBAGE: usize = 4096;
+
+/// Score for each matched char
+const SCORE_MATCH: i32 = 16;
+/// Bonus for a match directly after the previous matched char
+const BONUS_CONSECUTIVE: i32 = 12;
+/// Bonus for a match at the start of a word, i.e. at the start of the string, after a separator
+/// like `.` or `_`, or at a camelCase hump
+const BONUS_BOUNDARY: i32 = 10;
+/// Bonus for a match in the simple name, i.e. after the last `.`
+const BONUS_SIMPLE_NAME: i32 = 6;
+/// Bonus for a match with the same case as the search
+const BONUS_CASE: i32 = 1;
+/// Penalty for skipping chars between matches
+const PENALTY_GAP_START: i32 = 3;
+/// Penalty for each skipped char after the first
+const PENALTY_GAP: i32 = 1;
+/// Score used for impossible matches
+const NO_MATCH: i32 = ::std::i32::MIN / 2;
+
+/// A string matched by a ranked search
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchMatch<'a> {
+    pub text: &'a str,
+    /// Higher is a better match
+    pub score: i32,
+    /// The byte positions of the matched chars in `text`
+    pub positions: Vec<usize>,
+}
+
+/// Returns true if the char at this position starts a word
+fn is_boundary(prev: Option<char>, c: char) -> bool {
+    match prev {
+        None => true,
+        Some(p) => {
+            !p.is_alphanumeric() && c.is_alphanumeric() || p.is_lowercase() && c.is_uppercase() ||
+                p.is_alphabetic() && c.is_numeric()
+        }
+    }
+}
+
+/// Get the letter an accented char is based on, e.g. 'e' for 'é'. Other chars are returned as
+/// they are.
+fn strip_accent(c: char) -> char {
+    let mut base = None;
+    let mut accented = true;
+    decompose_canonical(c, |d| if base.is_none() {
+        base = Some(d);
+    } else if !is_combining_mark(d) {
+        accented = false;
+    });
+    match base {
+        Some(b) if accented => b,
+        _ => c,
+    }
+}
+
+/// Fold the case of a char, so that chars which only differ by case compare equal. If
+/// `ignore_accents` is true, accents are removed too.
+fn fold_char(c: char, ignore_accents: bool) -> char {
+    use std::ascii::AsciiExt;
+    if c.is_ascii() {
+        return c.to_ascii_lowercase();
+    }
+    let c = if ignore_accents { strip_accent(c) } else { c };
+    match c.to_lowercase().next() {
+        // Final sigma is only a different form of sigma
+        Some('ς') => 'σ',
+        Some(l) => l,
+        None => c,
+    }
+}
+
+/// Get the chars of a search, along with their folded forms
+fn fold_search(search: &str, ignore_accents: bool) -> Vec<(char, char)> {
+    search.chars().map(|c| (c, fold_char(c, ignore_accents))).collect()
+}
+
+/// Score the best match of the search in the candidate, or None if the search isn't a subsequence
+/// of the candidate. The search should be given as chars, along with their folded forms.
+fn fuzzy_match<'a>(
+    search: &[(char, char)],
+    candidate: &'a str,
+    ignore_accents: bool,
+) -> Option<SearchMatch<'a>> {
+    let chars: Vec<(usize, char, char)> = candidate
+        .char_indices()
+        .map(|(ix, c)| (ix, c, fold_char(c, ignore_accents)))
+        .collect();
+    let (n, m) = (chars.len(), search.len());
+    // Quick check that the search is a subsequence
+    let mut search_iter = search.iter().peekable();
+    for &(_, _, c) in &chars {
+        if search_iter.peek().map_or(false, |s| s.1 == c) {
+            search_iter.next();
+        }
+    }
+    if search_iter.peek().is_some() {
+        return None;
+    }
+    if m == 0 {
+        return Some(SearchMatch {
+            text: candidate,
+            score: -(n as i32),
+            positions: Vec::new(),
+        });
+    }
+
+    // The score of each char in the candidate, if matched
+    let simple_start = candidate.rfind('.').map(|ix| ix + 1).unwrap_or(0);
+    let char_bonus: Vec<i32> = (0..n)
+        .map(|i| {
+            let prev = if i > 0 { Some(chars[i - 1].1) } else { None };
+            let mut bonus = SCORE_MATCH;
+            if is_boundary(prev, chars[i].1) {
+                bonus += BONUS_BOUNDARY;
+            }
+            if chars[i].0 >= simple_start {
+                bonus += BONUS_SIMPLE_NAME;
+            }
+            bonus
+        })
+        .collect();
+
+    // scores[j * n + i] is the best score for matching search[..j + 1] with search[j] at
+    // candidate[i], and prev[j * n + i] is where search[j - 1] was matched for that score.
+    let mut scores = vec![NO_MATCH; m * n];
+    let mut prev = vec![0; m * n];
+    for (j, &(sc, sc_folded)) in search.iter().enumerate() {
+        // The best score from the previous row up to each position, allowing for gaps
+        let mut best_before = NO_MATCH;
+        let mut best_before_ix = 0;
+        for i in 0..n {
+            let (_, c, c_folded) = chars[i];
+            if c_folded == sc_folded {
+                let from = if j == 0 {
+                    Some((0, 0))
+                } else {
+                    let consecutive = if i > 0 && scores[(j - 1) * n + i - 1] > NO_MATCH {
+                        scores[(j - 1) * n + i - 1] + BONUS_CONSECUTIVE
+                    } else {
+                        NO_MATCH
+                    };
+                    let gap = if best_before > NO_MATCH {
+                        best_before - PENALTY_GAP_START
+                    } else {
+                        NO_MATCH
+                    };
+                    if consecutive >= gap && consecutive > NO_MATCH {
+                        Some((consecutive, i - 1))
+                    } else if gap > NO_MATCH {
+                        Some((gap, best_before_ix))
+                    } else {
+                        None
+                    }
+                };
+                if let Some((score, from_ix)) = from {
+                    let case_bonus = if c == sc { BONUS_CASE } else { 0 };
+                    scores[j * n + i] = score + char_bonus[i] + case_bonus;
+                    prev[j * n + i] = from_ix;
+                }
+            }
+            // Update the best score for matching the previous search char before i + 1,
+            // penalising the gap
+            if j > 0 {
+                if best_before > NO_MATCH {
+                    best_before -= PENALTY_GAP;
+                }
+                if i > 0 && scores[(j - 1) * n + i - 1] > best_before {
+                    best_before = scores[(j - 1) * n + i - 1];
+                    best_before_ix = i - 1;
+                }
+            }
+        }
+    }
+
+    // Find the best end position & trace back through the matched positions
+    let last_row = &scores[(m - 1) * n..];
+    let (mut ix, score) = match last_row.iter().enumerate().max_by_key(|&(i, s)| (*s, -(i as i32))) {
+        Some((ix, &s)) if s > NO_MATCH => (ix, s),
+        _ => return None,
+    };
+    let mut positions = vec![0; m];
+    for j in (0..m).rev() {
+        positions[j] = chars[ix].0;
+        ix = prev[j * n + ix];
+    }
+    Some(SearchMatch {
+        text: candidate,
+        score: score - n as i32,
+        positions: positions,
+    })
+}
+
+/// Match a search against a single string, as `SearchBuffer::find_ranked` does. Returns None if
+/// the search isn't a subsequence of the string.
+pub fn match_str<'a>(search: &str, candidate: &'a str) -> Option<SearchMatch<'a>> {
+    fuzzy_match(&fold_search(search, false), candidate, false)
+}
+
+/// Get the bit representing a char in a char mask. Letters & digits get a bit each, and other
+/// chars share the remaining bits. This ignores case & accents, so the same masks work whether or
+/// not a search ignores accents.
+fn char_mask(c: char) -> u64 {
+    let c = fold_char(c, true);
+    if c >= 'a' && c <= 'z' {
+        1 << (c as u32 - 'a' as u32)
+    } else if c >= '0' && c <= '9' {
+        1 << (26 + c as u32 - '0' as u32)
+    } else {
+        1 << (36 + c as u32 % 28)
+    }
+}
+
+/// Get a bitset of all the chars in a string. If a string contains a search as a subsequence, its
+/// mask contains all the bits of the search's mask.
+fn str_mask(s: &str) -> u64 {
+    s.chars().fold(0, |mask, c| mask | char_mask(c))
+}
+
 /// A buffer containing a list of searchable strings. Instead of being a simple Vec<String>, the
-/// whole buffer is instead stored contiguously separated with null terminators, to allow for more
-/// cache efficient subsequence searching.
+/// whole buffer is instead stored contiguously, to allow for more cache efficient subsequence
+/// searching. The strings may contain any chars, including nulls.
+///
+/// Ranked searches use an index of the chars in each string to skip strings which can't match,
+/// and the results of the last search are kept, so that adding to the search only needs to look
+/// through the strings which matched before. Strings can be inserted & removed individually
+/// without rebuilding the buffer.
 ///
-/// NOTE: This will be a case INSENSITIVE search.
+/// NOTE: This will be a case INSENSITIVE search, using Unicode case folding. Searches can also be
+/// set to ignore accents.
 pub struct SearchBuffer {
     /// The buffer containing all the chars
     buf: String,
+    /// The byte range of each string in buf
+    entries: Vec<(usize, usize)>,
+    /// The char mask of each string
+    masks: Vec<u64>,
+    /// The indices in `entries` of each string
+    index: HashMap<String, Vec<usize>>,
+    /// The number of bytes in buf belonging to removed strings
+    garbage: usize,
+    /// The number of threads to use for ranked searches of large buffers
+    threads: usize,
+    /// If true, searches ignore accents, so 'e' matches 'é'
+    ignore_accents: bool,
+    /// The last ranked search, and the indices of every string which matched it
+    last_search: Mutex<Option<(String, Vec<usize>)>>,
 }
 
 impl SearchBuffer {
     pub fn new() -> SearchBuffer {
-        SearchBuffer { buf: String::new() }
+        SearchBuffer {
+            buf: String::new(),
+            entries: Vec::new(),
+            masks: Vec::new(),
+            index: HashMap::new(),
+            garbage: 0,
+            threads: num_cpus::get(),
+            ignore_accents: false,
+            last_search: Mutex::new(None),
+        }
+    }
+
+    /// Set the number of threads to use for ranked searches. 1 disables multi-threading.
+    #[allow(dead_code)]
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = ::std::cmp::max(threads, 1);
+    }
+
+    /// Set whether searches ignore accents
+    #[allow(dead_code)]
+    pub fn set_ignore_accents(&mut self, ignore_accents: bool) {
+        self.ignore_accents = ignore_accents;
+        *self.last_search.lock().unwrap() = None;
+    }
+
+    /// Add a string to the buffer & index
+    fn push(&mut self, s: &str) {
+        let start = self.buf.len();
+        self.buf.push_str(s);
+        if s.len() > 0 {
+            self.index.entry(s.to_owned()).or_insert_with(Vec::new).push(
+                self.entries.len(),
+            );
+            self.entries.push((start, start + s.len()));
+            self.masks.push(str_mask(s));
+        }
+    }
+
+    /// Returns true if the buffer contains the given string
+    pub fn contains(&self, s: &str) -> bool {
+        self.index.contains_key(s)
+    }
+
+    /// Add a string, unless the buffer already contains it
+    pub fn insert(&mut self, s: &str) {
+        if !self.contains(s) {
+            self.push(s);
+            *self.last_search.lock().unwrap() = None;
+        }
+    }
+
+    /// Remove every copy of a string. Returns false if the buffer didn't contain it.
+    pub fn remove(&mut self, s: &str) -> bool {
+        let mut ixs = match self.index.remove(s) {
+            Some(ixs) => ixs,
+            None => return false,
+        };
+        // Remove from the back, so that the entries moved into the gaps aren't ones being removed
+        ixs.sort();
+        for ix in ixs.into_iter().rev() {
+            let (start, end) = self.entries.swap_remove(ix);
+            self.masks.swap_remove(ix);
+            self.garbage += end - start;
+            // Point the index at the moved entry's new position
+            if ix < self.entries.len() {
+                let (moved_start, moved_end) = self.entries[ix];
+                let old_ix = self.entries.len();
+                let moved = &self.buf[moved_start..moved_end];
+                for i in self.index.get_mut(moved).unwrap().iter_mut() {
+                    if *i == old_ix {
+                        *i = ix;
+                    }
+                }
+            }
+        }
+        if self.garbage >= COMPACT_MIN_GARBAGE && self.garbage * 2 >= self.buf.len() {
+            self.compact();
+        }
+        *self.last_search.lock().unwrap() = None;
+        true
+    }
+
+    /// Rebuild the buffer without the space left by removed strings
+    fn compact(&mut self) {
+        let mut buf = String::with_capacity(self.buf.len() - self.garbage);
+        for e in self.entries.iter_mut() {
+            let start = buf.len();
+            buf.push_str(&self.buf[e.0..e.1]);
+            *e = (start, buf.len());
+        }
+        self.buf = buf;
+        self.garbage = 0;
     }
 
     /// Add a list of strings
@@ -25,9 +366,9 @@ impl SearchBuffer {
 
         self.buf.reserve(len);
         for s in strings {
-            self.buf.push_str(s);
-            self.buf.push_str("\x00");
+            self.push(s);
         }
+        *self.last_search.lock().unwrap() = None;
     }
 
     /// Same as add_strings but with a slice of owned strings
@@ -39,70 +380,268 @@ impl SearchBuffer {
 
         self.buf.reserve(len);
         for s in strings {
-            self.buf.push_str(&s);
-            self.buf.push_str("\x00");
+            self.push(s);
         }
+        *self.last_search.lock().unwrap() = None;
+    }
+
+    /// Forget the last ranked search, so that the next one looks through every string
+    #[allow(dead_code)]
+    pub fn clear_last_search(&self) {
+        *self.last_search.lock().unwrap() = None;
     }
 
     /// Clear this buffer.
     pub fn clear(&mut self) {
-        self.buf.clear()
+        self.buf.clear();
+        self.entries.clear();
+        self.masks.clear();
+        self.index.clear();
+        self.garbage = 0;
+        *self.last_search.lock().unwrap() = None;
+    }
+
+    /// Get the strings in the buffer, in order
+    fn strings<'a>(&'a self) -> Box<Iterator<Item = &'a str> + 'a> {
+        Box::new(self.entries.iter().map(move |&(start, end)| &self.buf[start..end]))
     }
 
     /// Given a string, return a list of string slices of which the input is a subsequence of.
+    #[allow(dead_code)]
     pub fn find_all_subsequences(&self, search: &str) -> Vec<&str> {
-        use std::ascii::AsciiExt;
-        let search = &search.to_lowercase();
+        let search: Vec<char> = search.chars().map(|c| fold_char(c, self.ignore_accents)).collect();
         let mut results = Vec::with_capacity(1000);
-        // Hold a reference into the search string, advance each time a char is a match
-        let mut curr_search_iter = search.chars();
-        let mut curr_base = 0; // Base ix of the curr string in buf
-        for (ii, c) in self.buf.char_indices() {
-            if c == '\x00' {
-                // Null char is str boundary
-                if curr_search_iter.as_str().len() == 0 {
-                    unsafe {
-                        results.push(self.buf.slice_unchecked(curr_base, ii));
-                    }
+        for s in self.strings() {
+            // Advance through the search each time a char is a match
+            let mut search_ix = 0;
+            for c in s.chars() {
+                if search_ix == search.len() {
+                    break;
+                }
+                if fold_char(c, self.ignore_accents) == search[search_ix] {
+                    search_ix += 1;
                 }
-                curr_base = ii + 1;
-                curr_search_iter = search.chars();
-                continue;
             }
-            // Not a null char, so check if we can advance curr_search_ix
-            if curr_search_iter.as_str().len() > 0 &&
-                c.to_ascii_lowercase() == curr_search_iter.as_str().chars().next().unwrap()
-            {
-                curr_search_iter.next().unwrap();
+            if search_ix == search.len() {
+                results.push(s);
             }
         }
         return results;
     }
 
-    #[allow(dead_code)]
-    pub fn find_all_containing_char(&self, search: char) -> Vec<&str> {
-        use std::ascii::AsciiExt;
-        let search = search.to_ascii_lowercase();
-        let mut results = Vec::with_capacity(1000);
-        let mut curr_base = 0; // Base ix of the curr string in buf
-        let mut skipping = false;
-        for (ii, c) in self.buf.char_indices() {
-            if c == '\x00' {
-                skipping = false;
-                curr_base = ii + 1;
-            }
-            if skipping {
-                continue;
+    /// Search for strings which the input is a subsequence of, ranked by how well they match.
+    /// Matches in consecutive runs, at the start of words and in the simple name after the last
+    /// `.` are preferred, as are shorter strings. Returns at most `limit` results, best first.
+    pub fn find_ranked<'a>(&'a self, search: &str, limit: usize) -> Vec<SearchMatch<'a>> {
+        let search_chars = fold_search(search, self.ignore_accents);
+        let search_mask = str_mask(search);
+        let has_chars = |ix: &usize| self.masks[*ix] & search_mask == search_mask;
+
+        // If the search extends the last search, only the strings which matched that can match
+        let mut last_search = self.last_search.lock().unwrap();
+        let candidates: Vec<usize> = match last_search.take() {
+            Some((ref last, ref matched)) if search.starts_with(last.as_str()) => {
+                matched.iter().cloned().filter(&has_chars).collect()
             }
-            // Not a null char, so check if we can advance curr_search_ix
-            if search == c.to_ascii_lowercase() {
-                unsafe {
-                    results.push(self.buf.slice_unchecked(curr_base, ii));
-                }
-                skipping = true;
+            _ => (0..self.entries.len()).filter(&has_chars).collect(),
+        };
+
+        let matches = self.match_entries(&search_chars, &candidates);
+        *last_search = Some((
+            search.to_owned(),
+            matches.iter().map(|&(ix, _)| ix).collect(),
+        ));
+        let mut results: Vec<SearchMatch> = matches.into_iter().map(|(_, m)| m).collect();
+        // Equal matches are sorted alphabetically, since removing strings changes their order
+        results.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.text.cmp(b.text)));
+        results.truncate(limit);
+        results
+    }
+
+    /// Match the search against the strings with the given indices, in order. Returns the index
+    /// of each string which matched, along with the match. Large lists are split between threads.
+    fn match_entries<'a>(
+        &'a self,
+        search: &[(char, char)],
+        candidates: &[usize],
+    ) -> Vec<(usize, SearchMatch<'a>)> {
+        let match_chunk = |chunk: &[usize]| -> Vec<(usize, SearchMatch<'a>)> {
+            chunk
+                .iter()
+                .filter_map(|&ix| {
+                    let (start, end) = self.entries[ix];
+                    fuzzy_match(search, &self.buf[start..end], self.ignore_accents)
+                        .map(|m| (ix, m))
+                })
+                .collect()
+        };
+        if self.threads <= 1 || candidates.len() < PARALLEL_MIN_ENTRIES {
+            return match_chunk(candidates);
+        }
+        let chunk_size = (candidates.len() + self.threads - 1) / self.threads;
+        crossbeam::scope(|scope| {
+            let handles: Vec<_> = candidates
+                .chunks(chunk_size)
+                .map(|chunk| scope.spawn(move || match_chunk(chunk)))
+                .collect();
+            let mut matches = Vec::new();
+            for h in handles {
+                matches.extend(h.join());
             }
+            matches
+        })
+    }
+
+    #[allow(dead_code)]
+    pub fn find_all_containing_char(&self, search: char) -> Vec<&str> {
+        let search = fold_char(search, self.ignore_accents);
+        self.strings()
+            .filter(|s| s.chars().any(|c| fold_char(c, self.ignore_accents) == search))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranked<'a>(buf: &'a SearchBuffer, search: &str) -> Vec<&'a str> {
+        buf.find_ranked(search, 10).into_iter().map(|m| m.text).collect()
+    }
+
+    #[test]
+    fn test_ranking() {
+        let mut buf = SearchBuffer::new();
+        buf.add_strings(
+            &[
+                "com.foo.Bar",
+                "com.tom.FrobOrOther",
+                "com.tom.Foo",
+                "com.tom.FooBar",
+                "org.Xfxoxo",
+            ],
+        );
+        // Consecutive matches in the simple name come first, then shorter strings
+        assert_eq!(
+            ranked(&buf, "foo"),
+            vec![
+                "com.tom.Foo",
+                "com.tom.FooBar",
+                "com.foo.Bar",
+                "com.tom.FrobOrOther",
+                "org.Xfxoxo",
+            ]
+        );
+        // camelCase humps
+        assert_eq!(ranked(&buf, "FB")[0], "com.tom.FooBar");
+        assert_eq!(ranked(&buf, "xyz").len(), 0);
+        assert_eq!(buf.find_ranked("", 2).len(), 2);
+    }
+
+    #[test]
+    fn test_positions() {
+        let mut buf = SearchBuffer::new();
+        buf.add_strings(&["com.tom.FooBar"]);
+        assert_eq!(buf.find_ranked("fb", 1)[0].positions, vec![8, 11]);
+        assert_eq!(buf.find_ranked("oob", 1)[0].positions, vec![9, 10, 11]);
+        assert_eq!(buf.find_ranked("ctf", 1)[0].positions, vec![0, 4, 8]);
+    }
+
+    #[test]
+    fn test_narrowing() {
+        let mut buf = SearchBuffer::new();
+        buf.add_strings(&["com.tom.Foo", "com.tom.Bar", "org.Baz"]);
+        assert_eq!(ranked(&buf, "o").len(), 3);
+        assert_eq!(ranked(&buf, "oba"), vec!["org.Baz", "com.tom.Bar"]);
+        assert_eq!(ranked(&buf, "obaz"), vec!["org.Baz"]);
+        // Searches which don't extend the last search look through everything again
+        assert_eq!(ranked(&buf, "foo"), vec!["com.tom.Foo"]);
+        // Adding strings forgets the last search
+        buf.add_strings(&["com.FooBar"]);
+        assert_eq!(ranked(&buf, "foob"), vec!["com.FooBar"]);
+        buf.clear();
+        assert_eq!(ranked(&buf, "foob").len(), 0);
+    }
+
+    #[test]
+    fn test_parallel() {
+        let names: Vec<String> = (0..PARALLEL_MIN_ENTRIES * 2)
+            .map(|ii| format!("com.pkg{}.Class{}", ii % 97, ii))
+            .collect();
+        let mut buf = SearchBuffer::new();
+        buf.add_strings_owned(&names[..]);
+        let owned = |matches: Vec<SearchMatch>| -> Vec<(String, i32)> {
+            matches.into_iter().map(|m| (m.text.to_owned(), m.score)).collect()
+        };
+        buf.set_threads(1);
+        let single = owned(buf.find_ranked("pkg12cls3", 50));
+        buf.set_threads(4);
+        buf.clear_last_search();
+        let parallel = owned(buf.find_ranked("pkg12cls3", 50));
+        assert_eq!(single.len(), 50);
+        assert_eq!(single, parallel);
+    }
+
+    #[test]
+    fn test_insert_remove() {
+        let mut buf = SearchBuffer::new();
+        buf.add_strings(&["com.tom.Foo", "com.tom.Bar", "com.tom.Baz"]);
+        buf.insert("com.tom.Foo");
+        assert_eq!(ranked(&buf, "foo"), vec!["com.tom.Foo"]);
+        assert!(buf.remove("com.tom.Foo"));
+        assert!(!buf.remove("com.tom.Foo"));
+        assert!(!buf.contains("com.tom.Foo"));
+        assert_eq!(ranked(&buf, "ctb"), vec!["com.tom.Bar", "com.tom.Baz"]);
+        let mut found = buf.find_all_subsequences("ctb");
+        found.sort();
+        assert_eq!(found, vec!["com.tom.Bar", "com.tom.Baz"]);
+        buf.insert("com.Qux");
+        assert!(buf.remove("com.tom.Bar"));
+        assert_eq!(ranked(&buf, "c"), vec!["com.Qux", "com.tom.Baz"]);
+
+        // Removing lots of strings compacts the buffer
+        let names: Vec<String> = (0..1000).map(|ii| format!("com.Class{}", ii)).collect();
+        buf.add_strings_owned(&names[..]);
+        for n in &names[1..] {
+            buf.remove(n);
         }
-        return results;
+        assert!(buf.buf.len() < COMPACT_MIN_GARBAGE + 100);
+        assert_eq!(ranked(&buf, "class"), vec!["com.Class0"]);
+        assert_eq!(ranked(&buf, "c").len(), 3);
+    }
+
+    #[test]
+    fn test_non_ascii() {
+        let mut buf = SearchBuffer::new();
+        buf.add_strings(&["com.tom.Über", "com.tom.ÉCOLE", "com.tom.Λόγος", "com.tom.Ärger"]);
+        assert_eq!(ranked(&buf, "über"), vec!["com.tom.Über"]);
+        assert_eq!(ranked(&buf, "ÜBER"), vec!["com.tom.Über"]);
+        assert_eq!(ranked(&buf, "école"), vec!["com.tom.ÉCOLE"]);
+        // Final sigma matches sigma
+        assert_eq!(ranked(&buf, "ΓΟΣ"), vec!["com.tom.Λόγος"]);
+        // Positions are in bytes
+        assert_eq!(buf.find_ranked("üb", 1)[0].positions, vec![8, 10]);
+        assert_eq!(buf.find_all_subsequences("ÜBer"), vec!["com.tom.Über"]);
+        assert_eq!(buf.find_all_containing_char('ä'), vec!["com.tom.Ärger"]);
+        // Accents only match when ignored
+        assert_eq!(ranked(&buf, "uber").len(), 0);
+        assert_eq!(ranked(&buf, "ecole").len(), 0);
+        buf.set_ignore_accents(true);
+        assert_eq!(ranked(&buf, "uber"), vec!["com.tom.Über"]);
+        assert_eq!(ranked(&buf, "ecole"), vec!["com.tom.ÉCOLE"]);
+        assert_eq!(ranked(&buf, "λογ"), vec!["com.tom.Λόγος"]);
+        assert_eq!(buf.find_all_subsequences("arg"), vec!["com.tom.Ärger"]);
+    }
+
+    #[test]
+    fn test_nul() {
+        let mut buf = SearchBuffer::new();
+        buf.add_strings(&["a\x00b", "ab", "\x00"]);
+        assert_eq!(ranked(&buf, "ab"), vec!["ab", "a\x00b"]);
+        assert_eq!(ranked(&buf, "\x00"), vec!["\x00", "a\x00b"]);
+        assert_eq!(buf.find_all_subsequences("ab"), vec!["a\x00b", "ab"]);
+        assert!(buf.remove("a\x00b"));
+        assert_eq!(ranked(&buf, ""), vec!["\x00", "ab"]);
     }
 }
 
@@ -156,6 +695,104 @@ mod benches {
         bench_search(b, 300000);
     }
 
+    /// Test a ranked search through a given amount of 30 char items, with the given number of
+    /// threads
+    fn bench_ranked(b: &mut test::Bencher, num_classes: usize, threads: usize) {
+        const SEARCH_TERM: &'static str = "Al£adfAff";
+        let mut search_buffer = SearchBuffer::new();
+        search_buffer.add_strings(&vec!["ALKJasdlf.£asdwhio.fASdhsdfo."; num_classes][..]);
+        search_buffer.set_threads(threads);
+        b.iter(|| {
+            // Forget the last search, so that everything is searched each time
+            search_buffer.clear_last_search();
+            test::black_box(search_buffer.find_ranked(SEARCH_TERM, 10));
+        });
+    }
+
+    /// Generate a list of qualified class names, where only some contain each char
+    fn varied_names(num_classes: usize) -> Vec<String> {
+        const WORDS: &'static [&'static str] =
+            &["Abstract", "Factory", "Bean", "Proxy", "Visitor", "Node", "Queue", "Jar"];
+        (0..num_classes)
+            .map(|ii| {
+                format!(
+                    "com.tom.package{}.{}{}{}",
+                    ii % 100,
+                    WORDS[ii % WORDS.len()],
+                    WORDS[(ii / WORDS.len()) % WORDS.len()],
+                    ii
+                )
+            })
+            .collect()
+    }
+
+    #[bench]
+    /// Test a ranked search over 300k items on one thread
+    fn bench_ranked_300k_1_thread(b: &mut test::Bencher) {
+        bench_ranked(b, 300000, 1);
+    }
+
+    #[bench]
+    /// Test a ranked search over 300k items, split between threads
+    fn bench_ranked_300k_parallel(b: &mut test::Bencher) {
+        bench_ranked(b, 300000, num_cpus::get());
+    }
+
+    #[bench]
+    /// Test a ranked search over 300k varied items, without using the char index. Compare with
+    /// bench_ranked_varied_300k_indexed.
+    fn bench_ranked_varied_300k_unindexed(b: &mut test::Bencher) {
+        let names = varied_names(300000);
+        let search = [('q', 'q'), ('j', 'j')];
+        b.iter(|| {
+            test::black_box(
+                names
+                    .iter()
+                    .filter_map(|n| fuzzy_match(&search, n, false))
+                    .count(),
+            );
+        });
+    }
+
+    #[bench]
+    /// Test a ranked search over 300k varied items, where the char index rules out most of them
+    fn bench_ranked_varied_300k_indexed(b: &mut test::Bencher) {
+        let mut search_buffer = SearchBuffer::new();
+        search_buffer.add_strings_owned(&varied_names(300000)[..]);
+        search_buffer.set_threads(1);
+        b.iter(|| {
+            search_buffer.clear_last_search();
+            test::black_box(search_buffer.find_ranked("qj", 10));
+        });
+    }
+
+    #[bench]
+    /// Test typing the last char of a search over 300k varied items, which only searches the
+    /// results of the search before it. Compare with bench_ranked_varied_300k_retype_rescan.
+    fn bench_ranked_varied_300k_retype_narrowed(b: &mut test::Bencher) {
+        let mut search_buffer = SearchBuffer::new();
+        search_buffer.add_strings_owned(&varied_names(300000)[..]);
+        search_buffer.set_threads(1);
+        search_buffer.find_ranked("qn", 10);
+        let last_search = search_buffer.last_search.lock().unwrap().clone();
+        b.iter(|| {
+            *search_buffer.last_search.lock().unwrap() = last_search.clone();
+            test::black_box(search_buffer.find_ranked("qno", 10));
+        });
+    }
+
+    #[bench]
+    /// Test the same search as bench_ranked_varied_300k_retype_narrowed, searching everything
+    fn bench_ranked_varied_300k_retype_rescan(b: &mut test::Bencher) {
+        let mut search_buffer = SearchBuffer::new();
+        search_buffer.add_strings_owned(&varied_names(300000)[..]);
+        search_buffer.set_threads(1);
+        b.iter(|| {
+            search_buffer.clear_last_search();
+            test::black_box(search_buffer.find_ranked("qno", 10));
+        });
+    }
+
     #[bench]
     /// Test searching for a char through loads of 18 char items, then placing the results in a
     /// search buffer
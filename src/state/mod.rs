@@ -1,34 +1,110 @@
 //! Module pertaining to application state. All views will keep a reference to this state.
 
 mod project;
+mod tree;
+mod source;
 
 pub use self::project::*;
+pub use self::tree::*;
 
 use winit;
-use common;
 use command;
 use prompt;
 use std::sync::{Arc, Mutex};
 use qgfx;
 use input;
 use prompt::PromptResult;
+use template;
+use macros::{self, MacroEvent};
+use theme;
+use layout;
+use view::View;
+use winit::VirtualKeyCode as VKC;
+use std::collections::VecDeque;
+use std::mem;
 
 pub struct State {
     pub project: Project,
     pub command_buffer: Mutex<command::CommandBuffer>,
     pub curr_prompt: Mutex<Option<prompt::PromptInput>>,
+    /// User defined templates, loaded from the config directory
+    pub templates: Vec<template::Template>,
+    /// Arguments given on the command line. These fill in the next prompt instead of asking the
+    /// user.
+    pub pending_args: Mutex<Option<Vec<String>>>,
+    /// The command currently running, and the answers given to its prompts so far
+    pub recording: Mutex<Option<(command::Command, Vec<String>)>>,
+    /// The last command which ran to completion, and the answers given to its prompts. This is
+    /// what the repeat command runs.
+    pub last_cmd: Mutex<Option<(command::Command, Vec<String>)>>,
+    /// Keyboard macros, loaded from the config directory
+    pub macros: Mutex<Vec<macros::Macro>>,
+    /// The macro being recorded, if any
+    pub macro_recorder: Mutex<Option<macros::MacroRecorder>>,
+    /// Macro events waiting to be played back
+    pub macro_playback: Mutex<VecDeque<MacroEvent>>,
+    /// True while a macro is being played back. Its events aren't recorded into a macro being
+    /// recorded, since the keys which played the macro already are.
+    pub playing_macro: Mutex<bool>,
+    /// Commands which were queued when a macro started playing. They run once it finishes, so
+    /// that they don't take the macro's input.
+    pub held_cmds: Mutex<Vec<command::Command>>,
+    /// The values previously accepted by prompts
+    pub prompt_history: Mutex<prompt::History>,
+    /// True when in navigation mode, where keys move the selection through the package tree
+    pub nav_mode: Mutex<bool>,
+    /// The colours, font & sizes to render with
+    pub theme: Mutex<theme::ThemeLoader>,
+    /// The layout of the panes, and which has focus
+    pub layout: Mutex<layout::Layout>,
+    /// True when the last key pressed had no keycode, in which case the char it typed is used as
+    /// the key. On X11, winit 0.7 gives keys like `<` & `>` no keycode.
+    pub key_without_code: Mutex<bool>,
 }
 
 impl State {
     pub fn new() -> State {
+        let templates = template::load_templates();
+        let mut command_buffer = command::CommandBuffer::new();
+        for t in &templates {
+            if let Some(ref keys) = t.keys {
+                if let Err(e) = command_buffer.add_mapping(
+                    keys,
+                    command::Command::Template(t.name.clone()),
+                )
+                {
+                    println!("Failed to bind template {} to {}: {}", t.name, keys, e);
+                }
+            }
+        }
+        command_buffer.reload_keymap();
+        let project = Project::new();
+        project.load_collapsed();
         State {
-            project: Project::new(),
-            command_buffer: Mutex::new(command::CommandBuffer::new()),
+            project: project,
+            command_buffer: Mutex::new(command_buffer),
             curr_prompt: Mutex::new(None),
+            templates: templates,
+            pending_args: Mutex::new(None),
+            recording: Mutex::new(None),
+            last_cmd: Mutex::new(None),
+            macros: Mutex::new(macros::load_macros()),
+            macro_recorder: Mutex::new(None),
+            macro_playback: Mutex::new(VecDeque::new()),
+            playing_macro: Mutex::new(false),
+            held_cmds: Mutex::new(Vec::new()),
+            prompt_history: Mutex::new(prompt::load_history()),
+            nav_mode: Mutex::new(false),
+            theme: Mutex::new(theme::ThemeLoader::new()),
+            layout: Mutex::new(layout::load_layout()),
+            key_without_code: Mutex::new(false),
         }
     }
 
     /// Prompt the user for some input. Returns false if user is already being prompted.
+    ///
+    /// If there are pending args from the command line, these fill in the prompts. If there are
+    /// enough args for every prompt, the callback is called straight away.
     /// # Params
     /// * `this` - The state in an arc. This is needed because this prompt method creates a closure
     /// which needs to reference self.
@@ -40,63 +116,292 @@ impl State {
         prompts: Vec<prompt::PromptType>,
         callback: Box<FnMut(&[PromptResult])>,
     ) -> bool {
-        let mut prompt = this.curr_prompt.lock().unwrap();
-        if prompt.is_some() {
+        if this.curr_prompt.lock().unwrap().is_some() {
             return true;
-        } else {
-            *prompt = Some(prompt::PromptInput::new(prompts, callback));
-            true
         }
+        // Multi-line values aren't kept in the history
+        let labels: Vec<Option<String>> = prompts
+            .iter()
+            .map(|p| match *p {
+                prompt::PromptType::MultiLine(_) => None,
+                _ => Some(p.as_str().to_owned()),
+            })
+            .collect();
+        let history = {
+            let prompt_history = this.prompt_history.lock().unwrap();
+            labels
+                .iter()
+                .map(|l| match *l {
+                    Some(ref l) => prompt_history.get(l).to_vec(),
+                    None => Vec::new(),
+                })
+                .collect()
+        };
+        let callback = State::record_answers(this.clone(), callback);
+        let callback = State::record_history(this.clone(), labels, callback);
+        let mut prompt_input = prompt::PromptInput::new(prompts, callback);
+        prompt_input.set_history(history);
+        let args = this.pending_args.lock().unwrap().take();
+        if let Some(args) = args {
+            let (complete, leftover) = prompt_input.fill(args);
+            if complete {
+                // Leave any remaining args for a prompt opened by the callback
+                *this.pending_args.lock().unwrap() = Some(leftover);
+                prompt_input.finish(this.clone());
+                return true;
+            }
+        }
+        *this.curr_prompt.lock().unwrap() = Some(prompt_input);
+        true
+    }
+
+    /// Wrap a prompt callback so that the answers are added to the prompt history, under the given
+    /// labels.
+    fn record_history(
+        this: Arc<State>,
+        labels: Vec<Option<String>>,
+        mut callback: Box<FnMut(&[PromptResult])>,
+    ) -> Box<FnMut(&[PromptResult])> {
+        Box::new(move |data| {
+            {
+                let mut history = this.prompt_history.lock().unwrap();
+                for (label, r) in labels.iter().zip(data) {
+                    if let Some(ref label) = *label {
+                        history.add(label, &r.val);
+                    }
+                }
+                if let Err(e) = prompt::save_history(&history) {
+                    println!("Failed to save prompt history: {}", e);
+                }
+            }
+            callback(data);
+        })
     }
 
-    /// Returns true if input was used
-    pub fn process_input(this: Arc<State>, ev: &qgfx::WindowEvent) -> bool {
+    /// Wrap a prompt callback so that the answers are recorded against the running command. Once
+    /// the callback finishes without opening another prompt, the command is complete and can be
+    /// repeated.
+    fn record_answers(
+        this: Arc<State>,
+        mut callback: Box<FnMut(&[PromptResult])>,
+    ) -> Box<FnMut(&[PromptResult])> {
+        Box::new(move |data| {
+            if let Some((_, ref mut answers)) = *this.recording.lock().unwrap() {
+                answers.extend(data.iter().map(|r| r.val.clone()));
+            }
+            callback(data);
+            this.finish_command();
+        })
+    }
+
+    /// Called after a command or one of its prompts has run. If no prompt is open, the running
+    /// command is complete, and becomes the command to repeat.
+    pub fn finish_command(&self) {
+        if self.curr_prompt.lock().unwrap().is_some() {
+            return;
+        }
+        let recording = self.recording.lock().unwrap().take();
+        if recording.is_some() {
+            *self.last_cmd.lock().unwrap() = recording;
+        }
+    }
+
+    /// Returns true if input was used. `focused` is the view in the focused pane, which gets keys
+    /// in navigation mode.
+    pub fn process_input(this: Arc<State>, ev: &qgfx::WindowEvent, focused: &View) -> bool {
         match *ev {
             qgfx::WindowEvent::KeyboardInput {
                 device_id: _,
                 input: k,
             } => {
+                if k.state == winit::ElementState::Pressed {
+                    *this.key_without_code.lock().unwrap() = k.virtual_keycode.is_none();
+                }
                 if k.virtual_keycode.is_some() && k.state == winit::ElementState::Pressed {
-                    // C-g cancels everything
-                    if common::mods_to_bitflags(k.modifiers) == 0b0100 &&
-                        k.virtual_keycode.unwrap() == winit::VirtualKeyCode::G
-                    {
-                        this.command_buffer.lock().unwrap().reset_input();
-                        *this.curr_prompt.lock().unwrap() = None;
-                    }
                     let i = input::InputChunk::from_modifiers_state(
                         k.virtual_keycode.unwrap(),
                         k.modifiers,
                     );
-                    // If prompt is showing, send data to that first
-                    if this.curr_prompt.lock().unwrap().is_some() {
-                        let mut curr_prompt = this.curr_prompt.lock().unwrap();
-                        curr_prompt.as_mut().unwrap().key_input(i);
-                        curr_prompt.as_mut().unwrap().update_completions(
-                            this.clone(),
-                        );
-                    } else {
-                        // Otherwise, send data to the command buffer
-                        // Special case, clear the command buffer on C-g
-                        (*this.command_buffer.lock().unwrap()).add_key(i);
-                    }
+                    State::input_event(this, MacroEvent::Key(i), focused);
                     return true;
                 }
             }
             qgfx::WindowEvent::ReceivedCharacter(c) => {
-                if this.curr_prompt.lock().unwrap().is_some() {
-                    let mut curr_prompt = this.curr_prompt.lock().unwrap();
-                    if curr_prompt.as_mut().unwrap().char_input(c) {
-                        *curr_prompt = None;
-                    } else {
-                        curr_prompt.as_mut().unwrap().update_completions(
-                            this.clone(),
-                        );
-                    }
-                }
+                let key_without_code =
+                    mem::replace(&mut *this.key_without_code.lock().unwrap(), false);
+                let in_prompt = this.curr_prompt.lock().unwrap().is_some();
+                let e = match input::char_to_input(c) {
+                    Some(i) if key_without_code && !in_prompt => MacroEvent::Key(i),
+                    _ => MacroEvent::Char(c),
+                };
+                State::input_event(this, e, focused);
             }
             _ => (),
         }
         return false;
     }
+
+    /// Handle a key press or typed char, adding it to the macro being recorded, if any. Macros
+    /// are played back through this too, but the events they play aren't recorded again.
+    pub fn input_event(this: Arc<State>, e: MacroEvent, focused: &View) {
+        if !*this.playing_macro.lock().unwrap() {
+            if let Some(ref mut rec) = *this.macro_recorder.lock().unwrap() {
+                let in_prompt = this.curr_prompt.lock().unwrap().is_some();
+                match e {
+                    MacroEvent::Key(i) => {
+                        let new_cmd = this.command_buffer.lock().unwrap().is_empty();
+                        rec.key(i, in_prompt, !in_prompt && new_cmd);
+                    }
+                    MacroEvent::Char(c) => rec.char(c, in_prompt),
+                }
+            }
+        }
+        match e {
+            MacroEvent::Key(i) => State::key_input(this, i, focused),
+            MacroEvent::Char(c) => State::char_input(this, c),
+        }
+    }
+
+    /// Handle a key press, sending it to the prompt if one is showing, or the command buffer
+    /// otherwise. In navigation mode, keys between commands go to the focused view first.
+    pub fn key_input(this: Arc<State>, i: input::InputChunk, focused: &View) {
+        // Modifier keys on their own would break up key sequences
+        if i.is_modifier() {
+            return;
+        }
+        // C-g cancels everything, including macro playback. A C-g in a macro only cancels what
+        // the macro was doing.
+        if i == input::InputChunk(winit::VirtualKeyCode::G, input::CTRL) {
+            this.command_buffer.lock().unwrap().abort();
+            if !*this.playing_macro.lock().unwrap() {
+                this.macro_playback.lock().unwrap().clear();
+                this.held_cmds.lock().unwrap().clear();
+            }
+            *this.curr_prompt.lock().unwrap() = None;
+            *this.nav_mode.lock().unwrap() = false;
+        }
+        // If prompt is showing, send data to that first
+        if this.curr_prompt.lock().unwrap().is_some() {
+            let mut curr_prompt = this.curr_prompt.lock().unwrap();
+            curr_prompt.as_mut().unwrap().key_input(i);
+            curr_prompt.as_mut().unwrap().update_completions(this.clone());
+        } else {
+            // In navigation mode, keys between commands go to the focused view
+            let nav_mode = *this.nav_mode.lock().unwrap();
+            if nav_mode && this.command_buffer.lock().unwrap().is_empty() {
+                match (i.0, i.1) {
+                    (VKC::Escape, 0) | (VKC::Q, 0) => {
+                        *this.nav_mode.lock().unwrap() = false;
+                        return;
+                    }
+                    _ => {
+                        if focused.key_input(i) {
+                            return;
+                        }
+                    }
+                }
+            }
+            // Otherwise, send data to the command buffer
+            (*this.command_buffer.lock().unwrap()).add_key(i);
+        }
+    }
+
+    /// Handle a typed char. This is only used by prompts.
+    pub fn char_input(this: Arc<State>, c: char) {
+        if this.curr_prompt.lock().unwrap().is_some() {
+            let finished = {
+                let mut curr_prompt = this.curr_prompt.lock().unwrap();
+                if curr_prompt.as_mut().unwrap().char_input(c) {
+                    curr_prompt.take()
+                } else {
+                    curr_prompt.as_mut().unwrap().update_completions(this.clone());
+                    None
+                }
+            };
+            // Call the callback without holding the lock, so it can prompt again
+            if let Some(p) = finished {
+                p.finish(this.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use command::Command;
+    use common::Rect;
+    use prompt::{PromptType as PT, Prompt as P};
+
+    #[test]
+    fn test_finish_command() {
+        let state = Arc::new(State::new());
+        // A command which doesn't prompt is complete as soon as it's run
+        *state.recording.lock().unwrap() = Some((Command::Navigate, Vec::new()));
+        state.finish_command();
+        assert!(state.recording.lock().unwrap().is_none());
+        let last_cmd = state.last_cmd.lock().unwrap().clone();
+        match last_cmd {
+            Some((Command::Navigate, ref answers)) => assert_eq!(answers.len(), 0),
+            ref c => panic!("Expected navigate, got {:?}", c),
+        }
+
+        // A command with a prompt open isn't complete yet
+        *state.recording.lock().unwrap() = Some((Command::CommandLine, Vec::new()));
+        State::prompt(state.clone(), vec![PT::String(P::new("Name"))], Box::new(|_| ()));
+        state.finish_command();
+        assert!(state.recording.lock().unwrap().is_some());
+        let last_cmd = state.last_cmd.lock().unwrap().clone();
+        match last_cmd {
+            Some((Command::Navigate, _)) => (),
+            ref c => panic!("Expected navigate, got {:?}", c),
+        }
+    }
+
+    struct NoView;
+
+    impl View for NoView {
+        fn render(&self, _g: &mut qgfx::RendererController, _bounds: Rect) {}
+    }
+
+    #[test]
+    fn test_record_macro() {
+        let state = Arc::new(State::new());
+        let answer = Arc::new(Mutex::new(String::new()));
+        let answer_clone = answer.clone();
+        *state.macro_recorder.lock().unwrap() = Some(macros::MacroRecorder::new("m"));
+        State::prompt(
+            state.clone(),
+            vec![PT::String(P::new("Name"))],
+            Box::new(move |data| *answer_clone.lock().unwrap() = data[0].val.clone()),
+        );
+        let events = vec![
+            MacroEvent::Key(input::InputChunk(VKC::A, 0)),
+            MacroEvent::Char('a'),
+            MacroEvent::Char('\r'),
+            MacroEvent::Key(input::InputChunk(VKC::C, 0)),
+            MacroEvent::Key(input::InputChunk(VKC::F, 0)),
+        ];
+        for e in events {
+            State::input_event(state.clone(), e, &NoView);
+        }
+        assert_eq!(*answer.lock().unwrap(), "a");
+        // Events played back from another macro aren't recorded
+        *state.playing_macro.lock().unwrap() = true;
+        State::input_event(state.clone(), MacroEvent::Key(input::InputChunk(VKC::N, 0)), &NoView);
+        *state.playing_macro.lock().unwrap() = false;
+        // Nor are the keys of the command which stops the recording
+        State::input_event(state.clone(), MacroEvent::Key(input::InputChunk(VKC::M, 0)), &NoView);
+        State::input_event(state.clone(), MacroEvent::Key(input::InputChunk(VKC::R, 0)), &NoView);
+        let m = state.macro_recorder.lock().unwrap().take().unwrap().finish();
+        assert_eq!(
+            m.events,
+            vec![
+                MacroEvent::Char('a'),
+                MacroEvent::Char('\r'),
+                MacroEvent::Key(input::InputChunk(VKC::C, 0)),
+                MacroEvent::Key(input::InputChunk(VKC::F, 0)),
+            ]
+        );
+    }
 }
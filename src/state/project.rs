@@ -1,6 +1,8 @@
 use java_model::*;
 use std::sync::Mutex;
+use std::collections::HashSet;
 use search::SearchBuffer;
+use refactor::{self, RefactorErr, Diagnostic, SignatureChange};
 
 pub enum AddDeclErr {
     DeclNotFound,
@@ -14,42 +16,120 @@ pub enum Selection {
     Package(String),
     /// Selection of a decl / interface. Contains the qualified decl name.
     Decl(String),
+    /// Selection of a field. Contains the qualified decl name and the field name.
+    Field(String, String),
+    /// Selection of a method. Contains the qualified decl name and the method name.
+    Method(String, String),
 }
 
 impl Selection {
-    /// Checks if this selection is a package, then checks if the package name matches the given
-    /// input p.
-    pub fn is_package(&self, p: &str) -> bool {
+    /// Get the qualified name of the selected decl, or the decl containing the selected member.
+    pub fn decl(&self) -> Option<&str> {
         match *self {
-            Selection::Package(ref _p) => p == _p,
-            _ => false,
+            Selection::Package(_) => None,
+            Selection::Decl(ref d) |
+            Selection::Field(ref d, _) |
+            Selection::Method(ref d, _) => Some(d),
         }
     }
 
-    /// Checks if this selection is a decl, then checks if the package name matches the given
-    /// input p.
-    pub fn is_decl(&self, p: &str) -> bool {
+    /// Get the name of the selected member, if a member is selected.
+    pub fn member(&self) -> Option<&str> {
         match *self {
-            Selection::Decl(ref _p) => p == _p,
-            _ => false,
+            Selection::Field(_, ref m) |
+            Selection::Method(_, ref m) => Some(m),
+            _ => None,
         }
     }
+
+    /// Get the selection as shown in the package tree. Members aren't shown in the tree, so
+    /// their decl is used instead.
+    pub fn tree_sel(&self) -> Selection {
+        match *self {
+            Selection::Field(ref d, _) |
+            Selection::Method(ref d, _) => Selection::Decl(d.clone()),
+            ref s => s.clone(),
+        }
+    }
+
+}
+
+/// Get the name of a member of the given qualified decl, as used in the symbol completion list.
+/// This is written like a javadoc link, e.g. `com.tom.Foo#bar` for a field and
+/// `com.tom.Foo#baz()` for a method.
+pub fn symbol_name(decl: &str, member: &ClassMember) -> String {
+    match *member {
+        ClassMember::Field(_) => format!("{}#{}", decl, member.name()),
+        ClassMember::Method(_) => format!("{}#{}()", decl, member.name()),
+    }
+}
+
+/// Get the name of a package as used in the symbol completion list. This is written like an
+/// on-demand import, e.g. `com.tom.*`, so that it can't be confused with a decl of the same name.
+pub fn package_symbol_name(pkg: &str) -> String {
+    format!("{}.*", pkg)
+}
+
+/// Get the selection of a member of the given qualified decl.
+pub fn member_sel(decl: &str, member: &ClassMember) -> Selection {
+    match *member {
+        ClassMember::Field(_) => Selection::Field(decl.to_owned(), member.name().to_owned()),
+        ClassMember::Method(_) => Selection::Method(decl.to_owned(), member.name().to_owned()),
+    }
+}
+
+/// Something which can have a doc comment attached.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DocTarget {
+    /// A package, given by its qualified name. This is the package-info doc.
+    Package(String),
+    /// A decl, given by its qualified name.
+    Decl(String),
+    /// A member of a decl, given by the qualified decl name and member name.
+    Member(String, String),
+}
+
+/// Something which can be annotated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnnotationTarget {
+    /// A decl, given by its qualified name.
+    Decl(String),
+    /// A member of a decl, given by the qualified decl name and member name.
+    Member(String, String),
+    /// A method parameter, given by the qualified decl name, method name and param name.
+    Param(String, String, String),
 }
 
 pub struct Project {
     pub package_list: Mutex<Vec<Package>>,
 
-    /// A searchable list of strings for autocompleting packages
+    /// A searchable list of strings for autocompleting packages. This is kept up to date as
+    /// packages are added.
     pub pkg_completion_list: Mutex<SearchBuffer>,
 
     /// A searchable list of strings for autocompleting decls. This will probably be pretty
-    /// fucking heavyweight to search.
+    /// fucking heavyweight to search. This is kept up to date as decls are added & renamed.
     pub decl_completion_list: Mutex<SearchBuffer>,
 
+    /// A searchable list of every package, decl and member, for going straight to any symbol.
+    /// Members are named as in `symbol_name`. This is kept up to date as packages, decls & members
+    /// are added, and updated after refactorings.
+    pub symbol_completion_list: Mutex<SearchBuffer>,
+
+    /// A searchable list of the annotation types bundled with the JDK. This never changes, so is
+    /// built once here rather than on every completion.
+    pub jdk_annotation_list: SearchBuffer,
+
     /// A reference to the current selcetion. This will be highlighted when rendering, and allows
     /// for faster editing due to context-aware commands (i.e. create decl will already have
     /// package filled in when that package is selected)
     pub curr_sel: Mutex<Option<Selection>>,
+
+    /// Problems found by refactorings which the user needs to fix by hand.
+    pub diagnostics: Mutex<Vec<Diagnostic>>,
+
+    /// The qualified names of packages which are collapsed in the package tree
+    pub collapsed: Mutex<HashSet<String>>,
 }
 
 impl Project {
@@ -59,10 +139,20 @@ impl Project {
             curr_sel: Mutex::new(None),
             pkg_completion_list: Mutex::new(SearchBuffer::new()),
             decl_completion_list: Mutex::new(SearchBuffer::new()),
+            symbol_completion_list: Mutex::new(SearchBuffer::new()),
+            jdk_annotation_list: {
+                let mut list = SearchBuffer::new();
+                list.add_strings(JDK_ANNOTATIONS);
+                list
+            },
+            diagnostics: Mutex::new(Vec::new()),
+            collapsed: Mutex::new(HashSet::new()),
         }
     }
 
-    /// Regenerate the decl completion list.
+    /// Regenerate the decl completion list. This is only needed if the package list is changed
+    /// directly.
+    #[allow(dead_code)]
     pub fn regen_decl_completion_list(&self) {
         let decl_completion_list = &mut *self.decl_completion_list.lock().unwrap();
         decl_completion_list.clear();
@@ -72,7 +162,8 @@ impl Project {
         }
     }
 
-    /// Regenerate the package completion list.
+    /// Regenerate the package completion list. This is only needed if the package list is changed
+    /// directly.
     #[allow(dead_code)]
     pub fn regen_pkg_completion_list(&self) {
         let pkg_completion_list = &mut *self.pkg_completion_list.lock().unwrap();
@@ -83,12 +174,128 @@ impl Project {
         }
     }
 
+    /// Get the symbols of the given qualified decls & their members, as listed in the symbol
+    /// completion list.
+    fn decl_symbols(&self, decls: &[String]) -> HashSet<String> {
+        let mut symbols = HashSet::new();
+        let package_list = self.package_list.lock().unwrap();
+        for d in decls {
+            if let Some(&Declaration::Class(ref c)) =
+                package_list.iter().filter_map(|p| p.find_decl(d)).next()
+            {
+                symbols.insert(d.clone());
+                for m in &c.members {
+                    symbols.insert(symbol_name(d, m));
+                }
+            }
+        }
+        symbols
+    }
+
+    /// Update the symbol completion list after the given decls have changed. `before` is the
+    /// result of `decl_symbols` from before the change, and `decls` should include any decls the
+    /// change added.
+    fn update_decl_symbols(&self, before: HashSet<String>, decls: &[String]) {
+        let after = self.decl_symbols(decls);
+        let mut symbol_completion_list = self.symbol_completion_list.lock().unwrap();
+        for s in before.difference(&after) {
+            symbol_completion_list.remove(s);
+        }
+        for s in after.difference(&before) {
+            symbol_completion_list.insert(s);
+        }
+    }
+
+    /// Find the symbol with the given name, as listed in the symbol completion list.
+    pub fn find_symbol(&self, name: &str) -> Option<Selection> {
+        let package_list = self.package_list.lock().unwrap();
+        if let Some(ix) = name.find('#') {
+            let (decl, member) = (&name[..ix], &name[ix + 1..]);
+            let (member, is_method) = if member.ends_with("()") {
+                (&member[..member.len() - 2], true)
+            } else {
+                (member, false)
+            };
+            let m = package_list
+                .iter()
+                .filter_map(|p| match p.find_decl(decl) {
+                    Some(&Declaration::Class(ref c)) => c.find_member(member),
+                    _ => None,
+                })
+                .next();
+            return match (m, is_method) {
+                (Some(&ClassMember::Field(_)), false) => {
+                    Some(Selection::Field(decl.to_owned(), member.to_owned()))
+                }
+                (Some(&ClassMember::Method(_)), true) => {
+                    Some(Selection::Method(decl.to_owned(), member.to_owned()))
+                }
+                _ => None,
+            };
+        }
+        if name.ends_with(".*") {
+            let name = &name[..name.len() - 2];
+            if package_list.iter().any(|p| match p.find_pkg(name) {
+                (Some(_), None) => true,
+                _ => false,
+            })
+            {
+                return Some(Selection::Package(name.to_owned()));
+            }
+            return None;
+        }
+        if package_list.iter().any(|p| p.find_decl(name).is_some()) {
+            return Some(Selection::Decl(name.to_owned()));
+        }
+        None
+    }
+
+    /// Move the selection to the next member of the selected decl, or the previous member if
+    /// `forward` is false. If the decl itself is selected, its first or last member is selected.
+    pub fn select_member(&self, forward: bool) {
+        let sel = match self.curr_sel.lock().unwrap().clone() {
+            Some(sel) => sel,
+            None => return,
+        };
+        let decl = match sel.decl() {
+            Some(d) => d,
+            None => return,
+        };
+        let new_sel = {
+            let package_list = self.package_list.lock().unwrap();
+            let members = match package_list.iter().filter_map(|p| p.find_decl(decl)).next() {
+                Some(&Declaration::Class(ref c)) if c.members.len() > 0 => &c.members,
+                _ => return,
+            };
+            let pos = sel.member().and_then(|m| members.iter().position(|o| o.name() == m));
+            let ix = match (pos, forward) {
+                (Some(ix), true) => (ix + 1).min(members.len() - 1),
+                (Some(ix), false) => ix.saturating_sub(1),
+                (None, true) => 0,
+                (None, false) => members.len() - 1,
+            };
+            member_sel(decl, &members[ix])
+        };
+        *self.curr_sel.lock().unwrap() = Some(new_sel);
+    }
+
     /// Add a declaration to the given package
     pub fn add_decl(&self, pkg: &str, decl: Declaration) {
         assert!(pkg.len() > 0, "Trying to add decl to default package, not implemented: {}", pkg);
         self.add_package(pkg);
+        let name = format!("{}.{}", pkg, decl.name());
         for p in self.package_list.lock().unwrap().iter_mut() {
             if p.add_decl(pkg, decl.clone()) {
+                self.decl_completion_list.lock().unwrap().insert(&name);
+                let mut symbol_completion_list = self.symbol_completion_list.lock().unwrap();
+                symbol_completion_list.insert(&name);
+                match decl {
+                    Declaration::Class(ref c) => {
+                        for m in &c.members {
+                            symbol_completion_list.insert(&symbol_name(&name, m));
+                        }
+                    }
+                }
                 return;
             }
         }
@@ -97,6 +304,16 @@ impl Project {
     /// Add a fully qualified package name. If the start of the package name is already used, trace
     /// down the tree and insert new package in the appropriate replaces.     
     pub fn add_package(&self, name: &str) {
+        {
+            // Complete the package & each of its parents
+            let mut pkg_completion_list = self.pkg_completion_list.lock().unwrap();
+            let mut symbol_completion_list = self.symbol_completion_list.lock().unwrap();
+            let parents = name.match_indices('.').map(|(ix, _)| &name[..ix]);
+            for n in parents.chain(Some(name)) {
+                pkg_completion_list.insert(n);
+                symbol_completion_list.insert(&package_symbol_name(n));
+            }
+        }
         let mut package_list = self.package_list.lock().unwrap();
         for p in package_list.iter_mut() {
             let deepest = p.add_subpackage(name);
@@ -110,10 +327,19 @@ impl Project {
     /// Add a field to a given fully qualified decl name. Returns Err if decl not found, or if decl
     /// wasn't the type of decl to accept fields (i.e. enum)
     pub fn add_decl_field(&self, name: &str, field: Field) -> Result<(), AddDeclErr>{
+        self.add_decl_member(name, ClassMember::Field(field))
+    }
+
+    /// Add a member to a given fully qualified decl name. Returns Err if decl not found, or if
+    /// decl wasn't the type of decl to accept members.
+    pub fn add_decl_member(&self, name: &str, member: ClassMember) -> Result<(), AddDeclErr> {
         for p in self.package_list.lock().unwrap().iter_mut() {
             match p.find_decl_mut(name) {
                 Some(&mut Declaration::Class(ref mut c)) => {
-                    c.members.push(ClassMember::Field(field));
+                    self.symbol_completion_list.lock().unwrap().insert(
+                        &symbol_name(name, &member),
+                    );
+                    c.members.push(member);
                     return Ok(());
                 }
                 _ => ()
@@ -121,4 +347,335 @@ impl Project {
         }
         return Err(AddDeclErr::DeclNotFound);
     }
+
+    /// Move a member of the given fully qualified class to its project-local superclass.
+    pub fn pull_up_member(&self, class_name: &str, member_name: &str) -> Result<(), RefactorErr> {
+        let mut decls = vec![class_name.to_owned()];
+        decls.extend(
+            refactor::find_superclass(&self.package_list.lock().unwrap()[..], class_name).ok(),
+        );
+        let before = self.decl_symbols(&decls);
+        refactor::pull_up_member(
+            &mut self.package_list.lock().unwrap()[..],
+            class_name,
+            member_name,
+        )?;
+        self.update_decl_symbols(before, &decls);
+        Ok(())
+    }
+
+    /// Move a member of the given fully qualified class into all of its project-local
+    /// subclasses.
+    pub fn push_down_member(&self, class_name: &str, member_name: &str) -> Result<(), RefactorErr> {
+        let mut decls = vec![class_name.to_owned()];
+        decls.extend(refactor::find_subclasses(&self.package_list.lock().unwrap()[..], class_name));
+        let before = self.decl_symbols(&decls);
+        refactor::push_down_member(
+            &mut self.package_list.lock().unwrap()[..],
+            class_name,
+            member_name,
+        )?;
+        self.update_decl_symbols(before, &decls);
+        Ok(())
+    }
+
+    /// Make a field of the given fully qualified class private, generating a getter & setter.
+    /// Any accesses which couldn't be updated are added to the diagnostics list.
+    pub fn encapsulate_field(&self, class_name: &str, field_name: &str) -> Result<(), RefactorErr> {
+        let decls = vec![class_name.to_owned()];
+        let before = self.decl_symbols(&decls);
+        let mut diagnostics = refactor::encapsulate_field(
+            &mut self.package_list.lock().unwrap()[..],
+            class_name,
+            field_name,
+        )?;
+        self.diagnostics.lock().unwrap().append(&mut diagnostics);
+        self.update_decl_symbols(before, &decls);
+        Ok(())
+    }
+
+    /// Get a copy of a method in the given fully qualified class. Fails if the method is
+    /// overloaded.
+    pub fn find_method(&self, class_name: &str, method_name: &str) -> Result<Method, RefactorErr> {
+        refactor::find_method(
+            &self.package_list.lock().unwrap()[..],
+            class_name,
+            method_name,
+        ).map(|m| m.clone())
+    }
+
+    /// Change the signature of a method in the given fully qualified class. Any calls which
+    /// couldn't be updated are added to the diagnostics list.
+    pub fn change_signature(
+        &self,
+        class_name: &str,
+        method_name: &str,
+        change: &SignatureChange,
+    ) -> Result<(), RefactorErr> {
+        let mut diagnostics = refactor::change_signature(
+            &mut self.package_list.lock().unwrap()[..],
+            class_name,
+            method_name,
+            change,
+        )?;
+        self.diagnostics.lock().unwrap().append(&mut diagnostics);
+        Ok(())
+    }
+
+    /// Call the given function with a mutable reference to the doc comment of the target.
+    /// Returns false if the target couldn't be found, in which case the function isn't called.
+    pub fn edit_doc<F: FnOnce(&mut Option<DocComment>)>(&self, target: &DocTarget, f: F) -> bool {
+        for p in self.package_list.lock().unwrap().iter_mut() {
+            match *target {
+                DocTarget::Package(ref name) => {
+                    if let (Some(pkg), None) = p.find_pkg_mut(name) {
+                        f(&mut pkg.doc);
+                        return true;
+                    }
+                }
+                DocTarget::Decl(ref name) => {
+                    if let Some(&mut Declaration::Class(ref mut c)) = p.find_decl_mut(name) {
+                        f(&mut c.doc);
+                        return true;
+                    }
+                }
+                DocTarget::Member(ref decl, ref member) => {
+                    if let Some(&mut Declaration::Class(ref mut c)) = p.find_decl_mut(decl) {
+                        return match c.find_member_mut(member) {
+                            Some(m) => {
+                                f(m.doc_mut());
+                                true
+                            }
+                            None => false,
+                        };
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    /// Call the given function with a mutable reference to the annotations of the target.
+    /// Returns false if the target couldn't be found, in which case the function isn't called.
+    pub fn edit_annotations<F: FnOnce(&mut Vec<Annotation>)>(
+        &self,
+        target: &AnnotationTarget,
+        f: F,
+    ) -> bool {
+        let decl = match *target {
+            AnnotationTarget::Decl(ref d) |
+            AnnotationTarget::Member(ref d, _) |
+            AnnotationTarget::Param(ref d, _, _) => d,
+        };
+        for p in self.package_list.lock().unwrap().iter_mut() {
+            let c = match p.find_decl_mut(decl) {
+                Some(&mut Declaration::Class(ref mut c)) => c,
+                _ => continue,
+            };
+            match *target {
+                AnnotationTarget::Decl(_) => {
+                    f(&mut c.annotations);
+                    return true;
+                }
+                AnnotationTarget::Member(_, ref member) => {
+                    if let Some(m) = c.find_member_mut(member) {
+                        f(m.annotations_mut());
+                        return true;
+                    }
+                }
+                AnnotationTarget::Param(_, ref method, ref param) => {
+                    if let Some(&mut ClassMember::Method(ref mut m)) = c.find_member_mut(method) {
+                        if let Some(p) = m.params.iter_mut().find(|p| &p.name == param) {
+                            f(&mut p.annotations);
+                            return true;
+                        }
+                    }
+                }
+            }
+            return false;
+        }
+        false
+    }
+
+    /// Rename the given fully qualified decl, updating references to it. The selection is updated
+    /// if it was the renamed decl.
+    pub fn rename_decl(&self, decl_name: &str, new_name: &str) -> Result<(), RefactorErr> {
+        let before = self.decl_symbols(&[decl_name.to_owned()]);
+        refactor::rename_decl(
+            &mut self.package_list.lock().unwrap()[..],
+            decl_name,
+            new_name,
+        )?;
+        let pkg_name = refactor::split_qualified(decl_name).unwrap().0;
+        let new_qualified = format!("{}.{}", pkg_name, new_name);
+        let mut curr_sel = self.curr_sel.lock().unwrap();
+        let renamed = match *curr_sel {
+            Some(Selection::Decl(ref d)) if d == decl_name => {
+                Some(Selection::Decl(new_qualified.clone()))
+            }
+            Some(Selection::Field(ref d, ref m)) if d == decl_name => {
+                Some(Selection::Field(new_qualified.clone(), m.clone()))
+            }
+            Some(Selection::Method(ref d, ref m)) if d == decl_name => {
+                Some(Selection::Method(new_qualified.clone(), m.clone()))
+            }
+            _ => None,
+        };
+        if renamed.is_some() {
+            *curr_sel = renamed;
+        }
+        drop(curr_sel);
+        {
+            let mut decl_completion_list = self.decl_completion_list.lock().unwrap();
+            decl_completion_list.remove(decl_name);
+            decl_completion_list.insert(&new_qualified);
+        }
+        self.update_decl_symbols(before, &[new_qualified]);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_completion_lists() {
+        let project = Project::new();
+        project.add_decl("com.tom", Declaration::Class(Class::new_with_name("Foo")));
+        project.add_package("com.other");
+        {
+            let pkgs = project.pkg_completion_list.lock().unwrap();
+            assert!(pkgs.contains("com"));
+            assert!(pkgs.contains("com.tom"));
+            assert!(pkgs.contains("com.other"));
+            assert!(project.decl_completion_list.lock().unwrap().contains("com.tom.Foo"));
+        }
+        assert!(project.rename_decl("com.tom.Foo", "Bar").is_ok());
+        let decls = project.decl_completion_list.lock().unwrap();
+        assert!(!decls.contains("com.tom.Foo"));
+        assert!(decls.contains("com.tom.Bar"));
+    }
+
+    #[test]
+    fn test_symbols() {
+        let project = Project::new();
+        let mut class = Class::new_with_name("Foo");
+        class.members.push(ClassMember::Method(Method {
+            modifiers: Vec::new(),
+            return_type: "void".to_owned(),
+            name: "run".to_owned(),
+            params: Vec::new(),
+            body: Vec::new(),
+            doc: None,
+            annotations: Vec::new(),
+        }));
+        project.add_decl("com.tom", Declaration::Class(class));
+        project.add_package("com.tom.Foo");
+        let field = Field {
+            modifiers: Vec::new(),
+            field_type: "int".to_owned(),
+            name: "count".to_owned(),
+            doc: None,
+            annotations: Vec::new(),
+        };
+        assert!(project.add_decl_field("com.tom.Foo", field).is_ok());
+        {
+            let symbols = project.symbol_completion_list.lock().unwrap();
+            for s in &["com.*", "com.tom.*", "com.tom.Foo", "com.tom.Foo.*", "com.tom.Foo#run()",
+                       "com.tom.Foo#count"] {
+                assert!(symbols.contains(s), "Missing symbol {}", s);
+            }
+        }
+        assert_eq!(
+            project.find_symbol("com.tom.*"),
+            Some(Selection::Package("com.tom".to_owned()))
+        );
+        // A decl & a package with the same name can both be found
+        assert_eq!(
+            project.find_symbol("com.tom.Foo"),
+            Some(Selection::Decl("com.tom.Foo".to_owned()))
+        );
+        assert_eq!(
+            project.find_symbol("com.tom.Foo.*"),
+            Some(Selection::Package("com.tom.Foo".to_owned()))
+        );
+        assert_eq!(project.find_symbol("com.tom"), None);
+        assert_eq!(
+            project.find_symbol("com.tom.Foo#run()"),
+            Some(Selection::Method("com.tom.Foo".to_owned(), "run".to_owned()))
+        );
+        assert_eq!(
+            project.find_symbol("com.tom.Foo#count"),
+            Some(Selection::Field("com.tom.Foo".to_owned(), "count".to_owned()))
+        );
+        assert_eq!(project.find_symbol("com.tom.Foo#count()"), None);
+        assert_eq!(project.find_symbol("com.tom.Bar"), None);
+
+        *project.curr_sel.lock().unwrap() =
+            Some(Selection::Method("com.tom.Foo".to_owned(), "run".to_owned()));
+        assert!(project.rename_decl("com.tom.Foo", "Bar").is_ok());
+        assert_eq!(
+            project.curr_sel.lock().unwrap().clone(),
+            Some(Selection::Method("com.tom.Bar".to_owned(), "run".to_owned()))
+        );
+        {
+            let symbols = project.symbol_completion_list.lock().unwrap();
+            assert!(symbols.contains("com.tom.Bar#run()"));
+            assert!(!symbols.contains("com.tom.Foo#run()"));
+        }
+
+        project.select_member(true);
+        assert_eq!(
+            project.curr_sel.lock().unwrap().clone(),
+            Some(Selection::Field("com.tom.Bar".to_owned(), "count".to_owned()))
+        );
+        project.select_member(true);
+        project.select_member(false);
+        project.select_member(false);
+        assert_eq!(
+            project.curr_sel.lock().unwrap().clone(),
+            Some(Selection::Method("com.tom.Bar".to_owned(), "run".to_owned()))
+        );
+        *project.curr_sel.lock().unwrap() = Some(Selection::Decl("com.tom.Bar".to_owned()));
+        project.select_member(false);
+        assert_eq!(
+            project.curr_sel.lock().unwrap().clone(),
+            Some(Selection::Field("com.tom.Bar".to_owned(), "count".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_refactor_symbols() {
+        let project = Project::new();
+        let mut child = Class::new_with_name("Child");
+        child.extends = "Base".to_owned();
+        child.members.push(ClassMember::Field(Field {
+            modifiers: vec![Modifier::Protected],
+            field_type: "int".to_owned(),
+            name: "count".to_owned(),
+            doc: None,
+            annotations: Vec::new(),
+        }));
+        project.add_decl("com.tom", Declaration::Class(Class::new_with_name("Base")));
+        project.add_decl("com.tom", Declaration::Class(child));
+
+        assert!(project.pull_up_member("com.tom.Child", "count").is_ok());
+        {
+            let symbols = project.symbol_completion_list.lock().unwrap();
+            assert!(symbols.contains("com.tom.Base#count"));
+            assert!(!symbols.contains("com.tom.Child#count"));
+        }
+        assert!(project.encapsulate_field("com.tom.Base", "count").is_ok());
+        {
+            let symbols = project.symbol_completion_list.lock().unwrap();
+            assert!(symbols.contains("com.tom.Base#getCount()"));
+            assert!(symbols.contains("com.tom.Base#setCount()"));
+        }
+        assert!(project.push_down_member("com.tom.Base", "count").is_ok());
+        let symbols = project.symbol_completion_list.lock().unwrap();
+        assert!(symbols.contains("com.tom.Child#count"));
+        assert!(!symbols.contains("com.tom.Base#count"));
+        assert!(symbols.contains("com.tom.Child"));
+    }
 }
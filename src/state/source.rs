@@ -0,0 +1,114 @@
+//! Importing & exporting the project as java source files, laid out in directories by package.
+
+use super::{Project, DocTarget};
+use java_model::*;
+use refactor::Diagnostic;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// The name of the file holding a package's doc
+const PACKAGE_INFO_FILE: &'static str = "package-info.java";
+
+fn write_file(path: &Path, contents: &str) -> io::Result<()> {
+    File::create(path)?.write_all(contents.as_bytes())
+}
+
+/// Write the source of a package & its subpackages into the given directory, which is the
+/// directory of the package's parent. `prefix` is the qualified name of the parent, followed by
+/// a '.', or empty for a root package.
+fn export_package(pkg: &Package, prefix: &str, dir: &Path) -> io::Result<()> {
+    let name = format!("{}{}", prefix, pkg.name);
+    let dir = dir.join(&pkg.name);
+    fs::create_dir_all(&dir)?;
+    if pkg.doc.is_some() {
+        write_file(&dir.join(PACKAGE_INFO_FILE), &package_info_to_source(&name, &pkg.doc))?;
+    }
+    for d in &pkg.decl_list {
+        match *d {
+            Declaration::Class(ref c) => {
+                write_file(&dir.join(format!("{}.java", c.name)), &class_to_source(&name, c))?;
+            }
+        }
+    }
+    for p in &pkg.package_list {
+        export_package(p, &format!("{}.", name), &dir)?;
+    }
+    Ok(())
+}
+
+/// Find all the .java files under a directory, in a consistent order.
+fn find_java_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries = Vec::new();
+    for e in fs::read_dir(dir)? {
+        entries.push(e?.path());
+    }
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            find_java_files(&path, files)?;
+        } else if path.extension().map(|e| e == "java") == Some(true) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+impl Project {
+    /// Write every class to `<dir>/<package path>/<Class>.java`, and the doc of each documented
+    /// package to its package-info.java.
+    pub fn export_source(&self, dir: &Path) -> io::Result<()> {
+        for p in self.package_list.lock().unwrap().iter() {
+            export_package(p, "", dir)?;
+        }
+        Ok(())
+    }
+
+    /// Import the classes & package docs in the .java files under the given directory. Files
+    /// which can't be parsed, and classes which already exist, are skipped & added to the
+    /// diagnostics list. Returns the number of files imported.
+    pub fn import_source(&self, dir: &Path) -> io::Result<usize> {
+        let mut files = Vec::new();
+        find_java_files(dir, &mut files)?;
+        let mut imported = 0;
+        for path in files {
+            let mut src = String::new();
+            File::open(&path)?.read_to_string(&mut src)?;
+            let problem = |line: usize, msg: &str| {
+                Diagnostic {
+                    location: format!("{}:{}", path.display(), line),
+                    message: msg.to_owned(),
+                }
+            };
+            let result = if path.file_name().map(|n| n == PACKAGE_INFO_FILE) == Some(true) {
+                parse_package_info(&src).map(|(pkg, doc)| {
+                    self.add_package(&pkg);
+                    self.edit_doc(&DocTarget::Package(pkg), |d| *d = doc);
+                    true
+                })
+            } else {
+                parse_class_source(&src).map(|(pkg, class)| {
+                    let name = format!("{}.{}", pkg, class.name);
+                    let exists = self.package_list.lock().unwrap().iter().any(|p| {
+                        p.find_decl(&name).is_some()
+                    });
+                    if exists {
+                        return false;
+                    }
+                    self.add_decl(&pkg, Declaration::Class(class));
+                    true
+                })
+            };
+            match result {
+                Ok(true) => imported += 1,
+                Ok(false) => {
+                    self.diagnostics.lock().unwrap().push(
+                        problem(1, "Class already exists"),
+                    )
+                }
+                Err(e) => self.diagnostics.lock().unwrap().push(problem(e.line, &e.msg)),
+            }
+        }
+        Ok(imported)
+    }
+}
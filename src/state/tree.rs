@@ -0,0 +1,254 @@
+//! The tree of packages & decls shown in the package list, flattened into rows, and keyboard
+//! navigation of it.
+
+use super::{Project, Selection};
+use java_model::*;
+use config;
+use std::collections::HashSet;
+use std::io;
+
+/// The name of the file in the config directory listing the collapsed packages
+const COLLAPSED_FILE: &'static str = "collapsed";
+
+/// A row in the package tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeItem {
+    pub sel: Selection,
+    /// The text to display. Packages show their qualified name, decls just their name.
+    pub label: String,
+    /// The number of parents this item has in the tree
+    pub depth: usize,
+    /// True if this is a package with subpackages or decls
+    pub has_children: bool,
+    /// True if this is a package whose children are shown
+    pub expanded: bool,
+}
+
+/// A movement of the selection in the package tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TreeMotion {
+    Next,
+    Prev,
+    Parent,
+    /// Move to the first child, expanding the package if needed
+    FirstChild,
+    /// Expand or collapse the selected package
+    Toggle,
+}
+
+/// Add the rows for a package & its children to the list.
+fn push_pkg_items(
+    items: &mut Vec<TreeItem>,
+    pkg: &Package,
+    prefix: &str,
+    depth: usize,
+    collapsed: &HashSet<String>,
+) {
+    let name = format!("{}{}", prefix, pkg.name);
+    let expanded = !collapsed.contains(&name);
+    items.push(TreeItem {
+        sel: Selection::Package(name.clone()),
+        label: name.clone(),
+        depth: depth,
+        has_children: pkg.package_list.len() > 0 || pkg.decl_list.len() > 0,
+        expanded: expanded,
+    });
+    if !expanded {
+        return;
+    }
+    let prefix = name + ".";
+    for p in &pkg.package_list {
+        push_pkg_items(items, p, &prefix, depth + 1, collapsed);
+    }
+    for d in &pkg.decl_list {
+        items.push(TreeItem {
+            sel: Selection::Decl(format!("{}{}", prefix, d.name())),
+            label: d.name().to_owned(),
+            depth: depth + 1,
+            has_children: false,
+            expanded: false,
+        });
+    }
+}
+
+/// Get the next selection after applying the motion to the selected row. Returns the new
+/// selection, along with a package to expand or collapse.
+fn apply_motion(
+    items: &[TreeItem],
+    ix: usize,
+    motion: TreeMotion,
+) -> (usize, Option<(String, bool)>) {
+    let item = &items[ix];
+    match motion {
+        TreeMotion::Next if ix + 1 < items.len() => (ix + 1, None),
+        TreeMotion::Prev if ix > 0 => (ix - 1, None),
+        TreeMotion::Parent if item.depth > 0 => {
+            let parent = items[..ix].iter().rposition(|i| i.depth < item.depth);
+            (parent.unwrap_or(ix), None)
+        }
+        TreeMotion::FirstChild if item.has_children => {
+            if item.expanded {
+                (ix + 1, None)
+            } else {
+                (ix, Some((item.label.clone(), true)))
+            }
+        }
+        TreeMotion::Toggle if item.has_children => {
+            (ix, Some((item.label.clone(), !item.expanded)))
+        }
+        _ => (ix, None),
+    }
+}
+
+impl Project {
+    /// Get the rows of the package tree, skipping the children of collapsed packages.
+    pub fn tree_items(&self) -> Vec<TreeItem> {
+        let package_list = self.package_list.lock().unwrap();
+        let collapsed = self.collapsed.lock().unwrap();
+        let mut items = Vec::new();
+        for p in package_list.iter() {
+            push_pkg_items(&mut items, p, "", 0, &collapsed);
+        }
+        items
+    }
+
+    /// Expand or collapse the package with the given qualified name
+    pub fn set_expanded(&self, pkg: &str, expanded: bool) {
+        let mut collapsed = self.collapsed.lock().unwrap();
+        if expanded {
+            collapsed.remove(pkg);
+        } else {
+            collapsed.insert(pkg.to_owned());
+        }
+    }
+
+    /// Load the collapsed packages from the config directory, so that the tree looks the same as
+    /// in the last session.
+    pub fn load_collapsed(&self) {
+        if let Some(src) = config::read_config_file(COLLAPSED_FILE) {
+            let mut collapsed = self.collapsed.lock().unwrap();
+            collapsed.extend(src.lines().filter(|l| l.len() > 0).map(|l| l.to_owned()));
+        }
+    }
+
+    /// Save the collapsed packages to the config directory.
+    pub fn save_collapsed(&self) -> io::Result<()> {
+        let mut names: Vec<String> = self.collapsed.lock().unwrap().iter().cloned().collect();
+        names.sort();
+        let mut contents = names.join("\n");
+        contents.push('\n');
+        config::write_config_file(COLLAPSED_FILE, &contents)
+    }
+
+    /// Expand every package containing the given selection, so that it's shown in the tree
+    pub fn reveal(&self, sel: &Selection) {
+        let name = match *sel {
+            Selection::Package(ref n) |
+            Selection::Decl(ref n) |
+            Selection::Field(ref n, _) |
+            Selection::Method(ref n, _) => n,
+        };
+        let mut collapsed = self.collapsed.lock().unwrap();
+        for (ix, _) in name.match_indices('.') {
+            collapsed.remove(&name[..ix]);
+        }
+    }
+
+    /// Move the current selection through the package tree. If nothing is selected, the first
+    /// row is selected. If a member is selected, the motion starts from its decl.
+    pub fn navigate(&self, motion: TreeMotion) {
+        let curr_sel = self.curr_sel.lock().unwrap().as_ref().map(|s| s.tree_sel());
+        if let Some(ref sel) = curr_sel {
+            self.reveal(sel);
+        }
+        let items = self.tree_items();
+        if items.len() == 0 {
+            return;
+        }
+        let ix = match curr_sel {
+            Some(ref sel) => items.iter().position(|i| &i.sel == sel),
+            None => None,
+        };
+        let ix = match ix {
+            Some(ix) => ix,
+            None => {
+                *self.curr_sel.lock().unwrap() = Some(items[0].sel.clone());
+                return;
+            }
+        };
+        let (new_ix, expand) = apply_motion(&items, ix, motion);
+        if let Some((pkg, expanded)) = expand {
+            self.set_expanded(&pkg, expanded);
+        }
+        *self.curr_sel.lock().unwrap() = Some(items[new_ix].sel.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_project() -> Project {
+        let project = Project::new();
+        project.add_decl("com.a", Declaration::Class(Class::new_with_name("A")));
+        project.add_decl("com.b", Declaration::Class(Class::new_with_name("B")));
+        project
+    }
+
+    fn sel(project: &Project) -> Selection {
+        project.curr_sel.lock().unwrap().clone().unwrap()
+    }
+
+    #[test]
+    fn test_tree_items() {
+        let project = test_project();
+        let labels: Vec<(String, usize)> = project
+            .tree_items()
+            .into_iter()
+            .map(|i| (i.label, i.depth))
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                ("com".to_owned(), 0),
+                ("com.a".to_owned(), 1),
+                ("A".to_owned(), 2),
+                ("com.b".to_owned(), 1),
+                ("B".to_owned(), 2),
+            ]
+        );
+        project.set_expanded("com.a", false);
+        assert_eq!(project.tree_items().len(), 4);
+        project.reveal(&Selection::Decl("com.a.A".to_owned()));
+        assert_eq!(project.tree_items().len(), 5);
+    }
+
+    #[test]
+    fn test_navigate() {
+        let project = test_project();
+        project.navigate(TreeMotion::Next);
+        assert_eq!(sel(&project), Selection::Package("com".to_owned()));
+        project.navigate(TreeMotion::Prev);
+        assert_eq!(sel(&project), Selection::Package("com".to_owned()));
+        project.navigate(TreeMotion::FirstChild);
+        project.navigate(TreeMotion::Next);
+        project.navigate(TreeMotion::Next);
+        assert_eq!(sel(&project), Selection::Package("com.b".to_owned()));
+        project.navigate(TreeMotion::Toggle);
+        project.navigate(TreeMotion::Next);
+        assert_eq!(sel(&project), Selection::Package("com.b".to_owned()));
+        project.navigate(TreeMotion::FirstChild);
+        assert_eq!(project.tree_items().len(), 5);
+        project.navigate(TreeMotion::FirstChild);
+        assert_eq!(sel(&project), Selection::Decl("com.b.B".to_owned()));
+        project.navigate(TreeMotion::Parent);
+        project.navigate(TreeMotion::Parent);
+        assert_eq!(sel(&project), Selection::Package("com".to_owned()));
+
+        // Selecting a hidden item reveals it
+        project.set_expanded("com", false);
+        *project.curr_sel.lock().unwrap() = Some(Selection::Decl("com.a.A".to_owned()));
+        project.navigate(TreeMotion::Prev);
+        assert_eq!(sel(&project), Selection::Package("com.a".to_owned()));
+    }
+}
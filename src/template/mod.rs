@@ -0,0 +1,357 @@
+//! User defined templates for declarations & members, loaded from the `templates` config file.
+//!
+//! A template starts with a header giving its name and, optionally, the key sequence to bind it
+//! to. This is followed by placeholder definitions, then the lines to expand. For example:
+//!
+//! ```text
+//! # A singleton class
+//! [singleton ts]
+//! param Package package = @sel
+//! param Name string
+//! package ${Package}
+//! class ${Name}
+//! field private static final ${Name} INSTANCE
+//! method public static ${Name} getInstance()
+//! body return INSTANCE;
+//! ```
+//!
+//! Placeholders are `string`, `package` or `decl`, which decides how they're completed when
+//! prompting. A default of `@sel` uses the current selection. Lines can be:
+//!
+//! * `package <name>` - Sets the package for following `class` lines
+//! * `class <name>` - Creates a class, which following members are added to
+//! * `into <qualified decl>` - Adds following members to an existing decl
+//! * `field <modifiers> <type> <name>`
+//! * `method <modifiers> <return type> <name>(<params>)`
+//! * `body <line>` - Adds a line to the body of the last method
+
+use java_model::*;
+use config;
+use common::split_top_level;
+
+/// The name of the template file in the config directory
+const TEMPLATE_FILE: &'static str = "templates";
+
+/// The kind of value a placeholder takes. This decides how the placeholder is completed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlaceholderKind {
+    String,
+    Package,
+    Decl,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placeholder {
+    pub name: String,
+    pub kind: PlaceholderKind,
+    /// The default value. `@sel` means the current selection.
+    pub default: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    pub name: String,
+    /// The key sequence to bind this template to, if any
+    pub keys: Option<String>,
+    pub placeholders: Vec<Placeholder>,
+    /// The lines to expand, along with their line number in the template file
+    lines: Vec<(usize, String)>,
+}
+
+/// An error in a template file, with the line number (starting at 1) it occurred on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateErr {
+    pub line: usize,
+    pub msg: String,
+}
+
+/// An item generated by expanding a template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateItem {
+    /// A new decl in the given package
+    Decl(String, Declaration),
+    /// A new member in the given fully qualified decl
+    Member(String, ClassMember),
+}
+
+fn err(line: usize, msg: &str) -> TemplateErr {
+    TemplateErr {
+        line: line,
+        msg: msg.to_owned(),
+    }
+}
+
+/// Parse the contents of a template file.
+pub fn parse_templates(src: &str) -> Result<Vec<Template>, TemplateErr> {
+    let mut templates: Vec<Template> = Vec::new();
+    for (ii, line) in src.lines().enumerate() {
+        let line_num = ii + 1;
+        let line = line.trim();
+        if line.len() == 0 || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') {
+            if !line.ends_with(']') {
+                return Err(err(line_num, "Unclosed template header"));
+            }
+            let mut words = line[1..line.len() - 1].split_whitespace();
+            let name = match words.next() {
+                Some(n) => n.to_owned(),
+                None => return Err(err(line_num, "Template has no name")),
+            };
+            templates.push(Template {
+                name: name,
+                keys: words.next().map(|k| k.to_owned()),
+                placeholders: Vec::new(),
+                lines: Vec::new(),
+            });
+            continue;
+        }
+        let template = match templates.last_mut() {
+            Some(t) => t,
+            None => return Err(err(line_num, "Line outside of a template")),
+        };
+        let (keyword, rest) = match line.find(char::is_whitespace) {
+            Some(ix) => (&line[..ix], line[ix..].trim()),
+            None => (line, ""),
+        };
+        match keyword {
+            "param" => {
+                let (def, default) = match rest.find('=') {
+                    Some(ix) => (rest[..ix].trim(), Some(rest[ix + 1..].trim().to_owned())),
+                    None => (rest, None),
+                };
+                let mut words = def.split_whitespace();
+                let (name, kind) = match (words.next(), words.next(), words.next()) {
+                    (Some(n), Some(k), None) => (n, k),
+                    _ => return Err(err(line_num, "Expected 'param <name> <kind>'")),
+                };
+                let kind = match kind {
+                    "string" => PlaceholderKind::String,
+                    "package" => PlaceholderKind::Package,
+                    "decl" => PlaceholderKind::Decl,
+                    _ => return Err(err(line_num, "Unknown placeholder kind")),
+                };
+                template.placeholders.push(Placeholder {
+                    name: name.to_owned(),
+                    kind: kind,
+                    default: default,
+                });
+            }
+            "package" | "class" | "into" | "field" | "method" | "body" => {
+                template.lines.push((line_num, line.to_owned()));
+            }
+            _ => return Err(err(line_num, "Unknown template line")),
+        }
+    }
+    Ok(templates)
+}
+
+/// Load the templates from the config directory. Returns an empty list if there's no template
+/// file, or it contains an error.
+pub fn load_templates() -> Vec<Template> {
+    match config::read_config_file(TEMPLATE_FILE) {
+        Some(src) => {
+            match parse_templates(&src) {
+                Ok(t) => t,
+                Err(e) => {
+                    println!("Error in template file, line {}: {}", e.line, e.msg);
+                    Vec::new()
+                }
+            }
+        }
+        None => Vec::new(),
+    }
+}
+
+/// Parse `<modifiers> <type> <name>`, where the type may contain spaces.
+fn parse_member_decl(s: &str) -> Option<(Vec<Modifier>, String, String)> {
+    let mut modifiers = Vec::new();
+    let mut rest = s.trim();
+    loop {
+        let word_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        match Modifier::parse(&rest[..word_end]) {
+            Some(m) => {
+                modifiers.push(m);
+                rest = rest[word_end..].trim();
+            }
+            None => break,
+        }
+    }
+    let split_ix = match rest.rfind(char::is_whitespace) {
+        Some(ix) => ix,
+        None => return None,
+    };
+    Some((
+        modifiers,
+        rest[..split_ix].trim().to_owned(),
+        rest[split_ix..].trim().to_owned(),
+    ))
+}
+
+impl Template {
+    /// Replace all the placeholders in a line with the given values, which should be in the same
+    /// order as the placeholders.
+    fn substitute(&self, line: &str, values: &[String]) -> String {
+        let mut line = line.to_owned();
+        for (p, v) in self.placeholders.iter().zip(values) {
+            line = line.replace(&format!("${{{}}}", p.name), v);
+        }
+        line
+    }
+
+    /// Expand this template with the given placeholder values, which should be in the same order
+    /// as the placeholders.
+    pub fn expand(&self, values: &[String]) -> Result<Vec<TemplateItem>, TemplateErr> {
+        let mut items = Vec::new();
+        let mut curr_pkg: Option<String> = None;
+        // The decl members are added to
+        let mut curr_decl: Option<String> = None;
+        for &(line_num, ref line) in &self.lines {
+            let line = self.substitute(line, values);
+            let ix = line.find(char::is_whitespace).unwrap_or(line.len());
+            let (keyword, rest) = (&line[..ix], line[ix..].trim());
+            match keyword {
+                "package" => curr_pkg = Some(rest.to_owned()),
+                "class" => {
+                    let pkg = match curr_pkg {
+                        Some(ref p) => p.clone(),
+                        None => return Err(err(line_num, "Class without a package")),
+                    };
+                    curr_decl = Some(format!("{}.{}", pkg, rest));
+                    items.push(TemplateItem::Decl(
+                        pkg,
+                        Declaration::Class(Class::new_with_name(rest)),
+                    ));
+                }
+                "into" => curr_decl = Some(rest.to_owned()),
+                "field" | "method" => {
+                    let decl = match curr_decl {
+                        Some(ref d) => d.clone(),
+                        None => return Err(err(line_num, "Member without a class")),
+                    };
+                    let member = if keyword == "field" {
+                        let (modifiers, field_type, name) = match parse_member_decl(rest) {
+                            Some(d) => d,
+                            None => return Err(err(line_num, "Expected a type and name")),
+                        };
+                        ClassMember::Field(Field {
+                            modifiers: modifiers,
+                            field_type: field_type,
+                            name: name,
+                            doc: None,
+                            annotations: Vec::new(),
+                        })
+                    } else {
+                        let (open, close) = match (rest.find('('), rest.rfind(')')) {
+                            (Some(o), Some(c)) if o < c => (o, c),
+                            _ => return Err(err(line_num, "Expected a parameter list")),
+                        };
+                        let (modifiers, return_type, name) =
+                            match parse_member_decl(&rest[..open]) {
+                                Some(d) => d,
+                                None => return Err(err(line_num, "Expected a type and name")),
+                            };
+                        let mut params = Vec::new();
+                        for p in split_top_level(&rest[open + 1..close], true) {
+                            match p.rfind(char::is_whitespace) {
+                                Some(ix) => params.push(Param {
+                                    param_type: p[..ix].trim().to_owned(),
+                                    name: p[ix..].trim().to_owned(),
+                                    annotations: Vec::new(),
+                                }),
+                                None => return Err(err(line_num, "Expected a type and name")),
+                            }
+                        }
+                        ClassMember::Method(Method {
+                            modifiers: modifiers,
+                            return_type: return_type,
+                            name: name,
+                            params: params,
+                            body: Vec::new(),
+                            doc: None,
+                            annotations: Vec::new(),
+                        })
+                    };
+                    items.push(TemplateItem::Member(decl, member));
+                }
+                "body" => {
+                    match items.last_mut() {
+                        Some(&mut TemplateItem::Member(_, ClassMember::Method(ref mut m))) => {
+                            m.body.push(rest.to_owned())
+                        }
+                        _ => return Err(err(line_num, "Body line without a method")),
+                    }
+                }
+                _ => unreachable!(),
+            }
+        }
+        Ok(items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEMPLATES: &'static str = "
+# Comment
+[singleton ts]
+param Package package = @sel
+param Name string
+package ${Package}
+class ${Name}
+field private static final ${Name} INSTANCE
+method public static ${Name} getInstance()
+body return INSTANCE;
+
+[logger]
+param Class decl
+into ${Class}
+field private static final Logger LOG
+";
+
+    #[test]
+    fn parse() {
+        let templates = parse_templates(TEMPLATES).unwrap();
+        assert_eq!(templates.len(), 2);
+        assert_eq!(templates[0].keys, Some("ts".to_owned()));
+        assert_eq!(templates[0].placeholders[0].default, Some("@sel".to_owned()));
+        assert_eq!(templates[0].placeholders[1].kind, PlaceholderKind::String);
+        assert_eq!(templates[1].keys, None);
+
+        assert_eq!(
+            parse_templates("field int x"),
+            Err(err(1, "Line outside of a template"))
+        );
+        assert_eq!(
+            parse_templates("[t]\nparam x foo"),
+            Err(err(2, "Unknown placeholder kind"))
+        );
+    }
+
+    #[test]
+    fn expand() {
+        let templates = parse_templates(TEMPLATES).unwrap();
+        let items = templates[0]
+            .expand(&["com.tom".to_owned(), "Foo".to_owned()])
+            .unwrap();
+        assert_eq!(items.len(), 3);
+        assert_eq!(
+            items[0],
+            TemplateItem::Decl(
+                "com.tom".to_owned(),
+                Declaration::Class(Class::new_with_name("Foo")),
+            )
+        );
+        match items[2] {
+            TemplateItem::Member(ref decl, ClassMember::Method(ref m)) => {
+                assert_eq!(decl, "com.tom.Foo");
+                assert_eq!(m.return_type, "Foo");
+                assert_eq!(m.modifiers, vec![Modifier::Public, Modifier::Static]);
+                assert_eq!(m.body, vec!["return INSTANCE;"]);
+            }
+            _ => panic!("Expected method"),
+        }
+    }
+}
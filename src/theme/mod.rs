@@ -0,0 +1,419 @@
+//! Themes, which give the colours, font & row sizes used to render the editor.
+//!
+//! There are built-in `dark` and `light` themes. The `theme` config file picks one as a base,
+//! then overrides any of its settings, one per line. Colours are given as `#rrggbb`,
+//! `#rrggbbaa`, or 3 or 4 numbers from 0 to 1. The file is reloaded when it changes.
+//!
+//! ```text
+//! base light
+//! font /usr/share/fonts/TTF/DejaVuSansMono.ttf
+//! font-size 14
+//! row-height 18
+//! # Make the selection stand out more
+//! selection #40a040
+//! completion-match 1.0 0.5 0.0
+//! ```
+
+use config;
+use std::fs;
+use std::time::{Duration, Instant, SystemTime};
+
+/// The name of the theme file in the config directory
+pub const THEME_FILE: &'static str = "theme";
+
+/// The names of the built-in themes
+pub const THEME_NAMES: &'static [&'static str] = &["dark", "light"];
+
+/// The font used when the theme doesn't give one
+pub const DEFAULT_FONT: &'static str = "assets/FreeMonoBold.ttf";
+
+/// An RGBA colour, with each component from 0 to 1
+pub type Colour = [f32; 4];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    /// The path of the font file
+    pub font: String,
+    pub font_size: f32,
+    /// The height of a row in the panes
+    pub row_height: f32,
+    /// The height of the command buffer & prompt bars, and of each completion
+    pub bar_height: f32,
+
+    pub list_bg: Colour,
+    pub list_text: Colour,
+    /// The expand / collapse marker before packages in the package list
+    pub list_expander: Colour,
+    /// The selected row in the package list, and the selected member in the detail pane
+    pub selection: Colour,
+    /// The background of the detail, diagnostics & preview panes
+    pub detail_bg: Colour,
+    /// Text in the detail, diagnostics & preview panes
+    pub detail_text: Colour,
+    pub doc: Colour,
+    pub doc_tag: Colour,
+    pub annotation: Colour,
+    /// The location of each problem in the diagnostics pane
+    pub diagnostic: Colour,
+    /// The outline of the focused pane
+    pub focus_border: Colour,
+    /// The command buffer & prompt bars
+    pub bar_bg: Colour,
+    /// Text typed into the command buffer or prompt
+    pub bar_text: Colour,
+    pub prompt_label: Colour,
+    pub active_prompt_label: Colour,
+    pub active_completion: Colour,
+    pub completion_text: Colour,
+    /// The chars of a completion which matched the input
+    pub completion_match: Colour,
+    pub completion_detail: Colour,
+    /// The popup showing what can follow the current command input
+    pub popup_bg: Colour,
+    pub popup_key: Colour,
+    pub popup_text: Colour,
+}
+
+impl Theme {
+    pub fn dark() -> Theme {
+        Theme {
+            font: DEFAULT_FONT.to_owned(),
+            font_size: 12.0,
+            row_height: 16.0,
+            bar_height: 24.0,
+            list_bg: [0.1, 0.1, 0.1, 1.0],
+            list_text: [1.0, 1.0, 1.0, 1.0],
+            list_expander: [0.6, 0.6, 0.6, 1.0],
+            selection: [0.2, 0.5, 0.2, 1.0],
+            detail_bg: [0.15, 0.15, 0.15, 1.0],
+            detail_text: [1.0, 1.0, 1.0, 1.0],
+            doc: [0.5, 0.7, 0.5, 1.0],
+            doc_tag: [0.4, 0.6, 0.8, 1.0],
+            annotation: [0.8, 0.7, 0.4, 1.0],
+            diagnostic: [0.9, 0.5, 0.4, 1.0],
+            focus_border: [0.4, 0.4, 0.7, 1.0],
+            bar_bg: [0.1, 0.1, 0.4, 1.0],
+            bar_text: [1.0, 1.0, 1.0, 1.0],
+            prompt_label: [0.4, 0.4, 0.9, 0.4],
+            active_prompt_label: [0.7, 0.7, 1.0, 1.0],
+            active_completion: [0.4, 0.4, 0.7, 1.0],
+            completion_text: [1.0, 1.0, 1.0, 1.0],
+            completion_match: [1.0, 0.8, 0.3, 1.0],
+            completion_detail: [0.6, 0.6, 0.8, 1.0],
+            popup_bg: [0.15, 0.15, 0.3, 1.0],
+            popup_key: [0.9, 0.9, 0.4, 1.0],
+            popup_text: [1.0, 1.0, 1.0, 1.0],
+        }
+    }
+
+    pub fn light() -> Theme {
+        Theme {
+            list_bg: [0.92, 0.92, 0.92, 1.0],
+            list_text: [0.1, 0.1, 0.1, 1.0],
+            list_expander: [0.45, 0.45, 0.45, 1.0],
+            selection: [0.65, 0.85, 0.65, 1.0],
+            detail_bg: [0.98, 0.98, 0.98, 1.0],
+            detail_text: [0.1, 0.1, 0.1, 1.0],
+            doc: [0.2, 0.45, 0.2, 1.0],
+            doc_tag: [0.15, 0.35, 0.65, 1.0],
+            annotation: [0.6, 0.45, 0.1, 1.0],
+            diagnostic: [0.7, 0.2, 0.1, 1.0],
+            focus_border: [0.45, 0.5, 0.85, 1.0],
+            bar_bg: [0.8, 0.82, 0.95, 1.0],
+            bar_text: [0.1, 0.1, 0.1, 1.0],
+            prompt_label: [0.35, 0.35, 0.6, 1.0],
+            active_prompt_label: [0.1, 0.1, 0.4, 1.0],
+            active_completion: [0.65, 0.7, 0.95, 1.0],
+            completion_text: [0.1, 0.1, 0.1, 1.0],
+            completion_match: [0.8, 0.4, 0.0, 1.0],
+            completion_detail: [0.35, 0.35, 0.55, 1.0],
+            popup_bg: [0.88, 0.88, 0.97, 1.0],
+            popup_key: [0.55, 0.35, 0.0, 1.0],
+            popup_text: [0.1, 0.1, 0.1, 1.0],
+            ..Theme::dark()
+        }
+    }
+
+    /// Get the built-in theme with the given name
+    pub fn builtin(name: &str) -> Option<Theme> {
+        match name {
+            "dark" => Some(Theme::dark()),
+            "light" => Some(Theme::light()),
+            _ => None,
+        }
+    }
+
+    /// Change a setting, as named in the theme file
+    fn set(&mut self, key: &str, val: &str) -> Result<(), String> {
+        if key == "font" {
+            self.font = val.to_owned();
+            return Ok(());
+        }
+        if let Some(size) = self.size_mut(key) {
+            return match val.parse::<f32>() {
+                Ok(v) if v > 0.0 => {
+                    *size = v;
+                    Ok(())
+                }
+                _ => Err(format!("Expected a positive number, found '{}'", val)),
+            };
+        }
+        if let Some(colour) = self.colour_mut(key) {
+            *colour = parse_colour(val)?;
+            return Ok(());
+        }
+        Err(format!("Unknown setting '{}'", key))
+    }
+
+    /// Get the colour with the given role, as named in the theme file - e.g. `list-bg`
+    fn colour_mut(&mut self, role: &str) -> Option<&mut Colour> {
+        let colour = match role {
+            "list-bg" => &mut self.list_bg,
+            "list-text" => &mut self.list_text,
+            "list-expander" => &mut self.list_expander,
+            "selection" => &mut self.selection,
+            "detail-bg" => &mut self.detail_bg,
+            "detail-text" => &mut self.detail_text,
+            "doc" => &mut self.doc,
+            "doc-tag" => &mut self.doc_tag,
+            "annotation" => &mut self.annotation,
+            "diagnostic" => &mut self.diagnostic,
+            "focus-border" => &mut self.focus_border,
+            "bar-bg" => &mut self.bar_bg,
+            "bar-text" => &mut self.bar_text,
+            "prompt-label" => &mut self.prompt_label,
+            "active-prompt-label" => &mut self.active_prompt_label,
+            "active-completion" => &mut self.active_completion,
+            "completion-text" => &mut self.completion_text,
+            "completion-match" => &mut self.completion_match,
+            "completion-detail" => &mut self.completion_detail,
+            "popup-bg" => &mut self.popup_bg,
+            "popup-key" => &mut self.popup_key,
+            "popup-text" => &mut self.popup_text,
+            _ => return None,
+        };
+        Some(colour)
+    }
+
+    /// Get the size with the given name, as named in the theme file - e.g. `row-height`
+    fn size_mut(&mut self, name: &str) -> Option<&mut f32> {
+        let size = match name {
+            "font-size" => &mut self.font_size,
+            "row-height" => &mut self.row_height,
+            "bar-height" => &mut self.bar_height,
+            _ => return None,
+        };
+        Some(size)
+    }
+}
+
+/// An error in the theme file, with the line number (starting at 1) it occurred on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThemeErr {
+    pub line: usize,
+    pub msg: String,
+}
+
+/// Parse a colour, either as hex (`#rrggbb` or `#rrggbbaa`) or as 3 or 4 numbers from 0 to 1.
+fn parse_colour(s: &str) -> Result<Colour, String> {
+    use std::ascii::AsciiExt;
+    let mut colour = [1.0; 4];
+    if s.starts_with('#') {
+        let hex = &s[1..];
+        if (hex.len() != 6 && hex.len() != 8) || !hex.is_ascii() {
+            return Err(format!("Expected #rrggbb or #rrggbbaa, found '{}'", s));
+        }
+        for (ii, c) in colour.iter_mut().enumerate().take(hex.len() / 2) {
+            match u8::from_str_radix(&hex[ii * 2..ii * 2 + 2], 16) {
+                Ok(v) => *c = v as f32 / 255.0,
+                Err(_) => return Err(format!("Invalid hex colour '{}'", s)),
+            }
+        }
+        return Ok(colour);
+    }
+    let parts: Vec<&str> = s.split_whitespace().collect();
+    if parts.len() != 3 && parts.len() != 4 {
+        return Err(format!("Expected a colour, found '{}'", s));
+    }
+    for (c, p) in colour.iter_mut().zip(parts) {
+        match p.parse::<f32>() {
+            Ok(v) if v >= 0.0 && v <= 1.0 => *c = v,
+            _ => return Err(format!("Expected a number from 0 to 1, found '{}'", p)),
+        }
+    }
+    Ok(colour)
+}
+
+/// Load a theme from the source of a theme file. The theme starts as the built-in theme named
+/// `base`, or the one named in the file if `base` is None, or the dark theme if neither is given.
+/// Lines with errors are skipped, and an error is returned for each.
+pub fn load_theme(src: &str, base: Option<&str>) -> (Theme, Vec<ThemeErr>) {
+    let mut errors = Vec::new();
+    let mut settings = Vec::new();
+    let mut file_base = None;
+    for (ii, line) in src.lines().enumerate() {
+        let line = line.trim();
+        if line.len() == 0 || line.starts_with('#') {
+            continue;
+        }
+        let (key, val) = match line.find(char::is_whitespace) {
+            Some(ix) => (&line[..ix], line[ix..].trim()),
+            None => {
+                errors.push(ThemeErr {
+                    line: ii + 1,
+                    msg: format!("Expected a value for '{}'", line),
+                });
+                continue;
+            }
+        };
+        if key == "base" {
+            file_base = Some((ii + 1, val));
+        } else if key == "list-width" {
+            // Older theme files set the width of the package list, which the layout now sets
+            println!(
+                "Warning in theme file, line {}: 'list-width' is ignored, since pane sizes are set \
+                 by the layout",
+                ii + 1
+            );
+        } else {
+            settings.push((ii + 1, key, val));
+        }
+    }
+
+    let mut theme = Theme::dark();
+    match (base, file_base) {
+        (Some(name), _) => theme = Theme::builtin(name).unwrap_or(theme),
+        (None, Some((line, name))) => {
+            match Theme::builtin(name) {
+                Some(t) => theme = t,
+                None => {
+                    errors.push(ThemeErr {
+                        line: line,
+                        msg: format!("Unknown theme '{}'", name),
+                    })
+                }
+            }
+        }
+        (None, None) => (),
+    }
+
+    for (line, key, val) in settings {
+        if let Err(msg) = theme.set(key, val) {
+            errors.push(ThemeErr {
+                line: line,
+                msg: msg,
+            });
+        }
+    }
+    errors.sort_by_key(|e| e.line);
+    (theme, errors)
+}
+
+/// The current theme, loaded from the theme file & reloaded when it changes.
+pub struct ThemeLoader {
+    theme: Theme,
+    /// The built-in theme chosen by the user, which overrides the base in the theme file
+    base: Option<String>,
+    /// The modified time of the theme file when it was last loaded
+    mtime: Option<SystemTime>,
+    /// When we last checked whether the theme file had changed
+    last_check: Instant,
+}
+
+impl ThemeLoader {
+    pub fn new() -> ThemeLoader {
+        let mut loader = ThemeLoader {
+            theme: Theme::dark(),
+            base: None,
+            mtime: None,
+            last_check: Instant::now(),
+        };
+        loader.reload();
+        loader
+    }
+
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    /// Switch to the built-in theme with the given name, keeping the settings in the theme file.
+    /// Returns false if there's no such theme.
+    pub fn set_base(&mut self, name: &str) -> bool {
+        if Theme::builtin(name).is_none() {
+            return false;
+        }
+        self.base = Some(name.to_owned());
+        self.reload();
+        true
+    }
+
+    /// Load the theme file again
+    pub fn reload(&mut self) {
+        self.mtime = fs::metadata(config::config_path(THEME_FILE))
+            .and_then(|m| m.modified())
+            .ok();
+        let src = config::read_config_file(THEME_FILE).unwrap_or_default();
+        let (theme, errors) = load_theme(&src, self.base.as_ref().map(|b| &b[..]));
+        for e in errors {
+            println!("Error in theme file, line {}: {}", e.line, e.msg);
+        }
+        self.theme = theme;
+    }
+
+    /// Reload the theme if the theme file has changed. This only checks the file at most once a
+    /// second, so can be called every frame.
+    pub fn poll(&mut self) {
+        if self.last_check.elapsed() < Duration::from_secs(1) {
+            return;
+        }
+        self.last_check = Instant::now();
+        let mtime = fs::metadata(config::config_path(THEME_FILE))
+            .and_then(|m| m.modified())
+            .ok();
+        if mtime != self.mtime {
+            self.reload();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_colour() {
+        assert_eq!(parse_colour("#ff0000"), Ok([1.0, 0.0, 0.0, 1.0]));
+        assert_eq!(parse_colour("#00ff0000"), Ok([0.0, 1.0, 0.0, 0.0]));
+        assert_eq!(parse_colour("0.5 0.25 1"), Ok([0.5, 0.25, 1.0, 1.0]));
+        assert!(parse_colour("#ff00").is_err());
+        assert!(parse_colour("#gg0000").is_err());
+        assert!(parse_colour("0.5 2 1").is_err());
+        assert!(parse_colour("red").is_err());
+    }
+
+    #[test]
+    fn test_load_theme() {
+        let src = "# Comment\nbase light\nfont-size 14\nselection #000000\nfoo 1\n\
+                   row-height -1\nbar-bg\nfont fonts/Mono.ttf\nlist-width 200";
+        let (theme, errors) = load_theme(src, None);
+        assert_eq!(
+            errors.iter().map(|e| e.line).collect::<Vec<_>>(),
+            vec![5, 6, 7]
+        );
+        assert_eq!(theme.font, "fonts/Mono.ttf");
+        assert_eq!(theme.font_size, 14.0);
+        assert_eq!(theme.row_height, Theme::light().row_height);
+        assert_eq!(theme.selection, [0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(theme.bar_bg, Theme::light().bar_bg);
+
+        // The chosen base overrides the file's
+        let (theme, errors) = load_theme(src, Some("dark"));
+        assert_eq!(errors.len(), 3);
+        assert_eq!(theme.bar_bg, Theme::dark().bar_bg);
+        assert_eq!(theme.selection, [0.0, 0.0, 0.0, 1.0]);
+
+        let (theme, errors) = load_theme("base solarized", None);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(theme, Theme::dark());
+    }
+}
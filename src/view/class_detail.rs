@@ -0,0 +1,264 @@
+//! Rendering code for the detail pane, which shows the currently selected decl or package.
+
+use cgmath;
+use std;
+use std::cell::{Cell, RefCell};
+use state;
+use common::Rect;
+use input::{InputChunk, MouseEvent};
+use java_model::*;
+use qgfx::{RendererController, FontHandle};
+use theme::{Theme, Colour};
+use winit::VirtualKeyCode as VKC;
+use super::{View, scroll_wheel, char_width, elide_right, max_chars};
+
+pub struct ClassDetailView {
+    pub state: std::sync::Arc<state::State>,
+
+    pub font: FontHandle,
+
+    /// The area the pane was last rendered in. Lines outside it aren't drawn.
+    bounds: Cell<Rect>,
+    /// The number of rows scrolled past
+    scroll: Cell<usize>,
+    /// The selection when the pane was last rendered, so that it's only scrolled to the selected
+    /// member when the selection changes
+    last_sel: RefCell<Option<state::Selection>>,
+    /// The member signature rows which were last rendered, and the selections they stand for
+    rows: RefCell<Vec<(Rect, state::Selection)>>,
+    /// The width of a char in the font, measured when the pane is rendered
+    char_width: Cell<f32>,
+}
+
+impl ClassDetailView {
+    pub fn new(state: std::sync::Arc<state::State>, font: FontHandle) -> ClassDetailView {
+        ClassDetailView {
+            state: state,
+            font: font,
+            bounds: Cell::new(Rect::new(0.0, 0.0, 0.0, 0.0)),
+            scroll: Cell::new(0),
+            last_sel: RefCell::new(None),
+            rows: RefCell::new(Vec::new()),
+            char_width: Cell::new(1.0),
+        }
+    }
+
+    /// Returns true if a row at the given y position is inside the pane
+    fn row_visible(&self, theme: &Theme, y: f32) -> bool {
+        let bounds = self.bounds.get();
+        y >= bounds.top() && y + theme.row_height <= bounds.bottom()
+    }
+
+    /// Helper to render a line of text, then move the pos down a line. The end of the text is
+    /// elided if it doesn't fit in the pane.
+    fn render_line(
+        &self,
+        g: &mut RendererController,
+        theme: &Theme,
+        text: &str,
+        pos: &mut cgmath::Vector2<f32>,
+        indent: f32,
+        col: &Colour,
+    ) {
+        if self.row_visible(theme, pos.y) {
+            let x = pos.x + indent + 4.0;
+            let max_chars = max_chars(self.bounds.get(), x, self.char_width.get());
+            g.text(
+                &elide_right(text, max_chars),
+                &[x, pos.y + theme.row_height / 2.0 + 4.0],
+                self.font,
+                col,
+            );
+        }
+        pos.y += theme.row_height;
+    }
+
+    /// Helper to render a doc comment, with each tag on its own line.
+    fn render_doc(
+        &self,
+        g: &mut RendererController,
+        theme: &Theme,
+        doc: &DocComment,
+        pos: &mut cgmath::Vector2<f32>,
+        indent: f32,
+    ) {
+        for l in doc.description.lines() {
+            self.render_line(g, theme, l, pos, indent, &theme.doc);
+        }
+        for t in &doc.tags {
+            let (tag, text) = match *t {
+                DocTag::Param(ref p, ref s) => (format!("@param {}", p), s),
+                DocTag::Return(ref s) => ("@return".to_owned(), s),
+                DocTag::Throws(ref e, ref s) => (format!("@throws {}", e), s),
+                DocTag::Other(ref n, ref s) => (format!("@{}", n), s),
+            };
+            self.render_line(g, theme, &tag, pos, indent, &theme.doc_tag);
+            for l in text.lines() {
+                self.render_line(g, theme, l, pos, indent + theme.row_height, &theme.doc);
+            }
+        }
+    }
+
+}
+
+impl View for ClassDetailView {
+    /// Renders the current selection.
+    fn render(&self, g: &mut RendererController, bounds: Rect) {
+        let theme = self.state.theme.lock().unwrap().theme().clone();
+        let top = bounds.top() - self.scroll.get() as f32 * theme.row_height;
+        let mut pos = cgmath::Vector2 {
+            x: bounds.left(),
+            y: top,
+        };
+        let sel = self.state.project.curr_sel.lock().unwrap().clone();
+        let sel_changed = *self.last_sel.borrow() != sel;
+        *self.last_sel.borrow_mut() = sel.clone();
+        let package_list = &*self.state.project.package_list.lock().unwrap();
+        let mut rows = self.rows.borrow_mut();
+        rows.clear();
+        self.bounds.set(bounds);
+        self.char_width.set(char_width(g, self.font));
+        // The position of the selected member's row, from the top of the content
+        let mut sel_y = None;
+
+        // Render background
+        g.rect(
+            &[bounds.left(), bounds.top(), bounds.size.x, bounds.size.y],
+            &theme.detail_bg,
+        );
+
+        match sel {
+            Some(state::Selection::Package(ref name)) => {
+                let pkg = package_list.iter().filter_map(|p| match p.find_pkg(name) {
+                    (Some(pkg), None) => Some(pkg),
+                    _ => None,
+                }).next();
+                if let Some(pkg) = pkg {
+                    self.render_line(
+                        g,
+                        &theme,
+                        &format!("package {}", name),
+                        &mut pos,
+                        0.0,
+                        &theme.detail_text,
+                    );
+                    if let Some(ref doc) = pkg.doc {
+                        self.render_doc(g, &theme, doc, &mut pos, 0.0);
+                    }
+                }
+            }
+            Some(ref sel) => {
+                let name = sel.decl().unwrap();
+                let decl = package_list.iter().filter_map(|p| p.find_decl(name)).next();
+                if let Some(&Declaration::Class(ref c)) = decl {
+                    if let Some(ref doc) = c.doc {
+                        self.render_doc(g, &theme, doc, &mut pos, 0.0);
+                    }
+                    for a in &c.annotations {
+                        let source = a.to_source();
+                        self.render_line(g, &theme, &source, &mut pos, 0.0, &theme.annotation);
+                    }
+                    let mut header = format!("class {}", c.name);
+                    if c.extends.len() > 0 {
+                        header = header + " extends " + &c.extends;
+                    }
+                    self.render_line(g, &theme, &header, &mut pos, 0.0, &theme.detail_text);
+                    for m in &c.members {
+                        pos.y += theme.row_height / 2.0;
+                        if let Some(ref doc) = *m.doc() {
+                            self.render_doc(g, &theme, doc, &mut pos, theme.row_height);
+                        }
+                        for a in m.annotations() {
+                            self.render_line(
+                                g,
+                                &theme,
+                                &a.to_source(),
+                                &mut pos,
+                                theme.row_height,
+                                &theme.annotation,
+                            );
+                        }
+                        let visible = self.row_visible(&theme, pos.y);
+                        // Highlight the selected member
+                        if sel.member() == Some(m.name()) {
+                            sel_y = Some(pos.y - top);
+                            if visible {
+                                g.rect(
+                                    &[pos.x, pos.y, bounds.size.x, theme.row_height],
+                                    &theme.selection,
+                                );
+                            }
+                        }
+                        if visible {
+                            rows.push((
+                                Rect::new(pos.x, pos.y, bounds.size.x, theme.row_height),
+                                state::member_sel(name, m),
+                            ));
+                        }
+                        self.render_line(
+                            g,
+                            &theme,
+                            &m.signature(),
+                            &mut pos,
+                            theme.row_height,
+                            &theme.detail_text,
+                        );
+                    }
+                }
+            }
+            None => (),
+        }
+
+        // Clamp the scroll to the content, and scroll to a newly selected member. This takes
+        // effect from the next frame.
+        let num_rows = ((bounds.size.y / theme.row_height) as usize).max(1);
+        let content_rows = ((pos.y - top) / theme.row_height).ceil() as usize;
+        let mut scroll = self.scroll.get().min(content_rows.saturating_sub(num_rows));
+        if let (Some(y), true) = (sel_y, sel_changed) {
+            let ix = (y / theme.row_height) as usize;
+            if ix < scroll {
+                scroll = ix;
+            } else if ix >= scroll + num_rows {
+                scroll = ix + 1 - num_rows;
+            }
+        }
+        self.scroll.set(scroll);
+    }
+
+    /// `j` / `k` move the selection through the members of the selected decl, and `h` goes back
+    /// to the decl.
+    fn key_input(&self, i: InputChunk) -> bool {
+        let project = &self.state.project;
+        match (i.0, i.1) {
+            (VKC::J, 0) | (VKC::Down, 0) => project.select_member(true),
+            (VKC::K, 0) | (VKC::Up, 0) => project.select_member(false),
+            (VKC::H, 0) | (VKC::Left, 0) => {
+                let mut curr_sel = project.curr_sel.lock().unwrap();
+                *curr_sel = curr_sel.as_ref().map(|s| s.tree_sel());
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Clicking a member's signature selects it, and the scroll wheel scrolls the pane.
+    fn mouse_input(&self, ev: MouseEvent) -> bool {
+        let pos = ev.pos();
+        if !self.bounds.get().contains(pos) {
+            return false;
+        }
+        if let MouseEvent::Scroll(_, lines) = ev {
+            scroll_wheel(&self.scroll, lines);
+            return true;
+        }
+        let sel = self.rows
+            .borrow()
+            .iter()
+            .find(|&&(ref r, _)| r.contains(pos))
+            .map(|&(_, ref sel)| sel.clone());
+        if let Some(sel) = sel {
+            *self.state.project.curr_sel.lock().unwrap() = Some(sel);
+        }
+        true
+    }
+}
@@ -1,9 +1,10 @@
 //! Rendering code for the command buffer.
 
-use cgmath;
 use std;
 use state;
+use common::Rect;
 use qgfx::{RendererController, FontHandle};
+use super::View;
 
 pub struct CommandBufferView {
     pub state: std::sync::Arc<state::State>,
@@ -18,23 +19,33 @@ impl CommandBufferView {
             font: font,
         }
     }
+}
 
-    /// # Params
-    /// * `display_size` - The size of the current display, so that the command buffer can be
-    /// rendered at the bottom of the screen.
-    pub fn render(&self, g: &mut RendererController, display_size: cgmath::Vector2<f32>) {
-        if self.state.command_buffer.lock().unwrap().is_empty() {
+impl View for CommandBufferView {
+    /// Renders the bar along the bottom of the screen, with the keys typed so far. The prompt
+    /// takes the bar over while it's showing.
+    fn render(&self, g: &mut RendererController, bounds: Rect) {
+        if self.state.curr_prompt.lock().unwrap().is_some() {
             return;
         }
+        let theme = self.state.theme.lock().unwrap().theme().clone();
         g.rect(
-            &[0.0, display_size.y - 24.0, display_size.x, 24.0],
-            &[0.1, 0.1, 0.4, 1.0],
+            &[bounds.left(), bounds.top(), bounds.size.x, bounds.size.y],
+            &theme.bar_bg,
         );
+        let text = if !self.state.command_buffer.lock().unwrap().is_empty() {
+            self.state.command_buffer.lock().unwrap().get_input_as_str()
+        } else if *self.state.nav_mode.lock().unwrap() {
+            let focus = self.state.layout.lock().unwrap().focus;
+            format!("-- NAVIGATE {} --", focus.name())
+        } else {
+            return;
+        };
         g.text(
-            &self.state.command_buffer.lock().unwrap().get_input_as_str(),
-            &[8.0, display_size.y - 8.0],
+            &text,
+            &[bounds.left() + 8.0, bounds.bottom() - 8.0],
             self.font,
-            &[1.0, 1.0, 1.0, 1.0],
+            &theme.bar_text,
         );
     }
 }
@@ -0,0 +1,46 @@
+//! Rendering code for the popup which shows what can follow the current command input.
+
+use std;
+use state;
+use common::Rect;
+use qgfx::{RendererController, FontHandle};
+use super::View;
+
+/// The width of the key column
+const KEY_WIDTH: f32 = 48.0;
+
+pub struct ContinuationsView {
+    pub state: std::sync::Arc<state::State>,
+
+    pub font: FontHandle,
+}
+
+impl ContinuationsView {
+    pub fn new(state: std::sync::Arc<state::State>, font: FontHandle) -> ContinuationsView {
+        ContinuationsView {
+            state: state,
+            font: font,
+        }
+    }
+}
+
+impl View for ContinuationsView {
+    /// Renders the popup just above the given bar.
+    fn render(&self, g: &mut RendererController, bounds: Rect) {
+        let continuations = self.state.command_buffer.lock().unwrap().get_continuations();
+        if continuations.len() == 0 {
+            return;
+        }
+        let theme = self.state.theme.lock().unwrap().theme().clone();
+        let row_height = theme.row_height;
+        let height = row_height * continuations.len() as f32 + 8.0;
+        let top = bounds.top() - height;
+        g.rect(&[bounds.left(), top, bounds.size.x, height], &theme.popup_bg);
+        for (ii, &(ref key, ref name)) in continuations.iter().enumerate() {
+            let y = top + 4.0 + row_height * ii as f32 + row_height / 2.0 + 4.0;
+            let x = bounds.left() + 8.0;
+            g.text(key, &[x, y], self.font, &theme.popup_key);
+            g.text(name, &[x + KEY_WIDTH, y], self.font, &theme.popup_text);
+        }
+    }
+}
@@ -0,0 +1,96 @@
+//! Rendering code for the diagnostics pane, which lists the problems found by refactorings.
+
+use std;
+use std::cell::Cell;
+use state;
+use common::Rect;
+use input::{InputChunk, MouseEvent, SHIFT};
+use qgfx::{RendererController, FontHandle};
+use winit::VirtualKeyCode as VKC;
+use super::{View, scroll_key, scroll_wheel, char_width, elide_right, max_chars};
+
+pub struct DiagnosticsView {
+    pub state: std::sync::Arc<state::State>,
+
+    pub font: FontHandle,
+
+    /// The area the pane was last rendered in
+    bounds: Cell<Rect>,
+    /// The number of diagnostics scrolled past
+    scroll: Cell<usize>,
+}
+
+impl DiagnosticsView {
+    pub fn new(state: std::sync::Arc<state::State>, font: FontHandle) -> DiagnosticsView {
+        DiagnosticsView {
+            state: state,
+            font: font,
+            bounds: Cell::new(Rect::new(0.0, 0.0, 0.0, 0.0)),
+            scroll: Cell::new(0),
+        }
+    }
+}
+
+impl View for DiagnosticsView {
+    /// Renders a header with the number of diagnostics, followed by the location & message of
+    /// each.
+    fn render(&self, g: &mut RendererController, bounds: Rect) {
+        let theme = self.state.theme.lock().unwrap().theme().clone();
+        self.bounds.set(bounds);
+        g.rect(
+            &[bounds.left(), bounds.top(), bounds.size.x, bounds.size.y],
+            &theme.detail_bg,
+        );
+        let diagnostics = self.state.project.diagnostics.lock().unwrap();
+        let text_y = |row: usize| {
+            bounds.top() + row as f32 * theme.row_height + theme.row_height / 2.0 + 4.0
+        };
+        let x = bounds.left() + 4.0;
+        let max_chars = max_chars(bounds, x, char_width(g, self.font));
+        g.text(
+            &elide_right(&format!("Diagnostics ({})", diagnostics.len()), max_chars),
+            &[x, text_y(0)],
+            self.font,
+            &theme.detail_text,
+        );
+
+        let num_rows = ((bounds.size.y / theme.row_height) as usize).saturating_sub(1).max(1);
+        let scroll = self.scroll.get().min(diagnostics.len().saturating_sub(num_rows));
+        self.scroll.set(scroll);
+        for (ii, d) in diagnostics.iter().skip(scroll).take(num_rows).enumerate() {
+            let y = text_y(ii + 1);
+            // The location is elided first if the row doesn't fit, since the message matters more
+            let location = format!("{}: ", d.location);
+            let message = elide_right(&d.message, max_chars);
+            let location_chars = max_chars - message.chars().count();
+            let (w, _h) = g.text(
+                &elide_right(&location, location_chars),
+                &[x, y],
+                self.font,
+                &theme.diagnostic,
+            );
+            g.text(&message, &[x + w, y], self.font, &theme.detail_text);
+        }
+    }
+
+    /// Keys scroll the list, and `D` clears it once the problems have been fixed.
+    fn key_input(&self, i: InputChunk) -> bool {
+        if i == InputChunk(VKC::D, SHIFT) {
+            self.state.project.diagnostics.lock().unwrap().clear();
+            return true;
+        }
+        let theme = self.state.theme.lock().unwrap().theme().clone();
+        let page_rows = (self.bounds.get().size.y / theme.row_height) as usize;
+        scroll_key(&self.scroll, i, page_rows.saturating_sub(1))
+    }
+
+    fn mouse_input(&self, ev: MouseEvent) -> bool {
+        if !self.bounds.get().contains(ev.pos()) {
+            return false;
+        }
+        if let MouseEvent::Scroll(_, lines) = ev {
+            scroll_wheel(&self.scroll, lines);
+        }
+        true
+    }
+}
@@ -1,27 +1,188 @@
 //! A module which encompasses generating vertex data to render.
+//!
+//! The panes (package tree, detail, diagnostics & preview) are laid out by `layout::Layout`, and
+//! render into the area it gives them. The command buffer, prompt & continuations popup share the
+//! bar along the bottom of the screen.
 
 mod command_buffer;
 mod prompt;
+mod class_detail;
+mod continuations;
+mod diagnostics;
+mod preview;
 
 pub use self::command_buffer::*;
 pub use self::prompt::*;
+pub use self::class_detail::*;
+pub use self::continuations::*;
+pub use self::diagnostics::*;
+pub use self::preview::*;
 
-use java_model::*;
 use qgfx::{RendererController, FontHandle};
-use common::Rect;
-use cgmath;
 use std;
+use std::cell::{Cell, RefCell};
 use state;
+use command;
+use common::Rect;
+use input::{InputChunk, MouseEvent};
+use layout::Pane;
+use theme::Theme;
+use winit::VirtualKeyCode as VKC;
+
+/// Something which can be drawn in an area of the screen.
+pub trait View {
+    /// Render the view into the given area.
+    fn render(&self, g: &mut RendererController, bounds: Rect);
+
+    /// Handle a key while the view has focus in navigation mode. Returns false if the key wasn't
+    /// used, in which case it goes to the command buffer as usual.
+    fn key_input(&self, _i: InputChunk) -> bool {
+        false
+    }
+
+    /// Handle a mouse event. Returns false if the event wasn't over the view.
+    fn mouse_input(&self, _ev: MouseEvent) -> bool {
+        false
+    }
+}
+
+/// The views shown in the layout's panes
+pub struct Panes {
+    pub tree: PackageListView,
+    pub detail: ClassDetailView,
+    pub diagnostics: DiagnosticsView,
+    pub preview: PreviewView,
+}
 
-/// The height of an item in the package list
-const ITEM_HEIGHT: f32 = 16.0;
-/// The width of rht package list
-const ITEM_WIDTH: f32 = 200.0;
+impl Panes {
+    pub fn new(state: std::sync::Arc<state::State>, font: FontHandle) -> Panes {
+        Panes {
+            tree: PackageListView::new(state.clone(), font),
+            detail: ClassDetailView::new(state.clone(), font),
+            diagnostics: DiagnosticsView::new(state.clone(), font),
+            preview: PreviewView::new(state, font),
+        }
+    }
+
+    pub fn get(&self, pane: Pane) -> &View {
+        match pane {
+            Pane::Tree => &self.tree,
+            Pane::Detail => &self.detail,
+            Pane::Diagnostics => &self.diagnostics,
+            Pane::Preview => &self.preview,
+        }
+    }
+
+    pub fn set_font(&mut self, font: FontHandle) {
+        self.tree.font = font;
+        self.detail.font = font;
+        self.diagnostics.font = font;
+        self.preview.font = font;
+    }
+}
+
+/// Measure the width of a char in the given font, which is monospaced. Nothing visible is drawn.
+fn char_width(g: &mut RendererController, font: FontHandle) -> f32 {
+    const SAMPLE: &'static str = "MMMMMMMMMM";
+    let (w, _h) = g.text(SAMPLE, &[0.0, 0.0], font, &[0.0, 0.0, 0.0, 0.0]);
+    (w / SAMPLE.len() as f32).max(1.0)
+}
+
+/// Shown in place of the elided part of text which is too long
+const ELLIPSIS: &'static str = "...";
+
+/// Elide the start of some text so that it's at most `max_chars` chars long. Returns the new text,
+/// along with the byte positions of the given chars in it. Positions of elided chars are dropped.
+/// If there's no room for any of the text, just as much of the ellipsis as fits is returned.
+fn elide_left(text: &str, positions: &[usize], max_chars: usize) -> (String, Vec<usize>) {
+    let num_chars = text.chars().count();
+    if num_chars <= max_chars {
+        return (text.to_owned(), positions.to_vec());
+    }
+    if max_chars <= ELLIPSIS.len() {
+        return (ELLIPSIS[..max_chars].to_owned(), Vec::new());
+    }
+    let keep = max_chars - ELLIPSIS.len();
+    let start = text.char_indices()
+        .nth(num_chars - keep)
+        .map(|(ix, _)| ix)
+        .unwrap_or(text.len());
+    let elided = ELLIPSIS.to_owned() + &text[start..];
+    let positions = positions
+        .iter()
+        .filter(|&&p| p >= start)
+        .map(|p| p - start + ELLIPSIS.len())
+        .collect();
+    (elided, positions)
+}
+
+/// Elide the end of some text so that it's at most `max_chars` chars long. If there's no room for
+/// any of the text, just as much of the ellipsis as fits is returned.
+fn elide_right(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_owned();
+    }
+    if max_chars <= ELLIPSIS.len() {
+        return ELLIPSIS[..max_chars].to_owned();
+    }
+    text.chars().take(max_chars - ELLIPSIS.len()).collect::<String>() + ELLIPSIS
+}
+
+/// Get the number of chars which fit between x & the right of the bounds, leaving a margin.
+fn max_chars(bounds: Rect, x: f32, char_width: f32) -> usize {
+    ((bounds.right() - x - 4.0) / char_width).max(0.0) as usize
+}
+
+/// Outline the focused pane
+pub fn render_focus(g: &mut RendererController, r: Rect, theme: &Theme) {
+    const WIDTH: f32 = 2.0;
+    let col = &theme.focus_border;
+    g.rect(&[r.left(), r.top(), r.size.x, WIDTH], col);
+    g.rect(&[r.left(), r.bottom() - WIDTH, r.size.x, WIDTH], col);
+    g.rect(&[r.left(), r.top(), WIDTH, r.size.y], col);
+    g.rect(&[r.right() - WIDTH, r.top(), WIDTH, r.size.y], col);
+}
+
+/// Handle a key which scrolls a view by rows - `j` / `k` or the arrow keys by a row, and
+/// `PageDown` / `PageUp` by a page. Returns false if the key doesn't scroll. The scroll is clamped
+/// to the content when the view is next rendered.
+fn scroll_key(scroll: &Cell<usize>, i: InputChunk, page_rows: usize) -> bool {
+    let s = scroll.get();
+    scroll.set(match (i.0, i.1) {
+        (VKC::J, 0) | (VKC::Down, 0) => s + 1,
+        (VKC::K, 0) | (VKC::Up, 0) => s.saturating_sub(1),
+        (VKC::PageDown, 0) => s + page_rows.max(1),
+        (VKC::PageUp, 0) => s.saturating_sub(page_rows.max(1)),
+        _ => return false,
+    });
+    true
+}
+
+/// Scroll by the mouse wheel, which scrolls by the given number of lines. Positive is up.
+fn scroll_wheel(scroll: &Cell<usize>, lines: f32) {
+    let s = scroll.get() as f32 - (lines * SCROLL_ROWS).round();
+    scroll.set(s.max(0.0) as usize);
+}
+
+/// The width of the expand / collapse marker before packages
+const EXPANDER_WIDTH: f32 = 10.0;
+/// The number of rows to scroll a view by for each line scrolled by the mouse wheel
+const SCROLL_ROWS: f32 = 3.0;
 
 pub struct PackageListView {
     pub state: std::sync::Arc<state::State>,
 
     pub font: FontHandle,
+
+    /// The index of the first row shown. This follows the selection, so that it's always visible.
+    scroll: Cell<usize>,
+    /// The selection when the list was last rendered, so that the list is only scrolled to the
+    /// selection when it changes
+    last_sel: RefCell<Option<state::Selection>>,
+    /// The area the list was last rendered in
+    bounds: Cell<Rect>,
+    /// The rows which were last rendered, and where, for finding the row under the mouse
+    rows: RefCell<Vec<(Rect, state::TreeItem)>>,
 }
 
 impl PackageListView {
@@ -29,123 +190,147 @@ impl PackageListView {
         PackageListView {
             state: state,
             font: font,
+            scroll: Cell::new(0),
+            last_sel: RefCell::new(None),
+            bounds: Cell::new(Rect::new(0.0, 0.0, 0.0, 0.0)),
+            rows: RefCell::new(Vec::new()),
         }
     }
 
-    /// Helper to render a list of decls. Returns a rect which indicates the size used up by the
-    /// classes rendered.
-    /// # Params
-    /// * `prefix` - The prefix to this decl, i.e. all the parents. e.g - "com.tom."
-    /// * `sel` - The current selection. Will tell us whether or not to highlight.
-    fn render_decl_list(
-        &self,
-        g: &mut RendererController,
-        decl_list: &[Declaration],
-        offset: cgmath::Vector2<f32>,
-        text_indent: f32,
-        prefix: &mut String,
-        sel: &Option<state::Selection>,
-    ) -> Rect {
-        let mut pos = offset;
-        for d in decl_list {
-            let orig_prefix_len = prefix.len();
-            prefix.push_str(d.name().as_ref());
-            if sel.is_some() && sel.as_ref().unwrap().is_decl(prefix.as_str()) {
-                g.rect(
-                    &[pos.x, pos.y, ITEM_WIDTH, ITEM_HEIGHT],
-                    &[0.2, 0.5, 0.2, 1.0],
+}
+
+impl View for PackageListView {
+    /// Renders the package tree. Packages show their qualified names, and decls are indented
+    /// below their package.
+    fn render(&self, g: &mut RendererController, bounds: Rect) {
+        let items = self.state.project.tree_items();
+        let curr_sel = self.state.project.curr_sel.lock().unwrap().clone();
+        let theme = self.state.theme.lock().unwrap().theme().clone();
+        let (item_width, item_height) = (bounds.size.x, theme.row_height);
+
+        // Render background
+        g.rect(&[bounds.left(), bounds.top(), item_width, bounds.size.y], &theme.list_bg);
+
+        self.bounds.set(bounds);
+
+        // Scroll so that the selection is visible, if it's changed
+        let sel_changed = *self.last_sel.borrow() != curr_sel;
+        *self.last_sel.borrow_mut() = curr_sel.clone();
+        let num_rows = ((bounds.size.y / item_height) as usize).max(1);
+        let mut scroll = self.scroll.get();
+        let sel_ix = match curr_sel {
+            Some(ref sel) => {
+                let sel = sel.tree_sel();
+                items.iter().position(|i| i.sel == sel)
+            }
+            None => None,
+        };
+        if let (Some(ix), true) = (sel_ix, sel_changed) {
+            if ix < scroll {
+                scroll = ix;
+            } else if ix >= scroll + num_rows {
+                scroll = ix + 1 - num_rows;
+            }
+        }
+        scroll = scroll.min(items.len().saturating_sub(num_rows));
+        self.scroll.set(scroll);
+
+        // Render the visible items. Packages with children are marked with '+' when collapsed,
+        // and '-' when expanded. Labels which don't fit lose their start, so simple names stay
+        // visible.
+        let char_width = char_width(g, self.font);
+        let mut rows = self.rows.borrow_mut();
+        rows.clear();
+        for (ii, item) in items.iter().enumerate().skip(scroll).take(num_rows) {
+            let y = bounds.top() + (ii - scroll) as f32 * item_height;
+            rows.push((Rect::new(bounds.left(), y, item_width, item_height), item.clone()));
+            if Some(ii) == sel_ix {
+                g.rect(&[bounds.left(), y, item_width, item_height], &theme.selection);
+            }
+            let x = bounds.left() + item.depth as f32 * item_height + 4.0;
+            let text_y = y + item_height / 2.0 + 4.0;
+            if item.has_children {
+                g.text(
+                    if item.expanded { "-" } else { "+" },
+                    &[x, text_y],
+                    self.font,
+                    &theme.list_expander,
                 );
             }
+            let max_chars = max_chars(bounds, x + EXPANDER_WIDTH, char_width);
             g.text(
-                d.name().as_ref(),
-                &[pos.x + text_indent + 4.0, pos.y + ITEM_HEIGHT / 2.0 + 4.0],
+                &elide_left(&item.label, &[], max_chars).0,
+                &[x + EXPANDER_WIDTH, text_y],
                 self.font,
-                &[1.0, 1.0, 1.0, 1.0],
+                &theme.list_text,
             );
-            pos.y += ITEM_HEIGHT;
-            prefix.truncate(orig_prefix_len);
         }
-        return Rect::new(
-            offset.x,
-            offset.y,
-            ITEM_WIDTH,
-            ITEM_HEIGHT * decl_list.len() as f32,
-        );
+    }
 
+    /// Keys move the selection through the tree, as described in `command::navigate_key`.
+    fn key_input(&self, i: InputChunk) -> bool {
+        command::navigate_key(self.state.clone(), i)
     }
 
-    /// Function to render a package recursively. Returns a rect which contains the space used up.
-    ///
-    /// # Params
-    /// * `prefix` - The prefix to this package, i.e. all the parents. e.g - "com.tom."
-    /// * `sel` - The current selection. Will tell us whether or not to highlight.
-    fn render_pkg(
-        &self,
-        g: &mut RendererController,
-        pos: cgmath::Vector2<f32>,
-        text_indent: f32,
-        pkg: &Package,
-        prefix: &mut String,
-        sel: &Option<state::Selection>,
-    ) -> Rect {
-        let orig_prefix_len = prefix.len();
-        prefix.push_str(pkg.name.as_ref());
-        if sel.is_some() && sel.as_ref().unwrap().is_package(prefix.as_str()) {
-            g.rect(
-                &[pos.x, pos.y, ITEM_WIDTH, ITEM_HEIGHT],
-                &[0.2, 0.5, 0.2, 1.0],
-            );
+    /// Clicking a row selects it, double clicking a package expands or collapses it, and the
+    /// scroll wheel scrolls the list.
+    fn mouse_input(&self, ev: MouseEvent) -> bool {
+        let pos = ev.pos();
+        if !self.bounds.get().contains(pos) {
+            return false;
         }
-        // render this package - choose a highlighted colour if this prefix is the selected one
-        g.text(
-            prefix,
-            &[pos.x + text_indent + 4.0, pos.y + ITEM_HEIGHT / 2.0 + 4.0],
-            self.font,
-            &[1.0, 1.0, 1.0, 1.0],
-        );
-
-
-        // Add '.' to our pkg name so we can use it as a prefix
-        prefix.push_str(".");
-        let mut indented = cgmath::Vector2 {
-            x: pos.x,
-            y: pos.y + ITEM_HEIGHT,
+        if let MouseEvent::Scroll(_, lines) = ev {
+            scroll_wheel(&self.scroll, lines);
+            return true;
+        }
+        let item = match self.rows.borrow().iter().find(|&&(ref r, _)| r.contains(pos)) {
+            Some(&(_, ref item)) => item.clone(),
+            None => return true,
+        };
+        *self.state.project.curr_sel.lock().unwrap() = Some(item.sel.clone());
+        let double_click = match ev {
+            MouseEvent::DoubleClick(_) => true,
+            _ => false,
         };
-        for p in &pkg.package_list {
-            let rect = self.render_pkg(g, indented, text_indent + ITEM_HEIGHT, p, prefix, sel);
-            indented.y += rect.size.y;
+        if let (true, &state::Selection::Package(ref name)) = (double_click, &item.sel) {
+            if item.has_children {
+                self.state.project.set_expanded(name, !item.expanded);
+                if let Err(e) = self.state.project.save_collapsed() {
+                    println!("Failed to save collapsed packages: {}", e);
+                }
+            }
         }
-
-
-        let rect = self.render_decl_list(
-            g,
-            &pkg.decl_list[..],
-            indented,
-            text_indent + ITEM_HEIGHT,
-            prefix,
-            sel,
-        );
-
-        prefix.truncate(orig_prefix_len);
-        return Rect::new(pos.x, pos.y, ITEM_WIDTH, indented.y - pos.y + rect.size.y);
+        true
     }
+}
 
-    /// Renders a list of packages.
-    pub fn render(&self, g: &mut RendererController, screen_size: cgmath::Vector2<f32>) {
-        let mut pos = cgmath::Vector2 { x: 0.0, y: 0.0 };
-        let package_list = &*self.state.project.package_list.lock().unwrap();
-        let selected_pkg_name = &*self.state.project.curr_sel.lock().unwrap();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        // Render background
-        g.rect(
-            &[pos.x, pos.y, ITEM_WIDTH, screen_size.y],
-            &[0.1, 0.1, 0.1, 1.0],
+    #[test]
+    fn test_elide_left() {
+        assert_eq!(
+            elide_left("com.tom.Foo", &[0, 8], 20),
+            ("com.tom.Foo".to_owned(), vec![0, 8])
+        );
+        assert_eq!(
+            elide_left("com.tom.Foo", &[0, 8], 7),
+            ("....Foo".to_owned(), vec![4])
         );
+        assert_eq!(elide_left("com.tom.Foo", &[0, 8], 4), ("...o".to_owned(), vec![]));
+        assert_eq!(elide_left("com.tom.Foo", &[0, 8], 3), ("...".to_owned(), vec![]));
+        assert_eq!(elide_left("com.tom.Foo", &[0, 8], 2), ("..".to_owned(), vec![]));
+        assert_eq!(elide_left("com.tom.Foo", &[0, 8], 0), ("".to_owned(), vec![]));
+    }
 
-        // Render items
-        for p in package_list {
-            let rect = self.render_pkg(g, pos, 0.0, p, &mut "".to_owned(), selected_pkg_name);
-            pos.y += rect.size.y;
-        }
+    #[test]
+    fn test_elide_right() {
+        assert_eq!(elide_right("private int x;", 20), "private int x;");
+        assert_eq!(elide_right("private int x;", 10), "private...");
+        assert_eq!(elide_right("private int x;", 3), "...");
+        assert_eq!(elide_right("private int x;", 1), ".");
+        assert_eq!(elide_right("éééé", 3), "...");
+        assert_eq!(elide_right("éééé", 4), "éééé");
     }
 }
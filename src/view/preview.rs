@@ -0,0 +1,165 @@
+//! Rendering code for the preview pane, which shows the java source of the current selection.
+
+use std;
+use std::cell::Cell;
+use state::{self, Selection};
+use common::Rect;
+use input::{InputChunk, MouseEvent};
+use java_model::*;
+use qgfx::{RendererController, FontHandle};
+use super::{View, scroll_key, scroll_wheel, char_width, elide_right, max_chars};
+
+pub struct PreviewView {
+    pub state: std::sync::Arc<state::State>,
+
+    pub font: FontHandle,
+
+    /// The area the pane was last rendered in
+    bounds: Cell<Rect>,
+    /// The number of lines scrolled past
+    scroll: Cell<usize>,
+}
+
+impl PreviewView {
+    pub fn new(state: std::sync::Arc<state::State>, font: FontHandle) -> PreviewView {
+        PreviewView {
+            state: state,
+            font: font,
+            bounds: Cell::new(Rect::new(0.0, 0.0, 0.0, 0.0)),
+            scroll: Cell::new(0),
+        }
+    }
+}
+
+/// Generate the source lines to show for a selection. A selected member shows just that member.
+fn preview_lines(sel: &Selection, package_list: &[Package]) -> Vec<String> {
+    let name = match sel.decl() {
+        Some(name) => name,
+        None => {
+            if let Selection::Package(ref name) = *sel {
+                let pkg = package_list.iter().filter_map(|p| match p.find_pkg(name) {
+                    (Some(pkg), None) => Some(pkg),
+                    _ => None,
+                }).next();
+                if let Some(pkg) = pkg {
+                    return package_info_to_source(name, &pkg.doc)
+                        .lines()
+                        .map(|l| l.to_owned())
+                        .collect();
+                }
+            }
+            return Vec::new();
+        }
+    };
+    let c = match package_list.iter().filter_map(|p| p.find_decl(name)).next() {
+        Some(&Declaration::Class(ref c)) => c,
+        None => return Vec::new(),
+    };
+    match sel.member() {
+        Some(member) => c.find_member(member).map(|m| member_source_lines(m, "")).unwrap_or(
+            Vec::new(),
+        ),
+        None => class_source_lines(c),
+    }
+}
+
+impl View for PreviewView {
+    fn render(&self, g: &mut RendererController, bounds: Rect) {
+        let theme = self.state.theme.lock().unwrap().theme().clone();
+        self.bounds.set(bounds);
+        g.rect(
+            &[bounds.left(), bounds.top(), bounds.size.x, bounds.size.y],
+            &theme.detail_bg,
+        );
+        let sel = self.state.project.curr_sel.lock().unwrap().clone();
+        let lines = match sel {
+            Some(ref sel) => preview_lines(sel, &self.state.project.package_list.lock().unwrap()),
+            None => Vec::new(),
+        };
+
+        let num_rows = ((bounds.size.y / theme.row_height) as usize).max(1);
+        let scroll = self.scroll.get().min(lines.len().saturating_sub(num_rows));
+        self.scroll.set(scroll);
+        // Lines which don't fit are cut off at the edge of the pane
+        let x = bounds.left() + 4.0;
+        let max_chars = max_chars(bounds, x, char_width(g, self.font));
+        for (ii, l) in lines.iter().skip(scroll).take(num_rows).enumerate() {
+            let y = bounds.top() + ii as f32 * theme.row_height + theme.row_height / 2.0 + 4.0;
+            g.text(&elide_right(l, max_chars), &[x, y], self.font, &theme.detail_text);
+        }
+    }
+
+    /// Keys scroll the source
+    fn key_input(&self, i: InputChunk) -> bool {
+        let theme = self.state.theme.lock().unwrap().theme().clone();
+        let page_rows = (self.bounds.get().size.y / theme.row_height) as usize;
+        scroll_key(&self.scroll, i, page_rows)
+    }
+
+    fn mouse_input(&self, ev: MouseEvent) -> bool {
+        if !self.bounds.get().contains(ev.pos()) {
+            return false;
+        }
+        if let MouseEvent::Scroll(_, lines) = ev {
+            scroll_wheel(&self.scroll, lines);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preview_lines() {
+        let mut class = Class::new_with_name("Foo");
+        class.extends = "Bar".to_owned();
+        class.members.push(ClassMember::Field(Field {
+            modifiers: vec![Modifier::Private],
+            field_type: "int".to_owned(),
+            name: "count".to_owned(),
+            doc: None,
+            annotations: Vec::new(),
+        }));
+        class.members.push(ClassMember::Method(Method {
+            modifiers: Vec::new(),
+            return_type: "void".to_owned(),
+            name: "run".to_owned(),
+            params: Vec::new(),
+            body: vec!["count++;".to_owned()],
+            doc: None,
+            annotations: Vec::new(),
+        }));
+        let (mut pkg, _) = Package::new("com");
+        pkg.decl_list.push(Declaration::Class(class));
+        let mut pkgs = vec![pkg];
+
+        assert_eq!(
+            preview_lines(&Selection::Decl("com.Foo".to_owned()), &pkgs),
+            vec![
+                "class Foo extends Bar {",
+                "    private int count;",
+                "",
+                "    void run() {",
+                "        count++;",
+                "    }",
+                "}",
+            ]
+        );
+        assert_eq!(
+            preview_lines(&Selection::Method("com.Foo".to_owned(), "run".to_owned()), &pkgs),
+            vec!["void run() {", "    count++;", "}"]
+        );
+        assert_eq!(
+            preview_lines(&Selection::Package("com".to_owned()), &pkgs),
+            vec!["package com;"]
+        );
+        pkgs[0].doc = Some(DocComment::parse("Things"));
+        assert_eq!(
+            preview_lines(&Selection::Package("com".to_owned()), &pkgs),
+            vec!["/**", " * Things", " */", "package com;"]
+        );
+        assert_eq!(preview_lines(&Selection::Decl("com.Baz".to_owned()), &pkgs).len(), 0);
+    }
+}
@@ -2,13 +2,24 @@
 
 use cgmath;
 use std;
+use std::cell::RefCell;
 use state;
+use common::Rect;
+use input::MouseEvent;
+use prompt::Completion;
 use qgfx::{RendererController, FontHandle};
+use theme::Theme;
+use super::{View, char_width, elide_left};
 
 pub struct PromptInputView {
     pub state: std::sync::Arc<state::State>,
 
     pub font: FontHandle,
+
+    /// The areas of the prompt bar & completion rows which were last rendered, for finding what's
+    /// under the mouse
+    rows: RefCell<Vec<Rect>>,
+    completion_rows: RefCell<Vec<Rect>>,
 }
 
 impl PromptInputView {
@@ -16,39 +27,109 @@ impl PromptInputView {
         PromptInputView {
             state: state,
             font: font,
+            rows: RefCell::new(Vec::new()),
+            completion_rows: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Render a completion, highlighting the chars which matched the input, followed by its
+    /// detail. If it's longer than `max_chars`, the start is elided so that the simple name stays
+    /// visible.
+    fn render_completion(
+        &self,
+        g: &mut RendererController,
+        theme: &Theme,
+        c: &Completion,
+        max_chars: usize,
+        pos: [f32; 2],
+    ) {
+        let detail = c.detail.as_ref().map(|d| format!("  {}", d));
+        let detail_chars = detail.as_ref().map(|d| d.chars().count()).unwrap_or(0);
+        let (text, positions) =
+            elide_left(&c.val, &c.positions, max_chars.saturating_sub(detail_chars));
+        // Render runs of matched & unmatched chars in turn
+        let mut x = pos[0];
+        let mut run_start = 0;
+        let mut run_matched = false;
+        for (ix, _) in text.char_indices().chain(Some((text.len(), ' '))) {
+            let matched = ix < text.len() && positions.contains(&ix);
+            if ix == text.len() || matched != run_matched {
+                if ix > run_start {
+                    let col = if run_matched {
+                        &theme.completion_match
+                    } else {
+                        &theme.completion_text
+                    };
+                    let (w, _h) = g.text(&text[run_start..ix], &[x, pos[1]], self.font, col);
+                    x += w;
+                }
+                run_start = ix;
+                run_matched = matched;
+            }
+        }
+        if let Some(ref detail) = detail {
+            g.text(detail, &[x, pos[1]], self.font, &theme.completion_detail);
         }
     }
 
-    /// # Params
-    /// * `display_size` - The size of the current display, so that the command buffer can be
-    /// rendered at the bottom of the screen.
-    pub fn render(&self, g: &mut RendererController, display_size: cgmath::Vector2<f32>) {
+    /// Render a line of input, with the cursor before the given byte if it's on this line
+    fn render_line(
+        &self,
+        g: &mut RendererController,
+        theme: &Theme,
+        line: &str,
+        cursor: Option<usize>,
+        pos: [f32; 2],
+    ) {
+        let cursor = match cursor {
+            Some(c) => c,
+            None => {
+                g.text(line, &pos, self.font, &theme.bar_text);
+                return;
+            }
+        };
+        let (w, _h) = g.text(&line[..cursor], &pos, self.font, &theme.bar_text);
+        g.rect(&[pos[0] + w, pos[1] - 12.0, 2.0, 16.0], &theme.bar_text);
+        g.text(&line[cursor..], &[pos[0] + w + 2.0, pos[1]], self.font, &theme.bar_text);
+    }
+}
+
+impl View for PromptInputView {
+    /// Renders the prompt into the given bar, with completions & earlier lines of input stacked
+    /// above it.
+    fn render(&self, g: &mut RendererController, bounds: Rect) {
+        let mut rows = self.rows.borrow_mut();
+        let mut completion_rows = self.completion_rows.borrow_mut();
+        rows.clear();
+        completion_rows.clear();
         let prompt = self.state.curr_prompt.lock().unwrap();
         if prompt.is_none() {
             return;
         }
         let prompt = prompt.as_ref().unwrap();
+        let theme = self.state.theme.lock().unwrap().theme().clone();
+        let bar_height = theme.bar_height;
 
-        const BG_COL: [f32; 4] = [0.1, 0.1, 0.4, 1.0];
-
-        g.rect(&[0.0, display_size.y - 24.0, display_size.x, 24.0], &BG_COL);
+        g.rect(
+            &[bounds.left(), bounds.top(), bounds.size.x, bar_height],
+            &theme.bar_bg,
+        );
+        rows.push(bounds);
 
         let mut pos = cgmath::Vector2 { x: 0.0, y: 0.0 };
-
-        const PROMPT_COL: [f32; 4] = [0.4, 0.4, 0.9, 0.4];
-        const ACTIVE_PROMPT_COL: [f32; 4] = [7.0, 7.0, 1.0, 1.0];
+        let char_width = char_width(g, self.font);
 
         for (ii, p) in prompt.prompts.iter().enumerate() {
             let col;
             if ii == prompt.get_curr_prompt() {
-                col = &ACTIVE_PROMPT_COL;
+                col = &theme.active_prompt_label;
             } else {
-                col = &PROMPT_COL;
+                col = &theme.prompt_label;
             }
 
             let (w, _h) = g.text(
                 p.as_str(),
-                &[8.0 + pos.x, display_size.y - 8.0],
+                &[bounds.left() + 8.0 + pos.x, bounds.bottom() - 8.0],
                 self.font,
                 col,
             );
@@ -59,57 +140,138 @@ impl PromptInputView {
         match prompt.get_active_completion() {
             Some(ix) => {
                 g.text(
-                    &prompt.get_completions()[ix],
-                    &[8.0 + pos.x, display_size.y - 8.0],
+                    &prompt.get_completions()[ix].val,
+                    &[bounds.left() + 8.0 + pos.x, bounds.bottom() - 8.0],
                     self.font,
-                    &[1.0, 1.0, 1.0, 1.0],
+                    &theme.bar_text,
                 );
             }
             _ => {
-                g.text(
-                    prompt.get_curr_input(),
-                    &[8.0 + pos.x, display_size.y - 8.0],
-                    self.font,
-                    &[1.0, 1.0, 1.0, 1.0],
-                );
+                let input = prompt.get_curr_input();
+                let cursor = prompt.get_curr_cursor();
+                // Render the last line on the prompt bar, and previous lines of a multi-line
+                // input above it
+                let mut line_end = input.len();
+                for (ii, l) in input.rsplit('\n').enumerate() {
+                    let line_start = line_end - l.len();
+                    if ii > 0 {
+                        pos.y += bar_height;
+                        g.rect(
+                            &[
+                                bounds.left() + pos.x,
+                                bounds.bottom() - bar_height - pos.y,
+                                bounds.size.x - pos.x,
+                                bar_height,
+                            ],
+                            &theme.bar_bg,
+                        );
+                        rows.push(Rect::new(
+                            bounds.left() + pos.x,
+                            bounds.bottom() - bar_height - pos.y,
+                            bounds.size.x - pos.x,
+                            bar_height,
+                        ));
+                    }
+                    let line_cursor = if cursor >= line_start && cursor <= line_end {
+                        Some(cursor - line_start)
+                    } else {
+                        None
+                    };
+                    self.render_line(
+                        g,
+                        &theme,
+                        l,
+                        line_cursor,
+                        [bounds.left() + 8.0 + pos.x, bounds.bottom() - 8.0 - pos.y],
+                    );
+                    line_end = line_start.saturating_sub(1);
+                }
             }
         }
 
-        const ACTIVE_COMPLETION_COL: [f32; 4] = [0.4, 0.4, 0.7, 1.0];
-
         // Render completions
         for (ii, c) in prompt.get_completions().iter().enumerate() {
             // Select colour
             let col = match prompt.get_active_completion() {
                 Some(ix) => {
                     if ix == ii {
-                        &ACTIVE_COMPLETION_COL
+                        &theme.active_completion
                     } else {
-                        &BG_COL
+                        &theme.bar_bg
                     }
                 }
-                _ => &BG_COL,
+                _ => &theme.bar_bg,
             };
 
             // Inc cursor
-            pos.y += 24.0;
+            pos.y += bar_height;
 
             // Render completion
             g.rect(
                 &[
-                    pos.x,
-                    display_size.y - 24.0 - pos.y,
-                    display_size.x - pos.x,
-                    24.0,
+                    bounds.left() + pos.x,
+                    bounds.bottom() - bar_height - pos.y,
+                    bounds.size.x - pos.x,
+                    bar_height,
                 ],
                 col,
             );
-            g.text(
-                &c,
-                &[8.0 + pos.x, display_size.y - 8.0 - pos.y],
-                self.font,
-                &[1.0, 1.0, 1.0, 1.0],
+            completion_rows.push(Rect::new(
+                bounds.left() + pos.x,
+                bounds.bottom() - bar_height - pos.y,
+                bounds.size.x - pos.x,
+                bar_height,
+            ));
+            let max_chars = ((bounds.size.x - pos.x - 16.0) / char_width).max(0.0) as usize;
+            self.render_completion(
+                g,
+                &theme,
+                c,
+                max_chars,
+                [bounds.left() + 8.0 + pos.x, bounds.bottom() - 8.0 - pos.y],
             );
         }
     }
+
+    /// Handle a mouse event. Clicking a completion picks it, and double clicking accepts it.
+    /// Returns false if the event wasn't over the prompt.
+    fn mouse_input(&self, ev: MouseEvent) -> bool {
+        let pos = ev.pos();
+        let ix = self.completion_rows.borrow().iter().position(|r| r.contains(pos));
+        match (ev, ix) {
+            (MouseEvent::Click(_), Some(ix)) => {
+                let mut curr_prompt = self.state.curr_prompt.lock().unwrap();
+                if let Some(ref mut p) = *curr_prompt {
+                    p.pick_completion(ix);
+                    p.update_completions(self.state.clone());
+                }
+                true
+            }
+            // The first click has already picked a completion, which may have changed the list
+            // under the mouse, so accept whatever it picked
+            (MouseEvent::DoubleClick(_), Some(_)) => {
+                let finished = {
+                    let mut curr_prompt = self.state.curr_prompt.lock().unwrap();
+                    let accepted = match *curr_prompt {
+                        Some(ref mut p) => p.accept_picked(),
+                        None => false,
+                    };
+                    if accepted {
+                        curr_prompt.take()
+                    } else {
+                        if let Some(ref mut p) = *curr_prompt {
+                            p.update_completions(self.state.clone());
+                        }
+                        None
+                    }
+                };
+                // Call the callback without holding the lock, so it can prompt again
+                if let Some(p) = finished {
+                    p.finish(self.state.clone());
+                }
+                true
+            }
+            _ => ix.is_some() || self.rows.borrow().iter().any(|r| r.contains(pos)),
+        }
+    }
 }